@@ -1,7 +1,12 @@
+use screen::blobs::{Blob, BlobRanking};
+use screen::minimap_dots::{MinimapDotKind, MinimapDots};
+use screen::motion::{estimate_motion, MinimapCrop, MinimapOdometer};
 use screen::{
-    action_text, fuzzy_pixels, ActionText, Capturer, Frame, FrameHandler, FuzzyPixel, Locations,
+    action_text, bank_slot_pixels, fuzzy_pixels, ActionText, BankSlot, Capturer, Frame,
+    FrameHandler, FuzzyPixel, Locations, Orb, PixelMatcher,
 };
-use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::thread::sleep;
 use std::time::Duration;
 use userinput::InputBot;
@@ -14,21 +19,28 @@ pub enum BankQuantity {
     Exact(i32),
 }
 
-fn check_map_pixels(
-    frame: &screen::DefaultFrame,
-    middle: Position,
-    min_radius: i32,
-    d_radius: i32,
-    arc_of_interest: (f32, f32),
-    primary_pixel: FuzzyPixel,
-    check_pixels: &[FuzzyPixel],
+/// The part of a map (minimap or worldmap) that check_map_pixels searches.
+pub(crate) struct MapSearch {
+    pub middle: Position,
+    pub min_radius: i32,
+    pub d_radius: i32,
+    /// (min_angle_degrees, d_angle_degrees)
+    pub arc_of_interest: (f32, f32),
+}
+
+pub(crate) fn check_map_pixels(
+    frame: &impl Frame,
+    search: &MapSearch,
+    primary_pixel: impl PixelMatcher,
+    check_pixels: &[impl PixelMatcher],
+    ignore_dots: Option<&MinimapDots>,
 ) -> Option<Position> {
     let map_iter = PositionIteratorCircularSpiral::new(
-        middle,
-        min_radius,
-        d_radius,
-        /*min_angle_degrees=*/ arc_of_interest.0,
-        /*d_angle_degrees=*/ arc_of_interest.1,
+        search.middle,
+        search.min_radius,
+        search.d_radius,
+        /*min_angle_degrees=*/ search.arc_of_interest.0,
+        /*d_angle_degrees=*/ search.arc_of_interest.1,
         /*spacing=*/ 1,
     );
 
@@ -36,6 +48,10 @@ fn check_map_pixels(
         if !primary_pixel.matches(&frame.get_pixel(&pos)) {
             continue;
         }
+        // Players, NPCs and items can share colors with map icons.
+        if ignore_dots.is_some_and(|dots| dots.is_on_dot(&pos)) {
+            continue;
+        }
 
         // Check that the found pixel is in the correct situation.
         let mut all_check_pixels_match = true;
@@ -97,8 +113,55 @@ pub enum AwaitCondition {
     // given.
     PixelMismatch(Position, FuzzyPixel),
     PixelMatch(Position, FuzzyPixel),
+    // Wait until the minimap stops scrolling. Takes at least one game tick to
+    // check.
+    PlayerStopped,
 }
 
+/// Distance on the minimap from the player in which other players count as
+/// competing with us, about 5 tiles.
+const CROWDING_RADIUS: i32 = 5 * Locations::MINIMAP_PIXELS_PER_TILE;
+
+/// Number of consecutive game ticks without the minimap moving before we
+/// decide clicking to walk isn't working.
+const MAX_STILL_CHECKS: i32 = 3;
+
+/// Smallest group of matching pixels OpenScreenAction treats as a target.
+/// Anything smaller is likely a stray pixel which happens to match.
+const MIN_TARGET_AREA: usize = 3;
+
+/// Targets within this many pixels of a failed click are treated as the
+/// same spot.
+const FAILED_CLICK_RADIUS: i32 = 20;
+
+/// A healthbar found this close to where our own player's healthbar is drawn
+/// is ours. The bar may be partly covered, so its start can shift a little.
+const PLAYER_HEALTHBAR_RADIUS: i32 = Locations::OVERHEAD_HEALTHBAR_WIDTH / 3;
+
+/// Number of targets OpenScreenAction hovers over looking for the right
+/// action text before giving up.
+const MAX_TARGET_ATTEMPTS: usize = 5;
+
+/// How far from where we expect the clicked target of a WatchTarget to be
+/// its colors may be found, if no blob covers that spot.
+const WATCH_TARGET_RADIUS: i32 = 100;
+
+/// Rough width of a game tile on the open screen at the default zoom, used to
+/// follow a clicked spot as the screen scrolls with the player.
+const OPEN_SCREEN_PIXELS_PER_TILE: i32 = 32;
+
+/// Number of game ticks the minimap must be trackable after a jump, without
+/// matching the minimap from before the jump, to count as a new floor.
+const FLOOR_CHANGE_CHECKS: i32 = 2;
+
+/// OpenObstacle is used when a route leg stalls, so the obstacle in the way
+/// is near the player. Anything further is likely another door on screen.
+const OBSTACLE_SEARCH_RADIUS: i32 = 150;
+
+/// Letters of a ground item label are separate blobs, a few pixels apart on
+/// the same line.
+const LABEL_LETTER_GAP: DeltaPosition = DeltaPosition { dx: 4, dy: 0 };
+
 fn is_condition_met(
     framehandler: &mut FrameHandler,
     capturer: &mut Capturer,
@@ -116,8 +179,21 @@ fn is_condition_met(
         AwaitCondition::InventoryContains(item) => framehandler
             .first_matching_inventory_slot(&frame, &item)
             .is_some(),
-        AwaitCondition::PixelMismatch(pos, pixel) => !pixel.matches(&frame.get_pixel(&pos)),
-        AwaitCondition::PixelMatch(pos, pixel) => pixel.matches(&frame.get_pixel(&pos)),
+        AwaitCondition::PixelMismatch(pos, pixel) => {
+            !pixel.matches(&framehandler.calibrated(&frame).get_pixel(&pos))
+        }
+        AwaitCondition::PixelMatch(pos, pixel) => {
+            pixel.matches(&framehandler.calibrated(&frame).get_pixel(&pos))
+        }
+        AwaitCondition::PlayerStopped => {
+            let minimap_middle = framehandler.locations.minimap_middle();
+            let before = MinimapCrop::capture(&frame, minimap_middle);
+            // The player only moves once per tick.
+            sleep(GAME_TICK);
+            let after = MinimapCrop::capture(&capturer.frame().unwrap(), minimap_middle);
+            let motion = estimate_motion(&before, &after);
+            motion.is_confident() && motion.distance_tiles() == 0.0
+        }
     }
 }
 
@@ -148,6 +224,18 @@ pub trait Action {
         framehandler: &mut FrameHandler,
         capturer: &mut Capturer,
     ) -> bool;
+
+    /// Called when do_action succeeded but didn't achieve anything, e.g. we
+    /// clicked on a rock but no ore showed up in the inventory. Actions which
+    /// pick between targets can use this to try a different one next time.
+    fn report_failure(&self) {}
+
+    /// What to watch on screen after do_action to tell that the resource we
+    /// clicked on has run out, e.g. someone else mined our rock. None if this
+    /// action doesn't click on a resource which can be used up.
+    fn watch_target(&self) -> Option<WatchTarget> {
+        None
+    }
 }
 
 /// Click on the location for platelegs in the smithing menu.
@@ -160,6 +248,27 @@ pub struct PressMinimapMiddle {}
 /// orientation.
 pub struct PressCompass {}
 
+/// Turn the camera to face 'yaw_degrees' (clockwise from north), reading
+/// the compass to check how far we have turned. Calibrates the compass first
+/// if needed, which leaves the camera facing north.
+pub struct RotateCameraTo {
+    pub yaw_degrees: f32,
+    pub tolerance_degrees: f32,
+    pub timeout: Duration,
+}
+
+/// Put the camera into a known pitch and zoom so that searching the open
+/// screen behaves the same every time. Does this by moving to the end of each
+/// range and then back by a known amount.
+pub struct SetCamera {
+    /// Fraction of the pitch range, 0.0 is looking towards the horizon and
+    /// 1.0 is looking straight down.
+    pub pitch: f32,
+
+    /// Number of scroll wheel steps zoomed in from fully zoomed out.
+    pub zoom: i32,
+}
+
 /// This assumes that the bank is closed.
 pub struct OpenInventory {}
 
@@ -174,6 +283,10 @@ pub struct ClickKey {
 pub struct MaybeToggleRunning {
     // If true, will attempt to set the player to run.
     pub try_to_run: bool,
+
+    // Only turn on running if the run energy orb reads at least this much.
+    // If we can't read the orb we assume there is enough energy.
+    pub min_run_energy: i32,
 }
 
 /// Make sure the worldmap is open/closed.
@@ -217,9 +330,11 @@ pub struct TravelToOnMinimap {
 /// These colors are expected to match those of the worldmap (not there will be
 /// some differences since the minimap changes shading throughout the day).
 ///
-/// We assume the player is always at the center of the worldmap, and when we
-/// find the destination we use the minimap to walk in that direction. We don't
-/// search adjacent to the player so as not ot repeat the minimap search.
+/// We assume the player is at the center of the worldmap when it is opened,
+/// and when we find the destination we use the minimap to walk in that
+/// direction. We don't search adjacent to the player so as not ot repeat the
+/// minimap search. If the destination isn't in view we pan the worldmap to
+/// search around it.
 pub struct TravelTowardsOnWorldmap {
     /// See fields in TravelToOnMinimap.
     pub primary_pixel: FuzzyPixel,
@@ -227,8 +342,28 @@ pub struct TravelTowardsOnWorldmap {
     pub check_pixels: Vec<FuzzyPixel>,
 
     pub arc_of_interest: (f32, f32),
+
+    /// Number of rings of views around the player to search by panning the
+    /// worldmap. 0 only searches the view shown when the worldmap opens.
+    /// Views outside of 'arc_of_interest' are skipped.
+    pub search_rings: i32,
+}
+
+/// Zoom the worldmap in (positive) or out (negative) by scrolling over it.
+/// Assumes the worldmap is open.
+pub struct ZoomWorldmap {
+    pub steps: i32,
 }
 
+/// Drag the worldmap so that what is shown moves by 'offset'. Assumes the
+/// worldmap is open.
+pub struct PanWorldmap {
+    pub offset: DeltaPosition,
+}
+
+/// Center the worldmap back on the player, by closing and reopening it.
+pub struct RecenterWorldmap {}
+
 /// Interact with an item in the inventory based on its appearance.
 ///
 /// We will move the mouse to hover over it, and click according to the config.
@@ -263,11 +398,36 @@ pub struct SetBankQuantity {
     pub quantity: BankQuantity,
 }
 
+/// Click on 'slot' in the bank. Assumes the bank is open and ShowBankSlot
+/// has been done for the slot, unless it is in the main tab and visible
+/// without scrolling.
 pub struct ClickBankSlot {
-    pub slot_index: i32,
+    pub slot: BankSlot,
     pub mouse_click: MouseClick,
 }
 
+/// Open a tab in the bank. Tab 0 is the main tab. Assumes the bank is open.
+pub struct SelectBankTab {
+    pub tab: i32,
+}
+
+/// Scroll the bank down (positive) or up (negative) by 'rows' with the
+/// scroll wheel, checking how far it moved on the scrollbar. Fails if the
+/// bank won't go that far, e.g. past the bottom. Assumes the bank is open.
+pub struct ScrollBank {
+    pub rows: i32,
+}
+
+/// Scroll the bank all the way up by dragging the scrollbar. Unlike
+/// ScrollBank this doesn't need to know how far down the bank is.
+pub struct ScrollBankToTop {}
+
+/// Open the tab of 'slot' and scroll the bank so that it is visible, in
+/// the position ClickBankSlot expects. Assumes the bank is open.
+pub struct ShowBankSlot {
+    pub slot: BankSlot,
+}
+
 pub struct DepositEntireInventoryToBank {
     pub open_bank_action: OpenBank,
 }
@@ -431,6 +591,82 @@ impl Action for PressCompass {
     }
 }
 
+impl RotateCameraTo {
+    pub fn new(yaw_degrees: f32) -> RotateCameraTo {
+        RotateCameraTo {
+            yaw_degrees,
+            tolerance_degrees: 5.0,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Action for RotateCameraTo {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("RotateCameraTo [ yaw_degrees: {}]", self.yaw_degrees);
+        if framehandler.compass_template.is_none() {
+            PressCompass {}.do_action(inputbot, framehandler, capturer);
+            // Get the mouse off of the compass so it isn't highlighted, and
+            // let the camera finish turning north.
+            inputbot.move_near(&framehandler.locations.minimap_middle());
+            sleep(Duration::from_secs(2));
+            framehandler.calibrate_compass(&capturer.frame().unwrap());
+        }
+
+        let time = std::time::Instant::now();
+        while time.elapsed() < self.timeout {
+            let yaw = framehandler
+                .camera_yaw_degrees(&capturer.frame().unwrap())
+                .unwrap();
+            let diff = screen::compass::yaw_difference(yaw, self.yaw_degrees);
+            if diff.abs() <= self.tolerance_degrees {
+                return true;
+            }
+
+            // Panning is timed so it won't be exact, we correct on the next
+            // loop.
+            if diff > 0.0 {
+                inputbot.pan_right(diff);
+            } else {
+                inputbot.pan_left(-diff);
+            }
+            sleep(util::REDRAW_TIME);
+        }
+
+        false
+    }
+}
+
+impl Action for SetCamera {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        _capturer: &mut Capturer,
+    ) -> bool {
+        println!("SetCamera [ pitch: {}, zoom: {}]", self.pitch, self.zoom);
+        assert!((0.0..=1.0).contains(&self.pitch));
+        assert!((0..=userinput::MAX_ZOOM_STEPS).contains(&self.zoom));
+
+        inputbot.pitch_up(userinput::FULL_PITCH_TIME);
+        inputbot.pitch_down(userinput::FULL_PITCH_TIME.mul_f32(1.0 - self.pitch));
+
+        // Scrolling only zooms when the mouse is over the open screen.
+        inputbot.move_to(&util::random_position_polar(
+            framehandler.locations.mid_screen(),
+            50,
+        ));
+        inputbot.zoom_out(userinput::MAX_ZOOM_STEPS);
+        inputbot.zoom_in(self.zoom);
+        true
+    }
+}
+
 impl Action for OpenInventory {
     fn do_action(
         &self,
@@ -523,10 +759,24 @@ impl Action for CloseChatbox {
 
 impl MaybeToggleRunning {
     pub fn run() -> MaybeToggleRunning {
-        MaybeToggleRunning { try_to_run: true }
+        MaybeToggleRunning {
+            try_to_run: true,
+            min_run_energy: 0,
+        }
     }
     pub fn walk() -> MaybeToggleRunning {
-        MaybeToggleRunning { try_to_run: false }
+        MaybeToggleRunning {
+            try_to_run: false,
+            min_run_energy: 0,
+        }
+    }
+    /// Run, but only if we have at least 'min_run_energy'. Otherwise leave
+    /// running as it is, the game will switch us to walking once we run out.
+    pub fn run_above(min_run_energy: i32) -> MaybeToggleRunning {
+        MaybeToggleRunning {
+            try_to_run: true,
+            min_run_energy,
+        }
     }
 }
 
@@ -566,10 +816,21 @@ impl Action for MaybeToggleRunning {
 
         let frame = capturer.frame().unwrap();
         let pos = framehandler.locations.run_icon();
-        let is_run_on = frame.check_loose_pixel(&pos, &fuzzy_pixels::run_icon_on());
+        let is_run_on = framehandler
+            .calibrated(&frame)
+            .check_loose_pixel(&pos, &fuzzy_pixels::run_icon_on());
         if is_run_on == self.try_to_run {
             return true;
         }
+        if self.try_to_run {
+            match framehandler.orb_value(&frame, Orb::Run) {
+                Some(run_energy) if run_energy < self.min_run_energy => {
+                    println!("Not enough run energy: {}", run_energy);
+                    return true;
+                }
+                _ => (),
+            }
+        }
 
         inputbot.move_to(&util::random_position_polar(pos, 4));
         inputbot.left_click();
@@ -641,6 +902,30 @@ impl Action for MaybeToggleWorldmap {
     }
 }
 
+impl TravelToOnMinimap {
+    fn find_destination(
+        &self,
+        framehandler: &FrameHandler,
+        capturer: &mut Capturer,
+        d_radius: i32,
+        arc_of_interest: (f32, f32),
+    ) -> Option<Position> {
+        let frame = capturer.frame().unwrap();
+        check_map_pixels(
+            &framehandler.calibrated(&frame),
+            &MapSearch {
+                middle: framehandler.locations.minimap_middle(),
+                min_radius: 1,
+                d_radius,
+                arc_of_interest,
+            },
+            self.primary_pixel,
+            &self.check_pixels,
+            Some(&framehandler.find_minimap_dots(&frame)),
+        )
+    }
+}
+
 impl Action for TravelToOnMinimap {
     fn do_action(
         &self,
@@ -653,14 +938,11 @@ impl Action for TravelToOnMinimap {
         // Try twice. We may find the destination but hit something in the way.
         for _ in 0..2 {
             // Find the destination on the minimap.
-            match check_map_pixels(
-                &capturer.frame().unwrap(),
-                framehandler.locations.minimap_middle(),
-                /*min_radius=*/ 1,
-                /*d_radius=*/ Locations::MINIMAP_RADIUS,
+            match self.find_destination(
+                framehandler,
+                capturer,
+                Locations::MINIMAP_RADIUS,
                 self.arc_of_interest,
-                self.primary_pixel,
-                &self.check_pixels,
             ) {
                 None => return false, // Failed to find the dst.
                 Some(pos) => inputbot.move_to(&pos),
@@ -671,14 +953,11 @@ impl Action for TravelToOnMinimap {
             // slowest part, the mouse location may now be incorrect since
             // we kept moving. Move the mouse again to be closer, this
             // should be fast since we are already very close.
-            match check_map_pixels(
-                &capturer.frame().unwrap(),
-                framehandler.locations.minimap_middle(),
-                /*min_radius=*/ 1,
-                /*d_radius=*/ Locations::MINIMAP_RADIUS,
+            match self.find_destination(
+                framehandler,
+                capturer,
+                Locations::MINIMAP_RADIUS,
                 self.arc_of_interest,
-                self.primary_pixel,
-                &self.check_pixels,
             ) {
                 None => return false, // Failed to find the dst.
                 Some(pos) => inputbot.move_to(&pos),
@@ -690,14 +969,11 @@ impl Action for TravelToOnMinimap {
             // Wait until we are nearby or timeout.
             let time = std::time::Instant::now();
             while time.elapsed() < Duration::from_secs(15) {
-                match check_map_pixels(
-                    &capturer.frame().unwrap(),
-                    framehandler.locations.minimap_middle(),
-                    /*min_radius=*/ 1,
-                    /*d_radius=*/ Locations::MINIMAP_SMALL_RADIUS,
+                match self.find_destination(
+                    framehandler,
+                    capturer,
+                    Locations::MINIMAP_SMALL_RADIUS,
                     /*arc_of_interest=*/ (0.0, 360.0),
-                    self.primary_pixel,
-                    &self.check_pixels,
                 ) {
                     None => (),
                     Some(_) => return true,
@@ -711,6 +987,52 @@ impl Action for TravelToOnMinimap {
     }
 }
 
+impl TravelTowardsOnWorldmap {
+    /// Offsets of the views to search, in units of views, from the one centered
+    /// on the player outwards.
+    fn search_views(&self) -> Vec<DeltaPosition> {
+        let mut views = vec![DeltaPosition { dx: 0, dy: 0 }];
+        for ring in 1..=self.search_rings {
+            for j in -ring..=ring {
+                for i in -ring..=ring {
+                    if i.abs() != ring && j.abs() != ring {
+                        continue;
+                    }
+                    let view = DeltaPosition { dx: i, dy: j };
+                    let angle_degrees = view.angle_rads().to_degrees();
+                    let (min_angle_degrees, arc_angle_degrees) = self.arc_of_interest;
+                    if (angle_degrees - min_angle_degrees).rem_euclid(360.0) <= arc_angle_degrees {
+                        views.push(view);
+                    }
+                }
+            }
+        }
+        views
+    }
+
+    fn find_destination(
+        &self,
+        framehandler: &FrameHandler,
+        capturer: &mut Capturer,
+        min_radius: i32,
+        arc_of_interest: (f32, f32),
+    ) -> Option<Position> {
+        let DeltaPosition { dx, dy } = framehandler.locations.worldmap_map_dimensions();
+        check_map_pixels(
+            &framehandler.calibrated(&capturer.frame().unwrap()),
+            &MapSearch {
+                middle: framehandler.locations.worldmap_map_middle(),
+                min_radius,
+                d_radius: std::cmp::min(dx, dy) / 2 - min_radius - 1,
+                arc_of_interest,
+            },
+            self.primary_pixel,
+            &self.check_pixels,
+            /*ignore_dots=*/ None,
+        )
+    }
+}
+
 impl Action for TravelTowardsOnWorldmap {
     fn do_action(
         &self,
@@ -719,20 +1041,49 @@ impl Action for TravelTowardsOnWorldmap {
         capturer: &mut Capturer,
     ) -> bool {
         println!("TravelTowardsOnWorldmap");
-        let frame = &capturer.frame().unwrap();
 
-        // Find the destination on the worldmap.
+        // Find the destination on the worldmap. 'pan' is how far we have
+        // dragged the worldmap, which is also where the player now is relative
+        // to the middle of the worldmap.
         let DeltaPosition { dx, dy } = framehandler.locations.worldmap_map_dimensions();
-        let min_radius = 30;
-        let worldmap_pos = match check_map_pixels(
-            &frame,
-            framehandler.locations.worldmap_map_middle(),
-            min_radius,
-            /*d_radius=*/ std::cmp::min(dx, dy) / 2 - min_radius - 1,
-            self.arc_of_interest,
-            self.primary_pixel,
-            &self.check_pixels,
-        ) {
+        let mut pan = DeltaPosition { dx: 0, dy: 0 };
+        let mut destination = None;
+        for view in self.search_views() {
+            // Overlap the views a bit so icons on the edge aren't missed.
+            let view_pan = DeltaPosition {
+                dx: -view.dx * dx * 3 / 4,
+                dy: -view.dy * dy * 3 / 4,
+            };
+            if view_pan != pan {
+                PanWorldmap {
+                    offset: view_pan - pan,
+                }
+                .do_action(inputbot, framehandler, capturer);
+                pan = view_pan;
+            }
+
+            // Only the view around the player needs to skip the area the
+            // minimap already searched.
+            let is_first_view = view == DeltaPosition { dx: 0, dy: 0 };
+            destination = self.find_destination(
+                framehandler,
+                capturer,
+                /*min_radius=*/ if is_first_view { 30 } else { 1 },
+                if is_first_view {
+                    self.arc_of_interest
+                } else {
+                    (0.0, 360.0)
+                },
+            );
+            if destination.is_some() {
+                break;
+            }
+        }
+
+        if pan != (DeltaPosition { dx: 0, dy: 0 }) {
+            RecenterWorldmap {}.do_action(inputbot, framehandler, capturer);
+        }
+        let worldmap_pos = match destination {
             None => return false, // Failed to find the dst.
             Some(pos) => pos,
         };
@@ -740,7 +1091,8 @@ impl Action for TravelTowardsOnWorldmap {
         // Now that we have found the destination on the worldmap, we need to
         // translate this to a location on the minimap to press to walk in that
         // direction.
-        let angle_rads = (worldmap_pos - framehandler.locations.worldmap_map_middle()).angle_rads();
+        let player_pos = framehandler.locations.worldmap_map_middle() + pan;
+        let angle_rads = (worldmap_pos - player_pos).angle_rads();
         let minimap_pos = polar_to_cartesian(
             framehandler.locations.minimap_middle(),
             Locations::MINIMAP_RADIUS - 3,
@@ -749,15 +1101,86 @@ impl Action for TravelTowardsOnWorldmap {
         inputbot.move_to(&minimap_pos);
         inputbot.left_click();
 
-        let running = frame.check_loose_pixel(
-            &framehandler.locations.run_icon(),
-            &fuzzy_pixels::run_icon_on(),
-        );
+        let running = framehandler
+            .calibrated(&capturer.frame().unwrap())
+            .check_loose_pixel(
+                &framehandler.locations.run_icon(),
+                &fuzzy_pixels::run_icon_on(),
+            );
         sleep(Duration::from_secs(if running { 3 } else { 6 }));
         true
     }
 }
 
+impl Action for ZoomWorldmap {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        _capturer: &mut Capturer,
+    ) -> bool {
+        println!("ZoomWorldmap [ steps: {}]", self.steps);
+        inputbot.move_to(&util::random_position_polar(
+            framehandler.locations.worldmap_map_middle(),
+            20,
+        ));
+        if self.steps > 0 {
+            inputbot.zoom_in(self.steps);
+        } else {
+            inputbot.zoom_out(-self.steps);
+        }
+        sleep(util::REDRAW_TIME);
+        true
+    }
+}
+
+impl Action for PanWorldmap {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        _capturer: &mut Capturer,
+    ) -> bool {
+        println!("PanWorldmap [ offset: {:?}]", self.offset);
+        let middle = framehandler.locations.worldmap_map_middle();
+        let DeltaPosition { dx, dy } = framehandler.locations.worldmap_map_dimensions();
+        // Keep both ends of each drag well inside the map.
+        let max_drag = DeltaPosition {
+            dx: dx / 2 - 20,
+            dy: dy / 2 - 20,
+        };
+
+        let mut remaining = self.offset;
+        while remaining != (DeltaPosition { dx: 0, dy: 0 }) {
+            let drag = DeltaPosition {
+                dx: remaining.dx.clamp(-max_drag.dx, max_drag.dx),
+                dy: remaining.dy.clamp(-max_drag.dy, max_drag.dy),
+            };
+            let from = Position {
+                x: middle.x - drag.dx / 2,
+                y: middle.y - drag.dy / 2,
+            };
+            inputbot.left_drag(&from, &(from + drag));
+            remaining = remaining - drag;
+        }
+        sleep(util::REDRAW_TIME);
+        true
+    }
+}
+
+impl Action for RecenterWorldmap {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("RecenterWorldmap");
+        MaybeToggleWorldmap::close_worldmap().do_action(inputbot, framehandler, capturer)
+            && MaybeToggleWorldmap::open_worldmap().do_action(inputbot, framehandler, capturer)
+    }
+}
+
 impl InventorySlotAction {
     pub fn new(item: screen::InventorySlotPixels) -> InventorySlotAction {
         InventorySlotAction {
@@ -885,10 +1308,10 @@ impl Action for ClickBankSlot {
         framehandler: &mut FrameHandler,
         _capturer: &mut Capturer,
     ) -> bool {
-        println!("ClickBankSlot");
+        println!("ClickBankSlot [ slot: {:?}]", self.slot);
 
         inputbot.move_to(&util::random_position_polar(
-            framehandler.locations.bank_slot_center(self.slot_index),
+            framehandler.locations.scrolled_bank_slot_center(&self.slot),
             10,
         ));
         click_mouse(inputbot, self.mouse_click);
@@ -897,6 +1320,130 @@ impl Action for ClickBankSlot {
     }
 }
 
+impl Action for SelectBankTab {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        _capturer: &mut Capturer,
+    ) -> bool {
+        println!("SelectBankTab [ tab: {}]", self.tab);
+        inputbot.move_to(&util::random_position_polar(
+            framehandler.locations.bank_tab_center(self.tab),
+            /*radius=*/ 8,
+        ));
+        inputbot.left_click();
+        sleep(util::REDRAW_TIME);
+        true
+    }
+}
+
+impl Action for ScrollBank {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("ScrollBank [ rows: {}]", self.rows);
+        let mut rows_scrolled = match framehandler.bank_rows_scrolled(&capturer.frame().unwrap()) {
+            Some(rows) => rows,
+            None => {
+                println!("Unable to find the bank scrollbar");
+                return false;
+            }
+        };
+        let target = rows_scrolled + self.rows;
+
+        // The bank only scrolls while the mouse is over the slots.
+        inputbot.move_to(&util::random_position_polar(
+            framehandler
+                .locations
+                .bank_slot_center(Locations::NUM_BANK_COLUMNS / 2),
+            /*radius=*/ 10,
+        ));
+        // How far a step of the scroll wheel moves the bank depends on the
+        // client, so step one at a time until the scrollbar shows we're there.
+        let max_steps = 3 * self.rows.abs() + 3;
+        for _ in 0..max_steps {
+            if rows_scrolled == target {
+                return true;
+            }
+            if rows_scrolled < target {
+                inputbot.scroll_down(1);
+            } else {
+                inputbot.scroll_up(1);
+            }
+            sleep(util::REDRAW_TIME);
+            match framehandler.bank_rows_scrolled(&capturer.frame().unwrap()) {
+                Some(rows) => rows_scrolled = rows,
+                None => {
+                    println!("Lost the bank scrollbar while scrolling");
+                    return false;
+                }
+            }
+        }
+        if rows_scrolled != target {
+            println!(
+                "Bank stopped {} rows down instead of {}",
+                rows_scrolled, target
+            );
+            return false;
+        }
+        true
+    }
+}
+
+impl Action for ScrollBankToTop {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        _capturer: &mut Capturer,
+    ) -> bool {
+        println!("ScrollBankToTop");
+        // Grabbing the bottom of the scrollbar either grabs the thumb or
+        // jumps it there. Either way dragging to the top of the scrollbar
+        // brings the thumb with it.
+        let top = framehandler.locations.bank_scrollbar_top();
+        let bottom = framehandler.locations.bank_scrollbar_bottom();
+        inputbot.left_drag(
+            &Position {
+                x: bottom.x,
+                y: bottom.y - 2,
+            },
+            &Position {
+                x: top.x,
+                y: top.y - Locations::BANK_BORDER_WIDTH,
+            },
+        );
+        sleep(util::REDRAW_TIME);
+        true
+    }
+}
+
+impl Action for ShowBankSlot {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("ShowBankSlot [ slot: {:?}]", self.slot);
+        let mut actions: Vec<Box<dyn Action>> = vec![
+            Box::new(SelectBankTab { tab: self.slot.tab }),
+            Box::new(ScrollBankToTop {}),
+        ];
+        let rows = framehandler.locations.bank_rows_to_scroll(&self.slot);
+        if rows > 0 {
+            actions.push(Box::new(ScrollBank { rows }));
+        }
+        actions
+            .iter()
+            .all(|action| action.do_action(inputbot, framehandler, capturer))
+    }
+}
+
 impl DepositEntireInventoryToBank {
     pub fn new(bank_pixels: Vec<FuzzyPixel>) -> DepositEntireInventoryToBank {
         DepositEntireInventoryToBank {
@@ -1009,7 +1556,7 @@ impl Action for CloseBank {
 /// since that will find a destination on the map and is better at correcting
 /// for errors.
 ///
-/// Cannot fail.
+/// Cannot fail unless fail_when_stuck is set.
 pub struct TravelStraight {
     /// Direction that the player should move in in degrees.
     ///
@@ -1023,10 +1570,30 @@ pub struct TravelStraight {
     /// will not enforce stopping so it is possible for the player to keep
     /// walking until the last spot clicked on the minimap after this returns.
     pub travel_time: Duration,
+
+    /// Fail if the minimap shows that we stopped moving, e.g. because a wall
+    /// is in the way.
+    pub fail_when_stuck: bool,
 }
 
-/// Combines usage of the minimap and worldmap to make the player run/walk to a
-/// destination.
+/// Walk a number of game tiles from where the player is by clicking on the
+/// minimap. Only works for destinations shown on the minimap.
+///
+/// We can't tell exactly where the player is, so arrival is detected by the
+/// flag at the destination disappearing, or failing that the minimap no
+/// longer moving after having moved. If we never see the flag or the minimap
+/// move, the click didn't take us anywhere and we fail.
+pub struct WalkTiles {
+    /// Tiles to walk east, negative for west.
+    pub dx: i32,
+    /// Tiles to walk north, negative for south.
+    pub dy: i32,
+
+    pub timeout: Duration,
+}
+
+/// Combines usage of the minimap and worldmap to make the player run/walk to a
+/// destination.
 pub struct TravelTo {
     pub travel_minimap: TravelToOnMinimap,
     pub travel_worldmap: TravelTowardsOnWorldmap,
@@ -1049,6 +1616,152 @@ pub struct OpenScreenAction {
 
     /// How to press the mouse.
     pub mouse_click: MouseClick,
+
+    /// Ignore pixels that sit just below an overhead healthbar. Used when
+    /// attacking so that we don't click on NPCs someone else is fighting.
+    pub skip_npcs_in_combat: bool,
+
+    /// How long to avoid a spot after clicking it produced nothing, e.g. a
+    /// depleted rock or a fishing spot that moved.
+    pub failure_cooldown: Duration,
+
+    /// Where we clicked and which of those clicks failed.
+    pub click_history: ClickHistory,
+
+    /// The target is a resource which disappears once used up, like ore in
+    /// a rock or a tree which gets cut down. See WatchTarget.
+    pub watch_for_depletion: bool,
+
+    /// Only consider targets within this many pixels of the middle of the
+    /// screen. None searches the whole open screen.
+    pub search_radius: Option<i32>,
+}
+
+/// A resource on the open screen which can run out. Once the player has
+/// walked up to it, we lock on to the target at the spot we clicked, moved by
+/// how far the player walked, and consider the resource gone once its colors
+/// do.
+pub struct WatchTarget {
+    pub expected_pixels: Vec<FuzzyPixel>,
+
+    /// Where we clicked on the resource.
+    pub clicked: Position,
+
+    /// The minimap when we clicked, to measure how far we walked since.
+    pub clicked_minimap: MinimapCrop,
+}
+
+/// Remembers the last position an action clicked and the positions where
+/// clicking didn't get us anything.
+///
+/// Positions are on the screen, so they only mean anything from where we
+/// stood when clicking. Each click keeps the minimap as it was, and failures
+/// are forgotten once the minimap shows that we walked or the camera turned.
+/// Changing the camera's pitch or zoom doesn't show on the minimap.
+#[derive(Default)]
+pub struct ClickHistory {
+    last_click: RefCell<Option<(Position, MinimapCrop)>>,
+    failures: RefCell<Vec<(Position, MinimapCrop, std::time::Instant)>>,
+}
+
+/// An item we are willing to pick up off the ground.
+pub struct LootItem {
+    /// Pixels identifying the item on the ground. Either the item's own
+    /// colors or the color of the label RuneLite draws above it.
+    pub expected_pixels: Vec<FuzzyPixel>,
+
+    /// How far from the middle of the matching pixels to hover to reach the
+    /// item.
+    pub hover_offset: DeltaPosition,
+
+    /// Confirms "Take <item>" before clicking.
+    pub check_action_text: CheckActionText,
+
+    /// Used to confirm the item arrived in the inventory. If None we instead
+    /// wait for an open slot to be filled, which won't work for stackable
+    /// items we already have.
+    pub inventory_item: Option<screen::InventorySlotPixels>,
+}
+
+/// Pick up items near the player, such as drops after a kill. Only items on
+/// the allow list, 'items', are picked up.
+///
+/// Returns true if we picked up at least one item.
+pub struct Loot {
+    pub items: Vec<LootItem>,
+
+    /// Distance from the middle of the screen to search for items.
+    pub search_radius: i32,
+
+    /// How long to wait for an item to show up in the inventory after
+    /// clicking it, which includes walking to it.
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObstacleKind {
+    Door,
+    Gate,
+}
+
+/// Something which can block the way until opened.
+#[derive(Clone, Debug)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+
+    /// Colors of the obstacle when closed.
+    pub expected_pixels: Vec<FuzzyPixel>,
+}
+
+/// Open a door or gate near the player. Succeeds once the obstacle no longer
+/// offers to be opened.
+///
+/// Fails if no closed obstacle is found, which includes when it is already
+/// open.
+pub struct OpenObstacle {
+    pub obstacle: Obstacle,
+
+    /// How long to wait for the obstacle to open after clicking it, which
+    /// includes walking to it.
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClimbableKind {
+    Ladder,
+    Staircase,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloorDirection {
+    Up,
+    Down,
+}
+
+/// Something which takes us to another floor.
+#[derive(Clone, Debug)]
+pub struct Climbable {
+    pub kind: ClimbableKind,
+    pub expected_pixels: Vec<FuzzyPixel>,
+}
+
+/// Click on an entry in the right click menu of whatever the mouse is over.
+/// Closes the menu if the entry isn't found.
+pub struct SelectMenuEntry {
+    pub action_text: ActionText,
+}
+
+/// Climb a ladder or staircase near the player. If climbing in 'direction'
+/// isn't the default action, it is picked from the right click menu.
+///
+/// Succeeds once the minimap jumps to a new floor.
+pub struct ChangeFloor {
+    pub climbable: Climbable,
+    pub direction: FloorDirection,
+
+    /// How long to wait for the floor to change after clicking, which
+    /// includes walking to the ladder or staircase.
+    pub timeout: Duration,
 }
 
 /// Assumes we are near the bank and there is nothing in the way like a
@@ -1104,24 +1817,176 @@ impl Action for TravelStraight {
         // in a straight line.
         let minimap_pos = self.get_minimap_pos(framehandler);
 
-        // Continually press on this spot until we are done.
+        // Continually press on this spot until we are done. In between, watch
+        // the minimap to measure how far we go and notice if we are stuck.
+        let minimap_middle = framehandler.locations.minimap_middle();
+        let mut odometer = MinimapOdometer::new();
+        let mut num_still_checks = 0;
+        let mut last_click: Option<std::time::Instant> = None;
         let time = std::time::Instant::now();
         while time.elapsed() < self.travel_time {
-            let wait_time = std::cmp::min(
+            if last_click.is_none_or(|click| click.elapsed() >= Duration::from_secs(8)) {
+                inputbot.move_to(&minimap_pos);
+                inputbot.left_click();
+                last_click = Some(std::time::Instant::now());
+            }
+            sleep(std::cmp::min(
                 self.travel_time
                     .checked_sub(time.elapsed())
                     .unwrap_or(Duration::from_nanos(1)),
-                Duration::from_secs(8),
-            );
-            inputbot.move_to(&minimap_pos);
-            inputbot.left_click();
-            sleep(wait_time);
+                GAME_TICK,
+            ));
+
+            match odometer.update(&capturer.frame().unwrap(), minimap_middle) {
+                Some(motion) if motion.is_confident() && motion.distance_tiles() == 0.0 => {
+                    num_still_checks += 1
+                }
+                _ => num_still_checks = 0,
+            }
+            if self.fail_when_stuck && num_still_checks >= MAX_STILL_CHECKS {
+                println!(
+                    "TravelStraight stuck after {:.1} tiles",
+                    odometer.distance_tiles
+                );
+                return false;
+            }
         }
 
+        println!(
+            "TravelStraight travelled {:.1} tiles",
+            odometer.distance_tiles
+        );
         true
     }
 }
 
+impl WalkTiles {
+    pub fn new(dx: i32, dy: i32) -> WalkTiles {
+        WalkTiles {
+            dx,
+            dy,
+            timeout: Duration::from_secs(20),
+        }
+    }
+
+    /// Look for the destination flag within a few pixels of where we clicked.
+    fn is_flag_shown(frame: &impl Frame, target: Position) -> bool {
+        let flag_radius = 4;
+        (-flag_radius..=flag_radius).any(|dy| {
+            (-flag_radius..=flag_radius).any(|dx| {
+                frame.check_loose_pixel(
+                    &(target + DeltaPosition { dx, dy }),
+                    &fuzzy_pixels::minimap_flag_red(),
+                )
+            })
+        })
+    }
+
+    /// Sample the minimap sparsely, skipping the middle where the player's
+    /// dot is always drawn.
+    fn sample_minimap(frame: &impl Frame, minimap_middle: Position) -> Vec<screen::Pixel> {
+        let radius = Locations::MINIMAP_RADIUS - 10;
+        let mut samples = Vec::new();
+        for dy in (-radius..=radius).step_by(6) {
+            for dx in (-radius..=radius).step_by(6) {
+                let offset = DeltaPosition { dx, dy };
+                let distance = offset.distance();
+                if distance > radius || distance < 4 {
+                    continue;
+                }
+                samples.push(frame.get_pixel(&(minimap_middle + offset)));
+            }
+        }
+        samples
+    }
+
+    /// The minimap scrolls while the player moves, so a meaningful share of
+    /// the samples change color.
+    fn is_minimap_moving(before: &[screen::Pixel], after: &[screen::Pixel]) -> bool {
+        let changed = before
+            .iter()
+            .zip(after.iter())
+            .filter(|(a, b)| {
+                let diff = |x: u8, y: u8| (x as i32 - y as i32).abs() > 10;
+                diff(a.blue, b.blue) || diff(a.green, b.green) || diff(a.red, b.red)
+            })
+            .count();
+        changed * 10 > before.len()
+    }
+}
+
+impl Action for WalkTiles {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("WalkTiles [ dx: {}, dy: {}]", self.dx, self.dy);
+        let minimap_middle = framehandler.locations.minimap_middle();
+        let frame = capturer.frame().unwrap();
+
+        // If the compass hasn't been calibrated assume we are facing north.
+        let yaw_degrees = framehandler.camera_yaw_degrees(&frame).unwrap_or(0.0);
+        let offset = Locations::tiles_to_minimap_offset(
+            DeltaPosition {
+                dx: self.dx,
+                dy: self.dy,
+            },
+            yaw_degrees,
+        );
+        // Don't go to the edge of the minimap since the worldmap and orbs jut
+        // in so we wouldn't move.
+        if offset.distance() > Locations::MINIMAP_RADIUS - 7 {
+            println!("Destination is off the minimap");
+            return false;
+        }
+
+        let target = minimap_middle + offset;
+        inputbot.move_to(&target);
+        inputbot.left_click();
+
+        let mut flag_seen = false;
+        let mut moved = false;
+        let mut num_still_checks = 0;
+        let mut samples = Self::sample_minimap(&frame, minimap_middle);
+        let time = std::time::Instant::now();
+        while time.elapsed() < self.timeout {
+            sleep(Duration::from_millis(300));
+            let frame = capturer.frame().unwrap();
+
+            // The flag is removed once we reach it.
+            if Self::is_flag_shown(&framehandler.calibrated(&frame), target) {
+                flag_seen = true;
+            } else if flag_seen {
+                return true;
+            }
+
+            let new_samples = Self::sample_minimap(&frame, minimap_middle);
+            if Self::is_minimap_moving(&samples, &new_samples) {
+                moved = true;
+                num_still_checks = 0;
+            } else {
+                num_still_checks += 1;
+            }
+            samples = new_samples;
+
+            // Give the player a moment to start moving before treating a still
+            // minimap as arrived.
+            if num_still_checks >= 3 && time.elapsed() > Duration::from_secs(2) {
+                if flag_seen || moved {
+                    return true;
+                }
+                println!("WalkTiles never started moving");
+                return false;
+            }
+        }
+
+        println!("WalkTiles timed out");
+        false
+    }
+}
+
 impl TravelTo {
     pub fn new(
         primary_pixel: FuzzyPixel,
@@ -1140,6 +2005,7 @@ impl TravelTo {
                 primary_pixel,
                 check_pixels,
                 arc_of_interest,
+                search_rings: 1,
             },
             timeout,
             try_to_run,
@@ -1180,11 +2046,11 @@ impl Action for TravelTo {
                     // Once we are nearby we often will still move for another few
                     // seconds. This can cause us to click on an incorrect spot. So wait
                     // to make sure we are done moving.
-                    let running = capturer.frame().unwrap().check_loose_pixel(
-                        &framehandler.locations.run_icon(),
-                        &fuzzy_pixels::run_icon_on(),
-                    );
-                    sleep(Duration::from_secs(if running { 2 } else { 4 }));
+                    Await {
+                        condition: AwaitCondition::PlayerStopped,
+                        timeout: Duration::from_secs(5),
+                    }
+                    .do_action(inputbot, framehandler, capturer);
                 }
 
                 return true;
@@ -1247,8 +2113,134 @@ impl OpenScreenAction {
             expected_pixels,
             check_action_text,
             mouse_click,
+            skip_npcs_in_combat: false,
+            failure_cooldown: Duration::from_secs(30),
+            click_history: ClickHistory::default(),
+            watch_for_depletion: false,
+            search_radius: None,
         }
     }
+
+    /// Where to point the mouse to hit 'blob'. The centroid if it is part of
+    /// the blob, otherwise any matching pixel within it.
+    fn target_in_blob(&self, frame: &impl Frame, blob: &Blob) -> Option<Position> {
+        let centroid_pixel = frame.get_pixel(&blob.centroid);
+        if self
+            .expected_pixels
+            .iter()
+            .any(|fuzzy_pixel| fuzzy_pixel.matches(&centroid_pixel))
+        {
+            return Some(blob.centroid);
+        }
+        self.expected_pixels.iter().find_map(|fuzzy_pixel| {
+            frame.find_pixel_random(fuzzy_pixel, &blob.top_left, &blob.dimensions)
+        })
+    }
+}
+
+impl WatchTarget {
+    /// Find the target we clicked. Call once the player has stopped moving,
+    /// with 'player_offset' how far they moved on the minimap since the
+    /// click, since the screen scrolls as the player walks.
+    fn lock_on(
+        &self,
+        framehandler: &FrameHandler,
+        frame: &impl Frame,
+        player_offset: DeltaPosition,
+    ) -> Option<Blob> {
+        // The screen scrolls the opposite way to the player. Like
+        // estimated_distance_from_player, assume a step up or down the
+        // screen covers about twice the ground of a step to the side.
+        let scale = OPEN_SCREEN_PIXELS_PER_TILE / Locations::MINIMAP_PIXELS_PER_TILE;
+        let expected = self.clicked
+            - DeltaPosition {
+                dx: player_offset.dx * scale,
+                dy: player_offset.dy * scale / 2,
+            };
+
+        let blobs = framehandler.find_open_screen_blobs(
+            frame,
+            &self.expected_pixels,
+            MIN_TARGET_AREA,
+            BlobRanking::ClosestTo(expected),
+        );
+        let contains_expected = |blob: &Blob| {
+            let offset = expected - blob.top_left;
+            (0..=blob.dimensions.dx).contains(&offset.dx)
+                && (0..=blob.dimensions.dy).contains(&offset.dy)
+        };
+        match blobs.iter().find(|blob| contains_expected(blob)) {
+            Some(blob) => Some(blob.clone()),
+            None => blobs
+                .into_iter()
+                .next()
+                .filter(|blob| (blob.centroid - expected).distance() <= WATCH_TARGET_RADIUS),
+        }
+    }
+
+    /// Has the target found by lock_on lost its colors.
+    fn is_gone(&self, frame: &impl Frame, target: &Blob) -> bool {
+        screen::blobs::find_blobs(
+            frame,
+            &self.expected_pixels,
+            &target.top_left,
+            &target.dimensions,
+            MIN_TARGET_AREA,
+            BlobRanking::Largest,
+        )
+        .is_empty()
+    }
+}
+
+impl ClickHistory {
+    /// Where we last clicked and the minimap at the time.
+    pub fn last_click(&self) -> Option<(Position, MinimapCrop)> {
+        self.last_click.borrow().clone()
+    }
+
+    /// 'minimap' is the minimap at the time of the click.
+    pub fn record_click(&self, pos: Position, minimap: MinimapCrop) {
+        *self.last_click.borrow_mut() = Some((pos, minimap));
+    }
+
+    /// Mark the last click as having produced nothing.
+    pub fn record_failure(&self) {
+        if let Some((pos, minimap)) = self.last_click.borrow_mut().take() {
+            self.failures
+                .borrow_mut()
+                .push((pos, minimap, std::time::Instant::now()));
+        }
+    }
+
+    /// Forget failures from clicks made while the minimap looked different
+    /// from 'minimap', since their positions no longer line up with the
+    /// screen.
+    pub fn forget_if_moved(&self, minimap: &MinimapCrop) {
+        self.failures.borrow_mut().retain(|(_, before, _)| {
+            let motion = estimate_motion(before, minimap);
+            motion.is_confident() && motion.distance_tiles() == 0.0
+        });
+    }
+
+    /// Did clicking near 'pos' fail within the last 'cooldown'.
+    pub fn recently_failed(&self, pos: &Position, cooldown: Duration) -> bool {
+        let mut failures = self.failures.borrow_mut();
+        failures.retain(|(_, _, time)| time.elapsed() < cooldown);
+        failures
+            .iter()
+            .any(|(failed, _, _)| (*failed - *pos).distance() <= FAILED_CLICK_RADIUS)
+    }
+}
+
+/// Is 'pos' on a character drawn below one of the given overhead healthbars.
+fn is_below_healthbar(pos: &Position, healthbars: &[Position]) -> bool {
+    healthbars.iter().any(|healthbar| {
+        let dx = pos.x - (healthbar.x + Locations::OVERHEAD_HEALTHBAR_WIDTH / 2);
+        let dy = pos.y - healthbar.y;
+        dx.abs() <= Locations::OVERHEAD_HEALTHBAR_WIDTH
+            && dy > 0
+            && dy <= Locations::OVERHEAD_HEALTHBAR_OWNER_HEIGHT
+    })
 }
 
 impl Action for OpenScreenAction {
@@ -1259,46 +2251,481 @@ impl Action for OpenScreenAction {
         capturer: &mut Capturer,
     ) -> bool {
         println!("OpenScreenAction");
-        for (top_left, dimensions) in framehandler.locations.open_screen_search_boxes().iter() {
-            for fuzzy_pixel in self.expected_pixels.iter() {
-                let pos = capturer.frame().unwrap().find_pixel_random(
-                    &fuzzy_pixel,
-                    top_left,
-                    &dimensions,
-                );
-                if pos.is_none() {
-                    continue;
-                }
+        let frame = capturer.frame().unwrap();
+        let healthbars = if self.skip_npcs_in_combat {
+            // Our own healthbar is shown while anything attacks us, and would
+            // hide the NPC doing so.
+            let player_healthbar = framehandler.locations.player_healthbar_left();
+            framehandler
+                .find_overhead_healthbars(&frame)
+                .into_iter()
+                .filter(|healthbar| {
+                    (*healthbar - player_healthbar).distance() > PLAYER_HEALTHBAR_RADIUS
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        // Gather every target on the open screen, preferring those closest to
+        // the player. Spots which recently produced nothing go last.
+        let minimap = MinimapCrop::capture(&frame, framehandler.locations.minimap_middle());
+        self.click_history.forget_if_moved(&minimap);
+        let mid_screen = framehandler.locations.mid_screen();
+        let mut candidates: Vec<(bool, i32, Position)> = framehandler
+            .find_open_screen_blobs(
+                &frame,
+                &self.expected_pixels,
+                MIN_TARGET_AREA,
+                BlobRanking::Largest,
+            )
+            .iter()
+            .filter_map(|blob| self.target_in_blob(&framehandler.calibrated(&frame), blob))
+            .filter(|pos| !is_below_healthbar(pos, &healthbars))
+            .filter(|pos| match self.search_radius {
+                Some(radius) => (*pos - mid_screen).distance() <= radius,
+                None => true,
+            })
+            .map(|pos| {
+                (
+                    self.click_history
+                        .recently_failed(&pos, self.failure_cooldown),
+                    framehandler.locations.estimated_distance_from_player(&pos),
+                    pos,
+                )
+            })
+            .collect();
+        candidates.sort_by_key(|(recently_failed, distance, _)| (*recently_failed, *distance));
+
+        for (_, _, pos) in candidates.iter().take(MAX_TARGET_ATTEMPTS) {
+            inputbot.move_to(pos);
+
+            if self.check_action_text.is_none() {
+                click_mouse(inputbot, self.mouse_click);
+                self.click_history.record_click(*pos, minimap);
+                return true;
+            }
 
-                inputbot.move_to(&pos.unwrap());
+            // CheckActionText includes clicking.
+            let action_text_time = std::time::Instant::now();
+            while action_text_time.elapsed() < util::REDRAW_TIME {
+                // Put the sleep first to lower the chance of us reading
+                // an old action text.
 
-                if self.check_action_text.is_none() {
-                    click_mouse(inputbot, self.mouse_click);
+                sleep(Duration::from_millis(100));
+                if self.check_action_text.as_ref().unwrap().do_action(
+                    inputbot,
+                    framehandler,
+                    capturer,
+                ) {
+                    self.click_history.record_click(*pos, minimap);
                     return true;
                 }
+            }
+        }
 
-                // CheckActionText includes clicking.
-                let action_text_time = std::time::Instant::now();
-                while action_text_time.elapsed() < util::REDRAW_TIME {
-                    // Put the sleep first to lower the chance of us reading
-                    // an old action text.
+        false
+    }
 
-                    sleep(Duration::from_millis(100));
-                    if self.check_action_text.as_ref().unwrap().do_action(
-                        inputbot,
-                        framehandler,
-                        capturer,
-                    ) {
+    fn report_failure(&self) {
+        self.click_history.record_failure();
+    }
+
+    fn watch_target(&self) -> Option<WatchTarget> {
+        if !self.watch_for_depletion {
+            return None;
+        }
+        let (clicked, clicked_minimap) = self.click_history.last_click()?;
+        Some(WatchTarget {
+            expected_pixels: self.expected_pixels.clone(),
+            clicked,
+            clicked_minimap,
+        })
+    }
+}
+
+impl ObstacleKind {
+    fn open_action_text(&self) -> ActionText {
+        match self {
+            ObstacleKind::Door => action_text::open_door(),
+            ObstacleKind::Gate => action_text::open_gate(),
+        }
+    }
+}
+
+impl Obstacle {
+    pub fn al_kharid_door() -> Obstacle {
+        Obstacle {
+            kind: ObstacleKind::Door,
+            expected_pixels: vec![
+                fuzzy_pixels::al_kharid_door1(),
+                fuzzy_pixels::al_kharid_door2(),
+                fuzzy_pixels::al_kharid_door3(),
+                fuzzy_pixels::al_kharid_door4(),
+            ],
+        }
+    }
+}
+
+impl OpenObstacle {
+    pub fn new(obstacle: Obstacle) -> OpenObstacle {
+        OpenObstacle {
+            obstacle,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Find a pixel of the obstacle right next to the player.
+    fn find_near_player(
+        &self,
+        framehandler: &FrameHandler,
+        capturer: &mut Capturer,
+    ) -> Option<Position> {
+        let radius = 60;
+        let top_left = framehandler.locations.mid_screen()
+            - DeltaPosition {
+                dx: radius,
+                dy: radius,
+            };
+        let dimensions = DeltaPosition {
+            dx: 2 * radius,
+            dy: 2 * radius,
+        };
+        let frame = capturer.frame().unwrap();
+        self.obstacle.expected_pixels.iter().find_map(|pixel| {
+            framehandler
+                .calibrated(&frame)
+                .find_pixel_random(pixel, &top_left, &dimensions)
+        })
+    }
+}
+
+impl Action for OpenObstacle {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("OpenObstacle [ kind: {:?}]", self.obstacle.kind);
+        let open = OpenScreenAction {
+            search_radius: Some(OBSTACLE_SEARCH_RADIUS),
+            ..OpenScreenAction::new(
+                /*expected_pixels=*/ self.obstacle.expected_pixels.clone(),
+                /*action_text=*/ Some(self.obstacle.kind.open_action_text()),
+                /*mouse_click=*/ MouseClick::Left,
+            )
+        };
+        if !open.do_action(inputbot, framehandler, capturer) {
+            println!("No closed {:?} found", self.obstacle.kind);
+            return false;
+        }
+
+        // Clicking walks us up to the obstacle, so once it is open there
+        // should be no closed obstacle next to the player.
+        Await {
+            condition: AwaitCondition::PlayerStopped,
+            timeout: self.timeout,
+        }
+        .do_action(inputbot, framehandler, capturer);
+        let open_text = self.obstacle.kind.open_action_text();
+        let time = std::time::Instant::now();
+        while time.elapsed() < self.timeout {
+            let pos = match self.find_near_player(framehandler, capturer) {
+                Some(pos) => pos,
+                None => return true,
+            };
+            inputbot.move_to(&pos);
+            sleep(util::REDRAW_TIME);
+            if !framehandler.check_action_text(&capturer.frame().unwrap(), &open_text) {
+                return true;
+            }
+            sleep(GAME_TICK);
+        }
+
+        println!("{:?} did not open", self.obstacle.kind);
+        false
+    }
+}
+
+impl FloorDirection {
+    pub fn opposite(&self) -> FloorDirection {
+        match self {
+            FloorDirection::Up => FloorDirection::Down,
+            FloorDirection::Down => FloorDirection::Up,
+        }
+    }
+}
+
+impl ClimbableKind {
+    fn climb_action_text(&self, direction: FloorDirection) -> ActionText {
+        match (self, direction) {
+            (ClimbableKind::Ladder, FloorDirection::Up) => action_text::climb_up_ladder(),
+            (ClimbableKind::Ladder, FloorDirection::Down) => action_text::climb_down_ladder(),
+            (ClimbableKind::Staircase, FloorDirection::Up) => action_text::climb_up_staircase(),
+            (ClimbableKind::Staircase, FloorDirection::Down) => action_text::climb_down_staircase(),
+        }
+    }
+}
+
+impl Action for SelectMenuEntry {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("SelectMenuEntry");
+        let menu_pos = inputbot.mouse_position();
+        inputbot.right_click();
+        sleep(util::REDRAW_TIME);
+
+        match framehandler.find_right_click_menu_entry(
+            &capturer.frame().unwrap(),
+            menu_pos,
+            &self.action_text,
+        ) {
+            Some(pos) => {
+                inputbot.move_to(&pos);
+                inputbot.left_click();
+                true
+            }
+            None => {
+                // Moving the mouse away from the menu closes it.
+                inputbot.move_to(&Position {
+                    x: menu_pos.x,
+                    y: menu_pos.y - 2 * Locations::RIGHT_CLICK_MENU_HEADER_HEIGHT,
+                });
+                println!("Menu entry not found");
+                false
+            }
+        }
+    }
+}
+
+impl ChangeFloor {
+    pub fn new(climbable: Climbable, direction: FloorDirection) -> ChangeFloor {
+        ChangeFloor {
+            climbable,
+            direction,
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+impl Action for ChangeFloor {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!(
+            "ChangeFloor [ kind: {:?}, direction: {:?}]",
+            self.climbable.kind, self.direction
+        );
+        let minimap_middle = framehandler.locations.minimap_middle();
+        let mut previous = MinimapCrop::capture(&capturer.frame().unwrap(), minimap_middle);
+
+        let kind = self.climbable.kind;
+        let clicked = OpenScreenAction::new(
+            self.climbable.expected_pixels.clone(),
+            Some(kind.climb_action_text(self.direction)),
+            MouseClick::Left,
+        )
+        .do_action(inputbot, framehandler, capturer)
+            || (OpenScreenAction::new(
+                self.climbable.expected_pixels.clone(),
+                /*action_text=*/ None,
+                MouseClick::None,
+            )
+            .do_action(inputbot, framehandler, capturer)
+                && SelectMenuEntry {
+                    action_text: kind.climb_action_text(self.direction),
+                }
+                .do_action(inputbot, framehandler, capturer));
+        if !clicked {
+            println!("No {:?} found", kind);
+            return false;
+        }
+
+        // Walking scrolls the minimap smoothly, while changing floors replaces
+        // it all at once. Only trust a jump if the minimap could be tracked
+        // right before it, otherwise a featureless minimap looks like a jump.
+        // A single bad frame (e.g. a menu drawn over the minimap) also looks
+        // like a jump, so the floor has only changed once the minimap is
+        // trackable again and still doesn't match what it was before.
+        let mut was_trackable = false;
+        let mut before_jump: Option<(MinimapCrop, i32)> = None;
+        let time = std::time::Instant::now();
+        while time.elapsed() < self.timeout {
+            sleep(GAME_TICK);
+            let crop = MinimapCrop::capture(&capturer.frame().unwrap(), minimap_middle);
+            let motion = estimate_motion(&previous, &crop);
+            match before_jump.take() {
+                None if motion.is_confident() => was_trackable = true,
+                None if was_trackable => {
+                    before_jump = Some((previous, 0));
+                    was_trackable = false;
+                }
+                None => (),
+                Some((before, _)) if estimate_motion(&before, &crop).is_confident() => {
+                    // The old minimap is back, so it never went away.
+                    was_trackable = motion.is_confident();
+                }
+                Some((before, num_checks)) if motion.is_confident() => {
+                    if num_checks + 1 >= FLOOR_CHANGE_CHECKS {
                         return true;
                     }
+                    before_jump = Some((before, num_checks + 1));
                 }
+                Some(jump) => before_jump = Some(jump),
             }
+            previous = crop;
         }
 
+        println!("Floor did not change");
         false
     }
 }
 
+impl LootItem {
+    /// Find the item by the label drawn by RuneLite's Ground Items plugin.
+    /// Assumes the item is on the plugin's highlighted list.
+    pub fn from_label(
+        action_text: ActionText,
+        inventory_item: Option<screen::InventorySlotPixels>,
+    ) -> LootItem {
+        LootItem {
+            expected_pixels: vec![fuzzy_pixels::ground_item_label_purple()],
+            hover_offset: Locations::GROUND_ITEM_LABEL_OFFSET,
+            check_action_text: CheckActionText {
+                action_text,
+                mouse_click: MouseClick::Left,
+            },
+            inventory_item,
+        }
+    }
+}
+
+impl Loot {
+    /// The inventory state used to tell if 'item' arrived.
+    fn inventory_state(
+        &self,
+        item: &LootItem,
+        framehandler: &FrameHandler,
+        frame: &impl Frame,
+    ) -> Vec<i32> {
+        (0..Locations::NUM_INVENTORY_SLOTS)
+            .filter(|&slot_index| match &item.inventory_item {
+                Some(pixels) => framehandler.check_inventory_slot(frame, slot_index, pixels),
+                None => !framehandler.is_inventory_slot_open(frame, slot_index),
+            })
+            .collect()
+    }
+
+    /// Try to pick up a single instance of 'item'.
+    fn take_item(
+        &self,
+        item: &LootItem,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        let middle = framehandler.locations.mid_screen();
+        let top_left = Position {
+            x: middle.x - self.search_radius,
+            y: middle.y - self.search_radius,
+        };
+        let dimensions = DeltaPosition {
+            dx: 2 * self.search_radius,
+            dy: 2 * self.search_radius,
+        };
+
+        let frame = capturer.frame().unwrap();
+        let initial_inventory = self.inventory_state(item, framehandler, &frame);
+
+        // Every item's label has the same color, so hover each in turn,
+        // nearest first, until one offers to take the item we want.
+        let mut labels = screen::blobs::merge_nearby_blobs(
+            screen::blobs::find_blobs(
+                &framehandler.calibrated(&frame),
+                &item.expected_pixels,
+                &top_left,
+                &dimensions,
+                MIN_TARGET_AREA,
+                BlobRanking::Largest,
+            ),
+            LABEL_LETTER_GAP,
+        );
+        screen::blobs::rank_blobs(&mut labels, BlobRanking::ClosestTo(middle));
+
+        let mut clicked = false;
+        for label in labels.iter().take(MAX_TARGET_ATTEMPTS) {
+            inputbot.move_to(&(label.centroid + item.hover_offset));
+
+            let action_text_time = std::time::Instant::now();
+            while action_text_time.elapsed() < util::REDRAW_TIME {
+                sleep(Duration::from_millis(100));
+                if item
+                    .check_action_text
+                    .do_action(inputbot, framehandler, capturer)
+                {
+                    clicked = true;
+                    break;
+                }
+            }
+            if clicked {
+                break;
+            }
+        }
+        if !clicked {
+            return false;
+        }
+
+        let time = std::time::Instant::now();
+        while time.elapsed() < self.timeout {
+            sleep(Duration::from_millis(100));
+            let inventory = self.inventory_state(item, framehandler, &capturer.frame().unwrap());
+            if inventory.len() > initial_inventory.len() {
+                return true;
+            }
+        }
+        println!("Looted item never arrived in the inventory.");
+        false
+    }
+}
+
+impl Action for Loot {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("Loot");
+        let mut looted = false;
+        for item in self.items.iter() {
+            // There may be several of the same item, but don't get stuck on
+            // an item we can't pick up.
+            for _ in 0..3 {
+                if framehandler
+                    .first_open_inventory_slot(&capturer.frame().unwrap())
+                    .is_none()
+                {
+                    println!("Inventory is full");
+                    return looted;
+                }
+                if !self.take_item(item, inputbot, framehandler, capturer) {
+                    break;
+                }
+                looted = true;
+            }
+        }
+        looted
+    }
+}
+
 impl OpenBank {
     pub fn new(bank_pixels: Vec<FuzzyPixel>, timeout: Duration) -> OpenBank {
         OpenBank {
@@ -1484,15 +2911,51 @@ pub struct ConsumeInventory {
     pub actions: Vec<Box<dyn Action>>,
 }
 
+/// Where MaintainHealth reads the player's hitpoints from.
+#[derive(Clone, Copy, Debug)]
+pub enum HitpointsSource {
+    /// The hitpoints orb next to the minimap. Eat once hitpoints drop below
+    /// 'min_hitpoints'.
+    Orb { min_hitpoints: i32 },
+    /// The healthbar above the player's head. Eat once the bar drops below
+    /// 'min_fraction' (0.0 - 1.0). The bar is only shown during combat.
+    Overhead { min_fraction: f32 },
+}
+
+/// What MaintainHealth should do when it needs to eat but has no food left.
+pub enum OutOfFood {
+    /// Fail the action so the caller can stop the script.
+    Stop,
+    /// Perform the given action (e.g. travel somewhere safe) and then fail
+    /// the action.
+    Retreat(Box<dyn Action>),
+}
+
+/// Watch the player's hitpoints and eat when they get too low. This is meant
+/// to be called repeatedly from within a combat loop; it does nothing if our
+/// hitpoints are fine or can't be read.
+///
+/// Returns false only if we needed to eat and had no food left.
+pub struct MaintainHealth {
+    pub hitpoints_source: HitpointsSource,
+
+    /// Food to eat, in order of preference. The first one found in the
+    /// inventory is eaten.
+    pub food: Vec<screen::InventorySlotPixels>,
+
+    pub out_of_food: OutOfFood,
+}
+
 pub struct ExplicitActions {
     pub actions: Vec<Box<dyn Action>>,
 }
 
-/// Withdraw the given items from the bank. This works by being given the
-/// slot index in the bank of the items.
+/// Withdraw the given items from the bank. Once the bank is open we look
+/// through the main tab for the items, so they can be anywhere in the bank.
 ///
-/// Assumes that the bank is scrolled all the way up so that the slot are in
-/// their expected locations.
+/// Assumes that the bank opens on the main tab scrolled all the way up.
+/// Items further down are shown with ShowBankSlot, after which the bank is
+/// put back how we found it.
 ///
 /// Assumes we are near the bank and there is nothing in the way like a
 /// closed door.
@@ -1502,9 +2965,9 @@ pub struct ExplicitActions {
 pub struct WithdrawFromBank {
     pub open_bank_action: OpenBank,
 
-    /// This is the set of actions to take once the bank is open. It should
-    /// be in the pattern of [set_quantity, press_slot_center, .*].
-    pub withdrawal_actions: Vec<Box<dyn Action>>,
+    /// How many of each item to withdraw, and what the item looks like in
+    /// the inventory.
+    pub quantity_and_item: Vec<(BankQuantity, screen::InventorySlotPixels)>,
 
     // Items that should be withdrawn from the bank. Used to check withdrawal
     // succeeded.
@@ -1545,6 +3008,9 @@ impl Action for ConsumeSingleInventoryItem {
                     return true;
                 }
             }
+            for action in &self.actions {
+                action.report_failure();
+            }
         }
         false
     }
@@ -1585,6 +3051,9 @@ impl Action for ConsumeInventory {
             if !actions_succeeded {
                 consecutive_action_failures += 1;
                 if consecutive_action_failures > 3 {
+                    // Clicks may be missing because the UI isn't where we
+                    // think it is.
+                    framehandler.relayout(&capturer.frame().unwrap());
                     inputbot.pan_left(37.0);
                     consecutive_action_failures = 0;
                 }
@@ -1592,16 +3061,49 @@ impl Action for ConsumeInventory {
             }
             consecutive_action_failures = 0;
 
+            // Watch the resource we clicked so we can move on as soon as it
+            // runs out instead of waiting out the timer.
+            let watch_target = self.actions.iter().find_map(|action| action.watch_target());
+            let mut watched = None;
+            let mut odometer = match &watch_target {
+                Some(watch_target) => {
+                    MinimapOdometer::starting_from(watch_target.clicked_minimap.clone())
+                }
+                None => MinimapOdometer::new(),
+            };
+
             let mut waittime = std::time::Instant::now();
             let mut consumed_slot = false;
             while waittime.elapsed() < self.slot_consumption_waittime {
                 sleep(Duration::from_secs(1));
-                let matching_slot = framehandler.first_matching_inventory_slot(
-                    &capturer.frame().unwrap(),
-                    &self.item_to_consume,
-                );
+                let frame = capturer.frame().unwrap();
+                let matching_slot =
+                    framehandler.first_matching_inventory_slot(&frame, &self.item_to_consume);
                 if matching_slot == first_matching_inventory_slot {
                     // Nothing new in the inventory, just keep waiting.
+                    if let Some(watch_target) = &watch_target {
+                        match &watched {
+                            Some(target) => {
+                                if watch_target.is_gone(&framehandler.calibrated(&frame), target) {
+                                    println!("Target is gone");
+                                    break;
+                                }
+                            }
+                            None => {
+                                let motion = odometer
+                                    .update(&frame, framehandler.locations.minimap_middle());
+                                if matches!(motion, Some(motion) if motion.is_confident()
+                                    && motion.player_offset == DeltaPosition { dx: 0, dy: 0 })
+                                {
+                                    watched = watch_target.lock_on(
+                                        framehandler,
+                                        &frame,
+                                        odometer.player_offset,
+                                    );
+                                }
+                            }
+                        }
+                    }
                     continue;
                 }
 
@@ -1622,6 +3124,23 @@ impl Action for ConsumeInventory {
 
             if !consumed_slot {
                 // This could indicate crowding. Potentially world switch?
+                let dots = framehandler.find_minimap_dots(&capturer.frame().unwrap());
+                println!(
+                    "Nothing consumed, {} players and {} NPCs nearby",
+                    dots.count_near(
+                        MinimapDotKind::Player,
+                        &framehandler.locations.minimap_middle(),
+                        CROWDING_RADIUS
+                    ),
+                    dots.count_near(
+                        MinimapDotKind::Npc,
+                        &framehandler.locations.minimap_middle(),
+                        CROWDING_RADIUS
+                    ),
+                );
+                for action in &self.actions {
+                    action.report_failure();
+                }
                 consecutive_consumption_failures += 1;
                 if consecutive_consumption_failures > 3 {
                     // TODO: return false?
@@ -1635,6 +3154,74 @@ impl Action for ConsumeInventory {
     }
 }
 
+impl MaintainHealth {
+    /// Returns true if the player's hitpoints are known to be below the
+    /// threshold.
+    pub fn needs_healing(&self, framehandler: &FrameHandler, frame: &impl Frame) -> bool {
+        match self.hitpoints_source {
+            HitpointsSource::Orb { min_hitpoints } => {
+                match framehandler.orb_value(frame, Orb::Hitpoints) {
+                    Some(hitpoints) => hitpoints < min_hitpoints,
+                    None => false,
+                }
+            }
+            HitpointsSource::Overhead { min_fraction } => {
+                match framehandler.player_health_fraction(frame) {
+                    Some(fraction) => fraction < min_fraction,
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+impl Action for MaintainHealth {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        let frame = capturer.frame().unwrap();
+        if !self.needs_healing(framehandler, &frame) {
+            return true;
+        }
+        println!("MaintainHealth");
+
+        let food = self.food.iter().find_map(|food| {
+            framehandler
+                .first_matching_inventory_slot(&frame, food)
+                .map(|slot_index| (food, slot_index))
+        });
+        let (food, slot_index) = match food {
+            Some(food) => food,
+            None => {
+                println!("Out of food");
+                if let OutOfFood::Retreat(retreat) = &self.out_of_food {
+                    retreat.do_action(inputbot, framehandler, capturer);
+                }
+                return false;
+            }
+        };
+
+        inputbot.move_to(&util::random_position_polar(
+            framehandler.locations.inventory_slot_middle(slot_index),
+            10,
+        ));
+        inputbot.left_click();
+
+        // Wait for the food to disappear so that we don't eat twice.
+        let time = std::time::Instant::now();
+        while time.elapsed() < Duration::from_secs(3) {
+            sleep(Duration::from_millis(100));
+            if !framehandler.check_inventory_slot(&capturer.frame().unwrap(), slot_index, food) {
+                break;
+            }
+        }
+        true
+    }
+}
+
 impl ExplicitActions {
     /// Default set of actions useful for resetting the player.
     pub fn default_reset() -> ExplicitActions {
@@ -1668,79 +3255,182 @@ impl Action for ExplicitActions {
 
         true
     }
+
+    fn report_failure(&self) {
+        for action in &self.actions {
+            action.report_failure();
+        }
+    }
+
+    fn watch_target(&self) -> Option<WatchTarget> {
+        self.actions.iter().find_map(|action| action.watch_target())
+    }
 }
 
 impl WithdrawFromBank {
     /// 'bank_pixels' - pixels to look for on open screen action to open the
     /// bank.
     ///
-    /// 'bank_slot_and_quantity' - The bank_slot index and the quantity to
-    /// withdraw of each item.
+    /// 'quantity_and_item' - How many of each item to withdraw, and what the
+    /// item looks like in the inventory. The items are found in the bank by
+    /// their appearance, see bank_slot_pixels.
     ///
-    /// If quantity is 0, we will use All. Only 1 item can set this. If
-    /// quantity is -1, we will use X. Values outside of [-1, 28] are
-    /// invalid.
+    /// Only 1 item can use All. Exact quantities must be at most 28.
     ///
     /// Note that if using quantity X, this must be set before calling to
     /// this action.
     pub fn new(
         bank_pixels: Vec<FuzzyPixel>,
-        bank_slot_and_quantity_and_item: Vec<(i32, BankQuantity, screen::InventorySlotPixels)>,
+        quantity_and_item: Vec<(BankQuantity, screen::InventorySlotPixels)>,
     ) -> WithdrawFromBank {
+        let mut num_all = 0;
+        for (quantity, _) in quantity_and_item.iter() {
+            match quantity {
+                BankQuantity::All => num_all += 1,
+                BankQuantity::X => (),
+                BankQuantity::Exact(val) => assert!(*val <= 28),
+                BankQuantity::One => panic!("Invalid BankQuantity for WithdrawFromBank."),
+            }
+        }
+        assert!(num_all <= 1);
+        for (i, (_, item)) in quantity_and_item.iter().enumerate() {
+            let num_checks = bank_slot_pixels::num_checks(&bank_slot_pixels::from_inventory(item));
+            assert!(
+                num_checks >= bank_slot_pixels::MIN_NUM_CHECKS,
+                "Item {} has only {} checks in the bank",
+                i,
+                num_checks
+            );
+        }
+
+        WithdrawFromBank {
+            open_bank_action: OpenBank::new(
+                /*expected_pixels=*/ bank_pixels,
+                /*timeout=*/ Duration::from_secs(60),
+            ),
+            await_items: AwaitAll {
+                conditions: quantity_and_item
+                    .iter()
+                    .map(|(_, item)| AwaitCondition::InventoryContains(*item))
+                    .collect(),
+                timeout: Duration::from_secs(5),
+            },
+            quantity_and_item,
+        }
+    }
+
+    /// Find each item in the main tab of the bank. We scroll down a row at a
+    /// time until all are found or the bank won't scroll any further. Leaves
+    /// the bank scrolled to the top.
+    fn locate_items(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> Option<Vec<BankSlot>> {
+        let items: Vec<(usize, screen::BankSlotPixels)> = self
+            .quantity_and_item
+            .iter()
+            .enumerate()
+            .map(|(i, (_, item))| (i, bank_slot_pixels::from_inventory(item)))
+            .collect();
+
+        let mut found = HashMap::<usize, BankSlot>::new();
+        let mut rows_scrolled = match framehandler.bank_rows_scrolled(&capturer.frame().unwrap()) {
+            Some(rows) => rows,
+            None => {
+                println!("Unable to find the bank scrollbar");
+                return None;
+            }
+        };
+        let mut scrolled = rows_scrolled > 0;
+        loop {
+            let frame = capturer.frame().unwrap();
+            for (item, slot) in
+                framehandler.scan_bank(&frame, &items, /*tab=*/ 0, rows_scrolled)
+            {
+                found.entry(item).or_insert(slot);
+            }
+            if found.len() == items.len() {
+                break;
+            }
+
+            scrolled = true;
+            let scroll = ScrollBank { rows: 1 };
+            if !scroll.do_action(inputbot, framehandler, capturer) {
+                break;
+            }
+            rows_scrolled += 1;
+        }
+        if scrolled {
+            ScrollBankToTop {}.do_action(inputbot, framehandler, capturer);
+        }
+
+        let mut slots = Vec::<BankSlot>::new();
+        for (i, _) in items.iter() {
+            match found.get(i) {
+                Some(slot) => slots.push(*slot),
+                None => {
+                    println!("Unable to find item {} in the bank", i);
+                    return None;
+                }
+            }
+        }
+        Some(slots)
+    }
+
+    /// Actions to withdraw 'quantity' from each slot. Assumes the bank is
+    /// showing the main tab scrolled all the way up.
+    fn withdrawal_actions(
+        slot_and_quantity: Vec<(BankSlot, &BankQuantity)>,
+    ) -> Vec<Box<dyn Action>> {
         // Organize the withdrawals by quantity to optimize the number of
         // times we reset. Use a BTreeMap since the special values are the
         // lowest ones, so when we reach 1-28, we won't have to keep
         // resetting quantity to One.
-        let mut quantity_to_slot_indices = BTreeMap::<i32, Vec<i32>>::new();
-        let mut await_conditions = Vec::<AwaitCondition>::new();
-        for (slot_index, quantity, item) in bank_slot_and_quantity_and_item {
-            await_conditions.push(AwaitCondition::InventoryContains(item));
-            match quantity {
-                BankQuantity::All => quantity_to_slot_indices
-                    .entry(-1)
-                    .or_insert(Vec::<i32>::new())
-                    .push(slot_index),
-                BankQuantity::X => quantity_to_slot_indices
-                    .entry(0)
-                    .or_insert(Vec::<i32>::new())
-                    .push(slot_index),
-                BankQuantity::Exact(val) => {
-                    assert!(val <= 28);
-                    quantity_to_slot_indices
-                        .entry(val)
-                        .or_insert(Vec::<i32>::new())
-                        .push(slot_index);
-                }
+        let mut quantity_to_slots = BTreeMap::<i32, Vec<BankSlot>>::new();
+        for (slot, quantity) in slot_and_quantity {
+            let key = match quantity {
+                BankQuantity::All => -1,
+                BankQuantity::X => 0,
+                BankQuantity::Exact(val) => *val,
                 BankQuantity::One => panic!("Invalid BankQuantity for WithdrawFromBank."),
-            }
+            };
+            quantity_to_slots
+                .entry(key)
+                .or_insert(Vec::<BankSlot>::new())
+                .push(slot);
         }
 
         // Convert each item we need to withdraw into actions.
         let mut withdrawal_actions = Vec::<Box<dyn Action>>::new();
         let mut set_quantity_to_one = false;
+        let mut bank_view_moved = false;
         // Use BTreeMap + reverse iterator to erach All last.
-        for (quantity, slot_indices) in quantity_to_slot_indices.iter().rev() {
+        for (quantity, slots) in quantity_to_slots.iter().rev() {
             if *quantity == -1 {
-                assert_eq!(slot_indices.len(), 1);
+                assert_eq!(slots.len(), 1);
 
                 withdrawal_actions.push(Box::new(SetBankQuantity {
                     quantity: BankQuantity::All,
                 }));
 
-                withdrawal_actions.push(Box::new(ClickBankSlot {
-                    slot_index: slot_indices[0],
-                    mouse_click: MouseClick::Left,
-                }));
+                withdrawal_actions.extend(Self::click_slot(
+                    &slots[0],
+                    /*times=*/ 1,
+                    &mut bank_view_moved,
+                ));
             } else if *quantity == 0 {
                 withdrawal_actions.push(Box::new(SetBankQuantity {
                     quantity: BankQuantity::X,
                 }));
 
-                for slot_index in slot_indices.iter() {
-                    withdrawal_actions.push(Box::new(ClickBankSlot {
-                        slot_index: *slot_index,
-                        mouse_click: MouseClick::Left,
-                    }));
+                for slot in slots.iter() {
+                    withdrawal_actions.extend(Self::click_slot(
+                        slot,
+                        /*times=*/ 1,
+                        &mut bank_view_moved,
+                    ));
                 }
             } else {
                 if !set_quantity_to_one {
@@ -1752,28 +3442,40 @@ impl WithdrawFromBank {
                     set_quantity_to_one = true;
                 }
 
-                for slot_index in slot_indices.iter() {
-                    for _ in 0..*quantity {
-                        withdrawal_actions.push(Box::new(ClickBankSlot {
-                            slot_index: *slot_index,
-                            mouse_click: MouseClick::Left,
-                        }));
-                    }
+                for slot in slots.iter() {
+                    withdrawal_actions.extend(Self::click_slot(
+                        slot,
+                        /*times=*/ *quantity,
+                        &mut bank_view_moved,
+                    ));
                 }
             }
         }
+        if bank_view_moved {
+            // Leave the bank as we assume it will be next time.
+            withdrawal_actions.push(Box::new(SelectBankTab { tab: 0 }));
+            withdrawal_actions.push(Box::new(ScrollBankToTop {}));
+        }
+        withdrawal_actions
+    }
 
-        WithdrawFromBank {
-            open_bank_action: OpenBank::new(
-                /*expected_pixels=*/ bank_pixels,
-                /*timeout=*/ Duration::from_secs(60),
-            ),
-            withdrawal_actions,
-            await_items: AwaitAll {
-                conditions: await_conditions,
-                timeout: Duration::from_secs(5),
-            },
+    /// Actions to click 'slot' 'times' times. Slots in the main tab that are
+    /// visible without scrolling are clicked directly, as long as nothing
+    /// has moved the bank yet. After that we no longer know what is shown,
+    /// so every slot needs ShowBankSlot first.
+    fn click_slot(slot: &BankSlot, times: i32, bank_view_moved: &mut bool) -> Vec<Box<dyn Action>> {
+        let mut actions = Vec::<Box<dyn Action>>::new();
+        if *bank_view_moved || slot.tab != 0 || slot.index >= Locations::MIN_NUM_BANK_SLOTS {
+            actions.push(Box::new(ShowBankSlot { slot: *slot }));
+            *bank_view_moved = true;
         }
+        for _ in 0..times {
+            actions.push(Box::new(ClickBankSlot {
+                slot: *slot,
+                mouse_click: MouseClick::Left,
+            }));
+        }
+        actions
     }
 }
 
@@ -1792,7 +3494,17 @@ impl Action for WithdrawFromBank {
             return false;
         }
 
-        for action in &self.withdrawal_actions {
+        let slots = match self.locate_items(inputbot, framehandler, capturer) {
+            Some(slots) => slots,
+            None => return false,
+        };
+        let withdrawal_actions = Self::withdrawal_actions(
+            slots
+                .into_iter()
+                .zip(self.quantity_and_item.iter().map(|(quantity, _)| quantity))
+                .collect(),
+        );
+        for action in &withdrawal_actions {
             if !action.do_action(inputbot, framehandler, capturer) {
                 return false;
             }
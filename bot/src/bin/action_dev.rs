@@ -16,11 +16,6 @@ pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
 
-    #[structopt(long)]
-    pub jug_of_water_bank_slot_index: i32,
-    #[structopt(long)]
-    pub pot_of_flour_bank_slot_index: i32,
-
     #[structopt(long, about = "Which bank we are located in.")]
     pub location: BankLocation,
 }
@@ -31,7 +26,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
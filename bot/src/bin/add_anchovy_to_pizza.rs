@@ -10,11 +10,6 @@ pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
 
-    #[structopt(long, about = "Index of the slot in the bank pizza bases are stored.")]
-    pub plain_pizza_bank_slot_index: i32,
-    #[structopt(long, about = "Index of the slot in the bank tomatos are stored.")]
-    pub anchovy_bank_slot_index: i32,
-
     #[structopt(long, about = "Which bank we are located in.")]
     pub location: BankLocation,
 }
@@ -25,15 +20,10 @@ fn withdraw_pizza_and_anchovies(config: &Config) -> ExplicitActions {
             Box::new(WithdrawFromBank::new(
                 /*bank_pixels=*/
                 bank_pixels(config.location),
-                /*bank_slot_and_quantity=*/
+                /*quantity_and_item=*/
                 vec![
+                    (BankQuantity::X, inventory_slot_pixels::plain_pizza_bank()),
                     (
-                        config.plain_pizza_bank_slot_index,
-                        BankQuantity::X,
-                        inventory_slot_pixels::plain_pizza_bank(),
-                    ),
-                    (
-                        config.anchovy_bank_slot_index,
                         BankQuantity::X,
                         inventory_slot_pixels::cooked_anchovies_bank(),
                     ),
@@ -81,7 +71,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
 
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
@@ -1,5 +1,9 @@
 use bot::actions::*;
-use screen::{action_text, fuzzy_pixels, ActionText, Capturer, FrameHandler, FuzzyPixel};
+use bot::combat::{CombatState, CombatTracker};
+use screen::{
+    action_text, fuzzy_pixels, inventory_slot_pixels, ActionText, Capturer, FrameHandler,
+    FuzzyPixel, InventorySlotPixels,
+};
 use std::error::Error;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -16,6 +20,19 @@ pub enum Enemy {
     AlKharidWarrior,
 }
 
+#[derive(Debug, Copy, Clone, EnumString)]
+pub enum LootDrop {
+    Cowhide,
+    Bones,
+    Feather,
+}
+
+#[derive(Debug, Copy, Clone, EnumString)]
+pub enum Food {
+    Shrimp,
+    Anchovies,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct Config {
     #[structopt(flatten)]
@@ -23,6 +40,25 @@ pub struct Config {
 
     #[structopt(long)]
     pub enemy: Enemy,
+
+    #[structopt(
+        long,
+        about = "Food to eat when our hitpoints get low, in order of preference."
+    )]
+    pub food: Vec<Food>,
+
+    #[structopt(
+        long,
+        about = "Eat once the hitpoints orb drops below this.",
+        default_value = "5"
+    )]
+    pub min_hitpoints: i32,
+
+    #[structopt(
+        long,
+        about = "Drops to pick up after each kill. These must be highlighted in RuneLite's Ground Items plugin."
+    )]
+    pub loot: Vec<LootDrop>,
 }
 
 pub fn enemy_pixels(enemy: Enemy) -> Vec<FuzzyPixel> {
@@ -48,6 +84,26 @@ pub fn enemy_pixels(enemy: Enemy) -> Vec<FuzzyPixel> {
     }
 }
 
+pub fn food_pixels(food: Food) -> InventorySlotPixels {
+    match food {
+        Food::Shrimp => inventory_slot_pixels::cooked_shrimp(),
+        Food::Anchovies => inventory_slot_pixels::cooked_anchovies(),
+    }
+}
+
+pub fn loot_item(drop: LootDrop) -> LootItem {
+    // Pickups are confirmed by an open slot being filled. Feathers stack, so
+    // only the first pickup of them can be confirmed.
+    LootItem::from_label(
+        match drop {
+            LootDrop::Cowhide => action_text::take_cowhide(),
+            LootDrop::Bones => action_text::take_bones(),
+            LootDrop::Feather => action_text::take_feather(),
+        },
+        /*inventory_item=*/ None,
+    )
+}
+
 pub fn get_action_text(enemy: Enemy) -> ActionText {
     match enemy {
         Enemy::Cow => action_text::attack_cow(),
@@ -62,70 +118,53 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
         "\
 Assumes that:
     1. We are in a cow pasture appropriately armed.
-    2. We are high enough level not to need healing.
+    2. Our inventory holds the food given by --food. Without food we must be
+       high enough level not to need healing.
 "
     );
 
-    let attack_cow_action = OpenScreenAction::new(
-        /*expected_pixels=*/ enemy_pixels(config.enemy),
-        /*action_text=*/ Some(get_action_text(config.enemy)),
-        /*mouse_click=*/ MouseClick::Left,
-    );
+    let attack_cow_action = OpenScreenAction {
+        // Clicking on an enemy someone else is fighting fails, so skip them.
+        skip_npcs_in_combat: true,
+        ..OpenScreenAction::new(
+            /*expected_pixels=*/ enemy_pixels(config.enemy),
+            /*action_text=*/ Some(get_action_text(config.enemy)),
+            /*mouse_click=*/ MouseClick::Left,
+        )
+    };
 
-    // TODO: Immediately start waiting for pixel match instead of set time.
-    let await_begin_fighting = ExplicitActions {
-        actions: vec![
-            // Wait until the healthbar of the last enemy has disappeared.
-            Box::new(Await {
-                condition: AwaitCondition::Time(Duration::from_secs(3)),
-                timeout: Duration::from_secs(0),
-            }),
-            Box::new(AwaitAny {
-                conditions: vec![
-                    AwaitCondition::PixelMatch(
-                        framehandler.locations.enemy_healthbar_right(),
-                        fuzzy_pixels::enemy_healthbar_green(),
-                    ),
-                    AwaitCondition::PixelMatch(
-                        framehandler.locations.enemy_healthbar_right(),
-                        fuzzy_pixels::enemy_healthbar_red(),
-                    ),
-                ],
-                timeout: Duration::from_secs(5),
-            }),
-        ],
+    // Stop once we run out of food, rather than fighting until we die. Without
+    // food there is nothing to eat, so don't watch our hitpoints at all.
+    let maintain_health = if config.food.is_empty() {
+        None
+    } else {
+        Some(MaintainHealth {
+            hitpoints_source: HitpointsSource::Orb {
+                min_hitpoints: config.min_hitpoints,
+            },
+            food: config.food.iter().map(|&food| food_pixels(food)).collect(),
+            out_of_food: OutOfFood::Stop,
+        })
     };
 
-    let await_done_fighting = ExplicitActions {
-        actions: vec![
-            Box::new(AwaitAny {
-                conditions: vec![
-                    AwaitCondition::PixelMismatch(
-                        framehandler.locations.enemy_healthbar_right(),
-                        fuzzy_pixels::enemy_healthbar_red(),
-                    ),
-                    AwaitCondition::PixelMatch(
-                        framehandler.locations.enemy_healthbar_left(),
-                        fuzzy_pixels::enemy_healthbar_red(),
-                    ),
-                ],
-                timeout: Duration::from_secs(20),
-            }),
-            // Wait until the body disappears so we dont click on a dead enemy.
-            Box::new(Await {
-                condition: AwaitCondition::Time(Duration::from_secs(0)),
-                timeout: Duration::from_secs(3),
-            }),
-        ],
+    let loot = Loot {
+        items: config.loot.iter().map(|&drop| loot_item(drop)).collect(),
+        search_radius: 150,
+        timeout: Duration::from_secs(5),
     };
 
+    let mut combat_tracker = CombatTracker::new(/*engage_timeout=*/ Duration::from_secs(5));
+
     ExplicitActions::default_reset().do_action(&mut inputbot, &mut framehandler, &mut capturer);
     // Run so that we don't waste too much time getting to the cow.
     MaybeToggleRunning::run().do_action(&mut inputbot, &mut framehandler, &mut capturer);
@@ -133,15 +172,35 @@ Assumes that:
     let mut just_failed_to_start_fight = false;
     let runtime = config.bot_config.runtime();
     while time.elapsed() < runtime {
+        if let Some(maintain_health) = &maintain_health {
+            if !maintain_health.do_action(&mut inputbot, &mut framehandler, &mut capturer) {
+                break;
+            }
+        }
+
+        // Wait until the healthbar of the last enemy has disappeared so we
+        // don't mistake it for the new fight.
+        combat_tracker.await_states(
+            &framehandler,
+            &mut capturer,
+            &[CombatState::Idle, CombatState::UnderAttack],
+            Duration::from_secs(5),
+        );
+
         let res = attack_cow_action.do_action(&mut inputbot, &mut framehandler, &mut capturer);
         if !res {
             inputbot.pan_left(37.0);
             continue;
         }
-
-        // TODO: If we fail to start fighting twice in a row, exit/reset. We may be clicking across the fence.
-
-        if await_begin_fighting.do_action(&mut inputbot, &mut framehandler, &mut capturer) {
+        combat_tracker.engage();
+
+        let began_fighting = combat_tracker.await_states(
+            &framehandler,
+            &mut capturer,
+            &[CombatState::Fighting, CombatState::TargetDead],
+            combat_tracker.engage_timeout,
+        );
+        if began_fighting.is_some() {
             just_failed_to_start_fight = false;
         } else {
             if just_failed_to_start_fight {
@@ -157,10 +216,32 @@ Assumes that:
             continue;
         }
 
-        let res = await_done_fighting.do_action(&mut inputbot, &mut framehandler, &mut capturer);
-        if !res {
-            dbg!(res);
-            // break;
+        combat_tracker.await_states(
+            &framehandler,
+            &mut capturer,
+            &[
+                CombatState::TargetDead,
+                CombatState::Idle,
+                CombatState::UnderAttack,
+            ],
+            Duration::from_secs(20),
+        );
+
+        if let Some(maintain_health) = &maintain_health {
+            if !maintain_health.do_action(&mut inputbot, &mut framehandler, &mut capturer) {
+                break;
+            }
+        }
+
+        if !loot.items.is_empty() {
+            // Drops appear once the body disappears.
+            combat_tracker.await_states(
+                &framehandler,
+                &mut capturer,
+                &[CombatState::Idle, CombatState::UnderAttack],
+                Duration::from_secs(5),
+            );
+            loot.do_action(&mut inputbot, &mut framehandler, &mut capturer);
         }
     }
 
@@ -1,5 +1,6 @@
 use bot::actions::*;
-use screen::{action_text, fuzzy_pixels, inventory_slot_pixels, Capturer, FrameHandler};
+use bot::route::{FollowRoute, Landmark, Route, RouteLeg};
+use screen::{action_text, fuzzy_pixels, inventory_slot_pixels, Capturer, FrameHandler, Locations};
 use std::error::Error;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -30,6 +31,7 @@ fn travel_to_bank(config: &Config) -> ExplicitActions {
         actions.actions.push(Box::new(TravelStraight {
             direction_degrees: 90.0,
             travel_time: Duration::from_secs(7),
+            fail_when_stuck: false,
         }));
     }
     actions.actions.push(Box::new(TravelTo::new(
@@ -56,6 +58,42 @@ fn deposit_in_bank(_config: &Config) -> DepositEntireInventoryToBank {
     ])
 }
 
+/// From the bank, walk north until we reach the oaks (black on the minimap,
+/// next to a gray icon). The bank also has black next to gray icons, so we walk
+/// out of the bank along the headings the script has always used first.
+fn oak_route() -> Route {
+    Route {
+        origin: Landmark {
+            primary_pixel: fuzzy_pixels::map_icon_bank_yellow(),
+            check_pixels: vec![
+                fuzzy_pixels::map_icon_dark_gray(),
+                fuzzy_pixels::map_icon_light_gray(),
+            ],
+            arc_of_interest: (0.0, 360.0),
+        },
+        origin_arrival_radius: Locations::MINIMAP_SMALL_RADIUS,
+        legs: vec![RouteLeg {
+            landmark: Landmark {
+                primary_pixel: fuzzy_pixels::black(),
+                check_pixels: vec![
+                    fuzzy_pixels::map_icon_light_gray(),
+                    fuzzy_pixels::map_icon_dark_gray(),
+                    fuzzy_pixels::black(),
+                ],
+                arc_of_interest: (0.0, 360.0),
+            },
+            arrival_radius: Locations::MINIMAP_SMALL_RADIUS,
+            fallback_heading: Some(285.0),
+            waypoints: vec![
+                (300.0, Duration::from_secs(10)),
+                (270.0, Duration::from_secs(11)),
+            ],
+            obstacle: None,
+            floor_change: None,
+        }],
+    }
+}
+
 fn travel_to_trees(config: &Config) -> ExplicitActions {
     match config.tree_type {
         Tree::Tree => ExplicitActions {
@@ -64,6 +102,7 @@ fn travel_to_trees(config: &Config) -> ExplicitActions {
                 Box::new(TravelStraight {
                     direction_degrees: 230.0,
                     travel_time: Duration::from_secs(5),
+                    fail_when_stuck: false,
                 }),
                 Box::new(TravelTo::new(
                     /*primary_pixel=*/ fuzzy_pixels::dungeon_icon_blue(),
@@ -80,29 +119,7 @@ fn travel_to_trees(config: &Config) -> ExplicitActions {
             ],
         },
         Tree::Oak => ExplicitActions {
-            actions: vec![
-                Box::new(PressCompass {}),
-                Box::new(TravelStraight {
-                    direction_degrees: 300.0,
-                    travel_time: Duration::from_secs(10),
-                }),
-                Box::new(TravelStraight {
-                    direction_degrees: 270.0,
-                    travel_time: Duration::from_secs(11),
-                }),
-                Box::new(TravelTo::new(
-                    /*primary_pixel=*/ fuzzy_pixels::black(),
-                    /*check_pixels=*/
-                    vec![
-                        fuzzy_pixels::map_icon_light_gray(),
-                        fuzzy_pixels::map_icon_dark_gray(),
-                        fuzzy_pixels::black(),
-                    ],
-                    /*arc_of_interest=*/ (0.0, 360.0),
-                    /*timeout=*/ Duration::from_secs(60),
-                    /*try_to_run=*/ true,
-                )),
-            ],
+            actions: vec![Box::new(FollowRoute::new(oak_route()))],
         },
         Tree::Willow => ExplicitActions {
             actions: vec![Box::new(TravelTo::new(
@@ -132,12 +149,15 @@ fn chop_wood(config: &Config) -> ConsumeInventory {
             item_to_consume: inventory_slot_pixels::empty(),
             actions: vec![
                 // Press minimap middle to close the chatbox before clicking 1.
-                Box::new(OpenScreenAction::new(
-                    /*expected_pixels=*/
-                    vec![fuzzy_pixels::tree_bark()],
-                    /*action_text=*/ Some(action_text::chop_down_tree()),
-                    /*mouse_click=*/ MouseClick::Left,
-                )),
+                Box::new(OpenScreenAction {
+                    watch_for_depletion: true,
+                    ..OpenScreenAction::new(
+                        /*expected_pixels=*/
+                        vec![fuzzy_pixels::tree_bark()],
+                        /*action_text=*/ Some(action_text::chop_down_tree()),
+                        /*mouse_click=*/ MouseClick::Left,
+                    )
+                }),
             ],
         },
         Tree::Oak => ConsumeInventory {
@@ -147,12 +167,15 @@ fn chop_wood(config: &Config) -> ConsumeInventory {
             item_to_consume: inventory_slot_pixels::empty(),
             actions: vec![
                 // Press minimap middle to close the chatbox before clicking 1.
-                Box::new(OpenScreenAction::new(
-                    /*expected_pixels=*/
-                    vec![fuzzy_pixels::oak_bark()],
-                    /*action_text=*/ Some(action_text::chop_down_oak()),
-                    /*mouse_click=*/ MouseClick::Left,
-                )),
+                Box::new(OpenScreenAction {
+                    watch_for_depletion: true,
+                    ..OpenScreenAction::new(
+                        /*expected_pixels=*/
+                        vec![fuzzy_pixels::oak_bark()],
+                        /*action_text=*/ Some(action_text::chop_down_oak()),
+                        /*mouse_click=*/ MouseClick::Left,
+                    )
+                }),
             ],
         },
         Tree::Willow => ConsumeInventory {
@@ -162,12 +185,15 @@ fn chop_wood(config: &Config) -> ConsumeInventory {
             item_to_consume: inventory_slot_pixels::empty(),
             actions: vec![
                 // Press minimap middle to close the chatbox before clicking 1.
-                Box::new(OpenScreenAction::new(
-                    /*expected_pixels=*/
-                    vec![fuzzy_pixels::willow_bark1(), fuzzy_pixels::willow_bark2()],
-                    /*action_text=*/ Some(action_text::chop_down_willow()),
-                    /*mouse_click=*/ MouseClick::Left,
-                )),
+                Box::new(OpenScreenAction {
+                    watch_for_depletion: true,
+                    ..OpenScreenAction::new(
+                        /*expected_pixels=*/
+                        vec![fuzzy_pixels::willow_bark1(), fuzzy_pixels::willow_bark2()],
+                        /*action_text=*/ Some(action_text::chop_down_willow()),
+                        /*mouse_click=*/ MouseClick::Left,
+                    )
+                }),
             ],
         },
     }
@@ -179,7 +205,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
@@ -229,3 +258,45 @@ Assumes that:
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use screen::OwnedFrame;
+    use util::*;
+
+    #[test]
+    fn oak_route_walks_out_of_the_bank() {
+        // Saved from the client in and around the Draynor bank, facing north.
+        // The red boxes in the open screen screenshot put the game screen at
+        // (12, 11) to (963, 617).
+        let minimap_middle = Locations::new(
+            Position { x: 12, y: 11 },
+            DeltaPosition { dx: 952, dy: 607 },
+        )
+        .minimap_middle();
+        let leg = &oak_route().legs[0];
+        for name in &["inventories", "open_screen", "worldmap"] {
+            let frame = OwnedFrame::load(&format!(
+                "{}/../data/readme/screenshot_{}.png",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ));
+            let dots = screen::minimap_dots::find_minimap_dots(&frame, minimap_middle);
+
+            // The oak landmark matches the bank, so searching for it from here
+            // would end the leg straight away.
+            let pos = leg
+                .landmark
+                .find(&frame, minimap_middle, Some(&dots))
+                .unwrap();
+            assert!(
+                (pos - minimap_middle).distance() <= leg.arrival_radius,
+                "{} {:?}",
+                name,
+                pos
+            );
+        }
+        assert!(!leg.waypoints.is_empty());
+    }
+}
@@ -40,11 +40,6 @@ pub struct Config {
     pub logs: Logs,
     #[structopt(long)]
     pub food: Food,
-
-    #[structopt(long)]
-    pub food_bank_slot_index: i32,
-    #[structopt(long)]
-    pub logs_bank_slot_index: i32,
 }
 
 fn get_logs_inventory_pixel(config: &Config) -> InventorySlotPixels {
@@ -106,10 +101,9 @@ fn withdraw_from_bank(config: &Config) -> WithdrawFromBank {
     WithdrawFromBank::new(
         /*bank_pixels=*/
         bank_pixels(config),
-        /*bank_slot_and_quantity=*/
+        /*quantity_and_item=*/
         vec![
             (
-                config.logs_bank_slot_index,
                 BankQuantity::Exact(2),
                 match config.logs {
                     Logs::Tree => inventory_slot_pixels::tree_logs_bank(),
@@ -118,7 +112,6 @@ fn withdraw_from_bank(config: &Config) -> WithdrawFromBank {
                 },
             ),
             (
-                config.food_bank_slot_index,
                 BankQuantity::All,
                 match config.food {
                     Food::Shrimp => inventory_slot_pixels::raw_shrimp_bank(),
@@ -142,6 +135,7 @@ fn travel_to_cooking_spot(config: &Config) -> ExplicitActions {
                     Location::Draynor => 10,
                     Location::VarrockWest => 6,
                 }),
+                fail_when_stuck: false,
             }),
         ],
     }
@@ -195,7 +189,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
 
     let reset_actions = ExplicitActions::default_reset();
     let travel_to_bank_actions = travel_to_bank(&config);
@@ -9,12 +9,9 @@ use userinput::InputBot;
 pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
-
-    #[structopt(long)]
-    pub uncooked_pizza_bank_slot_index: i32,
 }
 
-fn withdraw_uncooked_pizzas(config: &Config) -> WithdrawFromBank {
+fn withdraw_uncooked_pizzas(_config: &Config) -> WithdrawFromBank {
     WithdrawFromBank::new(
         /*bank_pixels=*/
         vec![
@@ -22,9 +19,8 @@ fn withdraw_uncooked_pizzas(config: &Config) -> WithdrawFromBank {
             fuzzy_pixels::bank_brown2(),
             fuzzy_pixels::bank_brown3(),
         ],
-        /*bank_slot_and_quantity=*/
+        /*quantity_and_item=*/
         vec![(
-            config.uncooked_pizza_bank_slot_index,
             BankQuantity::All,
             inventory_slot_pixels::uncooked_pizza_bank(),
         )],
@@ -111,7 +107,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
 
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
@@ -11,11 +11,6 @@ pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
 
-    #[structopt(long)]
-    pub jug_of_water_bank_slot_index: i32,
-    #[structopt(long)]
-    pub pot_of_flour_bank_slot_index: i32,
-
     #[structopt(long, about = "Which bank we are located in.")]
     pub location: BankLocation,
 }
@@ -32,18 +27,10 @@ fn withdraw_ingredients(config: &Config) -> ExplicitActions {
         actions: vec![
             Box::new(WithdrawFromBank::new(
                 /*bank_pixels=*/ bank_pixels(config.location),
-                /*bank_slot_and_quantity=*/
+                /*quantity_and_item=*/
                 vec![
-                    (
-                        config.pot_of_flour_bank_slot_index,
-                        BankQuantity::X,
-                        inventory_slot_pixels::pot_of_flour_bank(),
-                    ),
-                    (
-                        config.jug_of_water_bank_slot_index,
-                        BankQuantity::X,
-                        inventory_slot_pixels::jug_of_water_bank(),
-                    ),
+                    (BankQuantity::X, inventory_slot_pixels::pot_of_flour_bank()),
+                    (BankQuantity::X, inventory_slot_pixels::jug_of_water_bank()),
                 ],
             )),
             Box::new(CloseBank {}),
@@ -87,7 +74,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
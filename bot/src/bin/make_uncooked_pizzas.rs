@@ -10,13 +10,6 @@ pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
 
-    #[structopt(long, about = "Index of the slot in the bank pizza bases are stored.")]
-    pub pizza_base_bank_slot_index: i32,
-    #[structopt(long, about = "Index of the slot in the bank tomatos are stored.")]
-    pub tomato_bank_slot_index: i32,
-    #[structopt(long, about = "Index of the slot in the bank chesse are stored.")]
-    pub cheese_bank_slot_index: i32,
-
     #[structopt(long, about = "Which bank we are located in.")]
     pub location: BankLocation,
 }
@@ -26,18 +19,10 @@ fn withdraw_pizza_base_and_tomato(config: &Config) -> ExplicitActions {
         actions: vec![
             Box::new(WithdrawFromBank::new(
                 /*bank_pixels=*/ bank_pixels(config.location),
-                /*bank_slot_and_quantity=*/
+                /*quantity_and_item=*/
                 vec![
-                    (
-                        config.pizza_base_bank_slot_index,
-                        BankQuantity::X,
-                        inventory_slot_pixels::pizza_base_bank(),
-                    ),
-                    (
-                        config.tomato_bank_slot_index,
-                        BankQuantity::X,
-                        inventory_slot_pixels::tomato_bank(),
-                    ),
+                    (BankQuantity::X, inventory_slot_pixels::pizza_base_bank()),
+                    (BankQuantity::X, inventory_slot_pixels::tomato_bank()),
                 ],
             )),
             Box::new(CloseBank {}),
@@ -64,12 +49,8 @@ fn withdraw_cheese(config: &Config) -> ExplicitActions {
         actions: vec![
             Box::new(WithdrawFromBank::new(
                 /*bank_pixels=*/ bank_pixels(config.location),
-                /*bank_slot_and_quantity=*/
-                vec![(
-                    config.cheese_bank_slot_index,
-                    BankQuantity::X,
-                    inventory_slot_pixels::cheese_bank(),
-                )],
+                /*quantity_and_item=*/
+                vec![(BankQuantity::X, inventory_slot_pixels::cheese_bank())],
             )),
             Box::new(CloseBank {}),
         ],
@@ -103,7 +84,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
 
     println!(
         "\
@@ -12,10 +12,12 @@ fn travel_to_mine() -> ExplicitActions {
             Box::new(TravelStraight {
                 direction_degrees: 0.0,
                 travel_time: Duration::from_secs(20),
+                fail_when_stuck: false,
             }),
             Box::new(TravelStraight {
                 direction_degrees: 60.0,
                 travel_time: Duration::from_secs(10),
+                fail_when_stuck: false,
             }),
             Box::new(TravelTo::new(
                 /*primary_pixel=*/ fuzzy_pixels::map_icon_pickaxe_dark_gray(),
@@ -47,12 +49,15 @@ fn mine_copper() -> ConsumeInventory {
         slot_consumption_waittime: Duration::from_secs(15),
         activity_timeout: Duration::from_secs(10 * 60),
         item_to_consume: inventory_slot_pixels::empty(),
-        actions: vec![Box::new(OpenScreenAction::new(
-            /*expected_pixels=*/
-            vec![fuzzy_pixels::copper_ore()],
-            /*action_text=*/ Some(action_text::mine_rocks()),
-            /*mouse_click=*/ MouseClick::Left,
-        ))],
+        actions: vec![Box::new(OpenScreenAction {
+            watch_for_depletion: true,
+            ..OpenScreenAction::new(
+                /*expected_pixels=*/
+                vec![fuzzy_pixels::copper_ore()],
+                /*action_text=*/ Some(action_text::mine_rocks()),
+                /*mouse_click=*/ MouseClick::Left,
+            )
+        })],
     }
 }
 
@@ -63,6 +68,7 @@ fn travel_to_bank() -> ExplicitActions {
             Box::new(TravelStraight {
                 direction_degrees: 290.0,
                 travel_time: Duration::from_secs(10),
+                fail_when_stuck: false,
             }),
             Box::new(TravelTo::new(
                 /*primary_pixel=*/ fuzzy_pixels::map_icon_bank_yellow(),
@@ -105,7 +111,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.userinput_config);
-    let mut framehandler = FrameHandler::new(config.screen_config);
+    let mut framehandler = FrameHandler::new(config.screen_config, &capturer.frame().unwrap());
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
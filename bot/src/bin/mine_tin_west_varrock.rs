@@ -12,6 +12,7 @@ fn travel_to_bank() -> ExplicitActions {
             Box::new(TravelStraight {
                 direction_degrees: 255.0,
                 travel_time: Duration::from_secs(20),
+                fail_when_stuck: false,
             }),
             Box::new(TravelTo::new(
                 /*primary_pixel=*/ fuzzy_pixels::map_icon_bank_yellow(),
@@ -54,6 +55,7 @@ fn travel_to_mine() -> ExplicitActions {
             Box::new(TravelStraight {
                 direction_degrees: 130.0,
                 travel_time: Duration::from_secs(15),
+                fail_when_stuck: false,
             }),
             Box::new(TravelTo::new(
                 // Items on the ground can occlude parts of the map icon.
@@ -82,6 +84,7 @@ fn travel_to_mine() -> ExplicitActions {
             Box::new(TravelStraight {
                 direction_degrees: 315.0,
                 travel_time: Duration::from_secs(5),
+                fail_when_stuck: false,
             }),
             Box::new(PressMinimapMiddle {}),
         ],
@@ -94,12 +97,15 @@ fn mine_tin() -> ConsumeInventory {
         slot_consumption_waittime: Duration::from_secs(15),
         activity_timeout: Duration::from_secs(10 * 60),
         item_to_consume: inventory_slot_pixels::empty(),
-        actions: vec![Box::new(OpenScreenAction::new(
-            /*expected_pixels=*/
-            vec![fuzzy_pixels::tin_ore()],
-            /*action_text=*/ Some(action_text::mine_rocks()),
-            /*mouse_click=*/ MouseClick::Left,
-        ))],
+        actions: vec![Box::new(OpenScreenAction {
+            watch_for_depletion: true,
+            ..OpenScreenAction::new(
+                /*expected_pixels=*/
+                vec![fuzzy_pixels::tin_ore()],
+                /*action_text=*/ Some(action_text::mine_rocks()),
+                /*mouse_click=*/ MouseClick::Left,
+            )
+        })],
     }
 }
 
@@ -110,7 +116,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.userinput_config);
-    let mut framehandler = FrameHandler::new(config.screen_config);
+    let mut framehandler = FrameHandler::new(config.screen_config, &capturer.frame().unwrap());
 
     println!(
         "\
@@ -77,7 +77,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.userinput_config);
-    let mut framehandler = FrameHandler::new(config.screen_config);
+    let mut framehandler = FrameHandler::new(config.screen_config, &capturer.frame().unwrap());
 
     let reset_actions = ExplicitActions::default_reset();
     let travel_to_bank_actions = travel_to_bank();
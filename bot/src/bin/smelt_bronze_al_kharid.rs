@@ -10,11 +10,6 @@ use userinput::InputBot;
 pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
-
-    #[structopt(long)]
-    pub copper_ore_bank_slot_index: i32,
-    #[structopt(long)]
-    pub tin_ore_bank_slot_index: i32,
 }
 
 fn travel_to_furnace(_config: &Config) -> TravelTo {
@@ -86,7 +81,7 @@ fn travel_to_bank(_config: &Config) -> TravelTo {
     )
 }
 
-fn withdraw_from_bank(config: &Config) -> WithdrawFromBank {
+fn withdraw_from_bank(_config: &Config) -> WithdrawFromBank {
     WithdrawFromBank::new(
         /*bank_pixels=*/
         vec![
@@ -94,18 +89,10 @@ fn withdraw_from_bank(config: &Config) -> WithdrawFromBank {
             fuzzy_pixels::bank_brown2(),
             fuzzy_pixels::bank_brown3(),
         ],
-        /*bank_slot_and_quantity=*/
+        /*quantity_and_item=*/
         vec![
-            (
-                config.copper_ore_bank_slot_index,
-                BankQuantity::X,
-                inventory_slot_pixels::copper_ore_bank(),
-            ),
-            (
-                config.tin_ore_bank_slot_index,
-                BankQuantity::X,
-                inventory_slot_pixels::tin_ore_bank(),
-            ),
+            (BankQuantity::X, inventory_slot_pixels::copper_ore_bank()),
+            (BankQuantity::X, inventory_slot_pixels::tin_ore_bank()),
         ],
     )
 }
@@ -116,7 +103,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
@@ -9,9 +9,6 @@ use userinput::InputBot;
 pub struct Config {
     #[structopt(flatten)]
     pub bot_config: bot::Config,
-
-    #[structopt(long)]
-    pub bronze_bar_bank_slot_index: i32,
 }
 
 fn travel_to_bank(_config: &Config) -> TravelTo {
@@ -40,16 +37,12 @@ fn deposit_in_bank(_config: &Config) -> DepositInBank {
     )
 }
 
-fn withdraw_from_bank(config: &Config) -> WithdrawFromBank {
+fn withdraw_from_bank(_config: &Config) -> WithdrawFromBank {
     WithdrawFromBank::new(
         /*bank_pixels=*/
         vec![fuzzy_pixels::varrock_bank_window1()],
-        /*bank_slot_and_quantity=*/
-        vec![(
-            config.bronze_bar_bank_slot_index,
-            BankQuantity::All,
-            inventory_slot_pixels::bronze_bar_bank(),
-        )],
+        /*quantity_and_item=*/
+        vec![(BankQuantity::All, inventory_slot_pixels::bronze_bar_bank())],
     )
 }
 
@@ -101,7 +94,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut capturer = Capturer::new();
     let mut inputbot = InputBot::new(config.bot_config.userinput_config.clone());
-    let mut framehandler = FrameHandler::new(config.bot_config.screen_config.clone());
+    let mut framehandler = FrameHandler::new(
+        config.bot_config.screen_config.clone(),
+        &capturer.frame().unwrap(),
+    );
     // Starting with the inventory full of uncooked pizzas is an optimization to
     // avoid putting reset between deposit and withdraw.
     println!(
@@ -0,0 +1,117 @@
+use screen::{Capturer, Frame, FrameHandler};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Where we are in a fight, as best we can tell from the healthbars on screen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CombatState {
+    /// Not fighting anything.
+    Idle,
+    /// We have attacked an enemy, but the fight hasn't started yet. Usually
+    /// this means we are walking to the enemy.
+    Engaging,
+    /// The enemy healthbar is shown and the enemy has hitpoints left.
+    Fighting,
+    /// The enemy healthbar is empty. The body takes a moment to disappear, so
+    /// don't click on it.
+    TargetDead,
+    /// Something is hitting us but we aren't fighting it.
+    UnderAttack,
+}
+
+/// Tracks the combat state across frames. The enemy healthbar alone can't
+/// tell us whether we are walking towards an enemy we just clicked on, so the
+/// tracker is told when we attack via 'engage'.
+pub struct CombatTracker {
+    pub state: CombatState,
+
+    /// How long to wait for a fight to start after engaging before assuming
+    /// the attack failed (e.g. the enemy is unreachable).
+    pub engage_timeout: Duration,
+
+    engaged_at: Option<Instant>,
+}
+
+impl CombatTracker {
+    pub fn new(engage_timeout: Duration) -> CombatTracker {
+        CombatTracker {
+            state: CombatState::Idle,
+            engage_timeout,
+            engaged_at: None,
+        }
+    }
+
+    /// Call right after clicking to attack an enemy.
+    pub fn engage(&mut self) {
+        self.engaged_at = Some(Instant::now());
+        self.state = CombatState::Engaging;
+    }
+
+    pub fn update(&mut self, framehandler: &FrameHandler, frame: &impl Frame) -> CombatState {
+        let engaging = match self.engaged_at {
+            Some(time) => time.elapsed() < self.engage_timeout,
+            None => false,
+        };
+        self.state = next_state(
+            framehandler.enemy_health_fraction(frame),
+            framehandler.player_health_fraction(frame).is_some(),
+            engaging,
+        );
+        if self.state != CombatState::Engaging {
+            // Either the fight started or engaging timed out.
+            self.engaged_at = None;
+        }
+        self.state
+    }
+
+    /// Keep updating until we reach one of 'states'. Returns None on timeout.
+    pub fn await_states(
+        &mut self,
+        framehandler: &FrameHandler,
+        capturer: &mut Capturer,
+        states: &[CombatState],
+        timeout: Duration,
+    ) -> Option<CombatState> {
+        let time = Instant::now();
+        while time.elapsed() < timeout {
+            let state = self.update(framehandler, &capturer.frame().unwrap());
+            if states.contains(&state) {
+                return Some(state);
+            }
+            sleep(Duration::from_millis(100));
+        }
+        None
+    }
+}
+
+fn next_state(
+    enemy_health_fraction: Option<f32>,
+    player_healthbar_shown: bool,
+    engaging: bool,
+) -> CombatState {
+    match enemy_health_fraction {
+        Some(fraction) if fraction <= 0.0 => CombatState::TargetDead,
+        Some(_) => CombatState::Fighting,
+        None if engaging => CombatState::Engaging,
+        None if player_healthbar_shown => CombatState::UnderAttack,
+        None => CombatState::Idle,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemy_healthbar_takes_priority() {
+        assert_eq!(next_state(Some(0.5), true, true), CombatState::Fighting);
+        assert_eq!(next_state(Some(0.0), true, false), CombatState::TargetDead);
+    }
+
+    #[test]
+    fn no_enemy_healthbar() {
+        assert_eq!(next_state(None, true, true), CombatState::Engaging);
+        assert_eq!(next_state(None, true, false), CombatState::UnderAttack);
+        assert_eq!(next_state(None, false, false), CombatState::Idle);
+    }
+}
@@ -1,8 +1,12 @@
 pub mod actions;
+pub mod combat;
 pub mod common;
+pub mod route;
 
 pub use actions::*;
+pub use combat::*;
 pub use common::*;
+pub use route::*;
 
 use structopt::StructOpt;
 #[derive(Debug, StructOpt, Clone)]
@@ -0,0 +1,530 @@
+use crate::actions::*;
+use screen::minimap_dots::MinimapDots;
+use screen::motion::MinimapOdometer;
+use screen::{Capturer, Frame, FrameHandler, FuzzyPixel, Locations};
+use std::thread::sleep;
+use std::time::Duration;
+use userinput::InputBot;
+use util::*;
+
+/// Number of consecutive checks without moving before a leg counts as stalled.
+/// Checks are more frequent than game ticks, so this is about 2 ticks.
+const STALLED_CHECKS: i32 = 4;
+
+/// Something identifiable on the minimap, found the same way as in
+/// TravelToOnMinimap.
+#[derive(Clone, Debug)]
+pub struct Landmark {
+    pub primary_pixel: FuzzyPixel,
+    pub check_pixels: Vec<FuzzyPixel>,
+
+    /// (min_angle_degrees, arc_angle_degrees), assuming the camera faces
+    /// north.
+    pub arc_of_interest: (f32, f32),
+}
+
+impl Landmark {
+    /// Position of the landmark on the minimap, if it is shown.
+    pub fn find(
+        &self,
+        frame: &impl Frame,
+        minimap_middle: Position,
+        dots: Option<&MinimapDots>,
+    ) -> Option<Position> {
+        check_map_pixels(
+            frame,
+            &MapSearch {
+                middle: minimap_middle,
+                min_radius: 1,
+                d_radius: Locations::MINIMAP_RADIUS,
+                arc_of_interest: self.arc_of_interest,
+            },
+            self.primary_pixel,
+            &self.check_pixels,
+            dots,
+        )
+    }
+}
+
+/// A single step of a route, walking to 'landmark'.
+#[derive(Clone, Debug)]
+pub struct RouteLeg {
+    pub landmark: Landmark,
+
+    /// We have arrived once the landmark is within this many pixels of the
+    /// middle of the minimap.
+    pub arrival_radius: i32,
+
+    /// Direction to walk in (degrees, as in TravelStraight) while the landmark
+    /// isn't on the minimap. If None the leg fails when the landmark can't be
+    /// seen.
+    pub fallback_heading: Option<f32>,
+
+    /// Waypoints to walk through before looking for the landmark, each a
+    /// direction (degrees, as in TravelStraight) and how long to walk that
+    /// way. For landmarks which also match around where the leg starts. Only
+    /// used going forward, in reverse we head straight for the start. The leg
+    /// fails if we get stuck on the way to a waypoint.
+    pub waypoints: Vec<(f32, Duration)>,
+
+    /// A door or gate along the leg which may be closed. Opened if we stop
+    /// moving before arriving.
+    pub obstacle: Option<Obstacle>,
+
+    /// Set for legs which go to another floor.
+    pub floor_change: Option<FloorChange>,
+}
+
+/// Climbing to another floor as part of a route leg.
+#[derive(Clone, Debug)]
+pub struct FloorChange {
+    pub climbable: Climbable,
+    pub direction: FloorDirection,
+
+    /// Climb as soon as the leg starts, instead of after reaching the landmark.
+    /// The landmark is then on the new floor.
+    pub before_walking: bool,
+
+    /// Where the climb leaves us on the new floor. Landmarks on the floor we
+    /// left aren't on the minimap any more, so when the route is walked in
+    /// reverse this is where we walk to in order to climb back.
+    pub landing: Landmark,
+    pub landing_arrival_radius: i32,
+}
+
+/// An ordered set of legs, starting from 'origin'. The origin is only used
+/// when following the route in reverse.
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub origin: Landmark,
+    pub origin_arrival_radius: i32,
+    pub legs: Vec<RouteLeg>,
+}
+
+impl Route {
+    /// The same route walked from the last landmark back to the origin.
+    pub fn reversed(&self) -> Route {
+        // Walking back along leg i takes us to where leg i started. That is
+        // the landmark of the leg before it (or the origin), unless the leg
+        // before it ended by climbing, in which case it is that climb's
+        // landing.
+        let mut starts: Vec<(Landmark, i32)> =
+            vec![(self.origin.clone(), self.origin_arrival_radius)];
+        starts.extend(self.legs.iter().map(|leg| match &leg.floor_change {
+            Some(floor_change) if !floor_change.before_walking => (
+                floor_change.landing.clone(),
+                floor_change.landing_arrival_radius,
+            ),
+            _ => (leg.landmark.clone(), leg.arrival_radius),
+        }));
+
+        let origin = starts.pop().unwrap();
+        let legs = self
+            .legs
+            .iter()
+            .zip(starts)
+            .rev()
+            .map(|(leg, (start, start_arrival_radius))| {
+                let fallback_heading = leg
+                    .fallback_heading
+                    .map(|heading| (heading + 180.0).rem_euclid(360.0));
+                let (landmark, arrival_radius, floor_change) = match &leg.floor_change {
+                    None => (start, start_arrival_radius, None),
+                    // Walking to a ladder and climbing it is undone by climbing
+                    // back down and then walking away. The climb lands us
+                    // where the original leg walked to.
+                    Some(floor_change) if !floor_change.before_walking => (
+                        start,
+                        start_arrival_radius,
+                        Some(FloorChange {
+                            climbable: floor_change.climbable.clone(),
+                            direction: floor_change.direction.opposite(),
+                            before_walking: true,
+                            landing: leg.landmark.clone(),
+                            landing_arrival_radius: leg.arrival_radius,
+                        }),
+                    ),
+                    // Climbing and then walking away is undone by walking back
+                    // to where we landed and climbing back down to the start.
+                    Some(floor_change) => (
+                        floor_change.landing.clone(),
+                        floor_change.landing_arrival_radius,
+                        Some(FloorChange {
+                            climbable: floor_change.climbable.clone(),
+                            direction: floor_change.direction.opposite(),
+                            before_walking: false,
+                            landing: start,
+                            landing_arrival_radius: start_arrival_radius,
+                        }),
+                    ),
+                };
+                RouteLeg {
+                    landmark,
+                    arrival_radius,
+                    fallback_heading,
+                    waypoints: vec![],
+                    obstacle: leg.obstacle.clone(),
+                    floor_change,
+                }
+            })
+            .collect();
+
+        Route {
+            origin: origin.0,
+            origin_arrival_radius: origin.1,
+            legs,
+        }
+    }
+}
+
+/// Walk each leg of a route in order. A failed leg is retried before giving
+/// up on the route.
+pub struct FollowRoute {
+    pub route: Route,
+
+    /// Walk the route from the last landmark back to the origin.
+    pub reverse: bool,
+
+    /// Number of extra attempts given to each leg.
+    pub leg_retries: i32,
+
+    /// Max time to spend on a single attempt at a leg.
+    pub leg_timeout: Duration,
+
+    pub try_to_run: bool,
+}
+
+impl FollowRoute {
+    pub fn new(route: Route) -> FollowRoute {
+        FollowRoute {
+            route,
+            reverse: false,
+            leg_retries: 2,
+            leg_timeout: Duration::from_secs(60),
+            try_to_run: true,
+        }
+    }
+    pub fn reversed(route: Route) -> FollowRoute {
+        FollowRoute {
+            reverse: true,
+            ..FollowRoute::new(route)
+        }
+    }
+
+    fn find_landmark(
+        &self,
+        landmark: &Landmark,
+        framehandler: &FrameHandler,
+        capturer: &mut Capturer,
+    ) -> Option<Position> {
+        let frame = capturer.frame().unwrap();
+        landmark.find(
+            &framehandler.calibrated(&frame),
+            framehandler.locations.minimap_middle(),
+            Some(&framehandler.find_minimap_dots(&frame)),
+        )
+    }
+
+    fn change_floor(
+        &self,
+        floor_change: &FloorChange,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        let climb = ChangeFloor::new(floor_change.climbable.clone(), floor_change.direction);
+        (0..=self.leg_retries).any(|_| climb.do_action(inputbot, framehandler, capturer))
+    }
+
+    fn follow_leg(
+        &self,
+        leg: &RouteLeg,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        let minimap_middle = framehandler.locations.minimap_middle();
+        let time = std::time::Instant::now();
+        while time.elapsed() < self.leg_timeout {
+            let pos = match self.find_landmark(&leg.landmark, framehandler, capturer) {
+                Some(pos) => pos,
+                None => match leg.fallback_heading {
+                    Some(direction_degrees) => {
+                        println!("Landmark not found, heading {}", direction_degrees);
+                        let travel_straight = TravelStraight {
+                            direction_degrees,
+                            travel_time: Duration::from_secs(3),
+                            fail_when_stuck: false,
+                        };
+                        inputbot.move_to(&travel_straight.get_minimap_pos(framehandler));
+                        inputbot.left_click();
+                        sleep(travel_straight.travel_time);
+                        continue;
+                    }
+                    None => return false,
+                },
+            };
+            if (pos - minimap_middle).distance() <= leg.arrival_radius {
+                return true;
+            }
+
+            inputbot.move_to(&pos);
+            inputbot.left_click();
+
+            // Wait until we reach the landmark, or stop seeing it. If we stop
+            // moving on the way there may be a closed obstacle in the way.
+            let mut odometer = MinimapOdometer::new();
+            let mut num_still_checks = 0;
+            let walk_time = std::time::Instant::now();
+            while walk_time.elapsed() < Duration::from_secs(15) {
+                sleep(Duration::from_millis(300));
+                match self.find_landmark(&leg.landmark, framehandler, capturer) {
+                    Some(pos) if (pos - minimap_middle).distance() <= leg.arrival_radius => {
+                        return true;
+                    }
+                    Some(_) => (),
+                    None => break,
+                }
+
+                match odometer.update(&capturer.frame().unwrap(), minimap_middle) {
+                    Some(motion) if motion.is_confident() && motion.distance_tiles() == 0.0 => {
+                        num_still_checks += 1
+                    }
+                    _ => num_still_checks = 0,
+                }
+                if num_still_checks >= STALLED_CHECKS {
+                    if let Some(obstacle) = &leg.obstacle {
+                        println!("Stalled, trying to open {:?}", obstacle.kind);
+                        OpenObstacle::new(obstacle.clone()).do_action(
+                            inputbot,
+                            framehandler,
+                            capturer,
+                        );
+                    }
+                    // Click on the landmark again.
+                    break;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Action for FollowRoute {
+    fn do_action(
+        &self,
+        inputbot: &mut InputBot,
+        framehandler: &mut FrameHandler,
+        capturer: &mut Capturer,
+    ) -> bool {
+        println!("FollowRoute [ reverse: {}]", self.reverse);
+        let route = if self.reverse {
+            self.route.reversed()
+        } else {
+            self.route.clone()
+        };
+
+        // Landmark arcs and headings assume the camera faces north.
+        PressCompass {}.do_action(inputbot, framehandler, capturer);
+        if self.try_to_run {
+            MaybeToggleRunning::run().do_action(inputbot, framehandler, capturer);
+        }
+
+        for (i, leg) in route.legs.iter().enumerate() {
+            if let Some(floor_change) = leg.floor_change.as_ref().filter(|f| f.before_walking) {
+                if !self.change_floor(floor_change, inputbot, framehandler, capturer) {
+                    println!("Failed to change floor on route leg {}", i);
+                    return false;
+                }
+            }
+
+            for &(direction_degrees, travel_time) in leg.waypoints.iter() {
+                let travel_straight = TravelStraight {
+                    direction_degrees,
+                    travel_time,
+                    fail_when_stuck: true,
+                };
+                if !travel_straight.do_action(inputbot, framehandler, capturer) {
+                    println!("Failed to reach a waypoint on route leg {}", i);
+                    return false;
+                }
+            }
+            // TravelStraight walks to keep its timing.
+            if self.try_to_run && !leg.waypoints.is_empty() {
+                MaybeToggleRunning::run().do_action(inputbot, framehandler, capturer);
+            }
+
+            let mut arrived = false;
+            for attempt in 0..=self.leg_retries {
+                println!("Route leg {} [ attempt: {}]", i, attempt);
+                if self.follow_leg(leg, inputbot, framehandler, capturer) {
+                    arrived = true;
+                    break;
+                }
+            }
+            if !arrived {
+                println!("Failed route leg {}", i);
+                return false;
+            }
+
+            if let Some(floor_change) = leg.floor_change.as_ref().filter(|f| !f.before_walking) {
+                if !self.change_floor(floor_change, inputbot, framehandler, capturer) {
+                    println!("Failed to change floor on route leg {}", i);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use screen::fuzzy_pixels;
+
+    fn landmark(primary_pixel: FuzzyPixel) -> Landmark {
+        Landmark {
+            primary_pixel,
+            check_pixels: vec![],
+            arc_of_interest: (0.0, 360.0),
+        }
+    }
+
+    fn ladder_up(before_walking: bool, landing: FuzzyPixel, radius: i32) -> FloorChange {
+        FloorChange {
+            climbable: Climbable {
+                kind: ClimbableKind::Ladder,
+                expected_pixels: vec![],
+            },
+            direction: FloorDirection::Up,
+            before_walking,
+            landing: landmark(landing),
+            landing_arrival_radius: radius,
+        }
+    }
+
+    fn leg(primary_pixel: FuzzyPixel, arrival_radius: i32) -> RouteLeg {
+        RouteLeg {
+            landmark: landmark(primary_pixel),
+            arrival_radius,
+            fallback_heading: None,
+            waypoints: vec![],
+            obstacle: None,
+            floor_change: None,
+        }
+    }
+
+    #[test]
+    fn reverse_route() {
+        let route = Route {
+            origin: landmark(fuzzy_pixels::map_icon_bank_yellow()),
+            origin_arrival_radius: 5,
+            legs: vec![
+                RouteLeg {
+                    fallback_heading: Some(300.0),
+                    waypoints: vec![(270.0, Duration::from_secs(5))],
+                    obstacle: Some(Obstacle::al_kharid_door()),
+                    ..leg(fuzzy_pixels::map_icon_anvil_gray(), 10)
+                },
+                // Walk to the ladder and climb it.
+                RouteLeg {
+                    floor_change: Some(ladder_up(
+                        /*before_walking=*/ false,
+                        fuzzy_pixels::map_icon_pickaxe_light_gray(),
+                        8,
+                    )),
+                    ..leg(fuzzy_pixels::black(), 15)
+                },
+                leg(fuzzy_pixels::map_icon_fish_dark_blue(), 12),
+                // Climb another ladder and walk away from it.
+                RouteLeg {
+                    floor_change: Some(ladder_up(
+                        /*before_walking=*/ true,
+                        fuzzy_pixels::map_icon_fish_medium_blue(),
+                        7,
+                    )),
+                    ..leg(fuzzy_pixels::map_icon_cookrange_light_brown(), 6)
+                },
+            ],
+        };
+
+        let reversed = route.reversed();
+        assert_eq!(
+            reversed.origin.primary_pixel,
+            fuzzy_pixels::map_icon_cookrange_light_brown()
+        );
+        assert_eq!(reversed.origin_arrival_radius, 6);
+        assert_eq!(reversed.legs.len(), 4);
+
+        // Walk back to where the top ladder left us, and climb down it.
+        assert_eq!(
+            reversed.legs[0].landmark.primary_pixel,
+            fuzzy_pixels::map_icon_fish_medium_blue()
+        );
+        assert_eq!(reversed.legs[0].arrival_radius, 7);
+        let floor_change = reversed.legs[0].floor_change.as_ref().unwrap();
+        assert_eq!(floor_change.direction, FloorDirection::Down);
+        assert!(!floor_change.before_walking);
+        assert_eq!(
+            floor_change.landing.primary_pixel,
+            fuzzy_pixels::map_icon_fish_dark_blue()
+        );
+
+        // Walk to where the bottom ladder left us, not to the ladder on the
+        // floor below.
+        assert_eq!(
+            reversed.legs[1].landmark.primary_pixel,
+            fuzzy_pixels::map_icon_pickaxe_light_gray()
+        );
+        assert_eq!(reversed.legs[1].arrival_radius, 8);
+        assert!(reversed.legs[1].floor_change.is_none());
+
+        // Climb down the bottom ladder and walk away from it.
+        assert_eq!(
+            reversed.legs[2].landmark.primary_pixel,
+            fuzzy_pixels::map_icon_anvil_gray()
+        );
+        assert_eq!(reversed.legs[2].arrival_radius, 10);
+        assert_eq!(reversed.legs[2].fallback_heading, None);
+        assert!(reversed.legs[2].obstacle.is_none());
+        let floor_change = reversed.legs[2].floor_change.as_ref().unwrap();
+        assert_eq!(floor_change.direction, FloorDirection::Down);
+        assert!(floor_change.before_walking);
+        assert_eq!(floor_change.landing.primary_pixel, fuzzy_pixels::black());
+        assert_eq!(floor_change.landing_arrival_radius, 15);
+
+        assert_eq!(
+            reversed.legs[3].landmark.primary_pixel,
+            fuzzy_pixels::map_icon_bank_yellow()
+        );
+        assert_eq!(reversed.legs[3].arrival_radius, 5);
+        assert_eq!(reversed.legs[3].fallback_heading, Some(120.0));
+        assert!(reversed.legs[3].waypoints.is_empty());
+        assert_eq!(
+            reversed.legs[3].obstacle.as_ref().unwrap().kind,
+            ObstacleKind::Door
+        );
+
+        // Reversing twice gets us back to the original route.
+        let twice = reversed.reversed();
+        assert_eq!(twice.origin.primary_pixel, route.origin.primary_pixel);
+        for (leg, original) in twice.legs.iter().zip(route.legs.iter()) {
+            assert_eq!(leg.landmark.primary_pixel, original.landmark.primary_pixel);
+            assert_eq!(leg.arrival_radius, original.arrival_radius);
+            assert_eq!(leg.fallback_heading, original.fallback_heading);
+            match (&leg.floor_change, &original.floor_change) {
+                (None, None) => (),
+                (Some(floor_change), Some(original)) => {
+                    assert_eq!(floor_change.direction, original.direction);
+                    assert_eq!(floor_change.before_walking, original.before_walking);
+                    assert_eq!(
+                        floor_change.landing.primary_pixel,
+                        original.landing.primary_pixel
+                    );
+                }
+                _ => panic!("Floor change lost when reversing twice"),
+            }
+        }
+    }
+}
@@ -158,6 +158,28 @@ mod letters {
         }
     }
 
+    pub fn upper_g() -> Character {
+        Character {
+            width: 9,
+
+            // Delta from the top left corner of the letters box. Not yet
+            // checked against a screenshot of an action with a G in it.
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 7 },
+                DeltaPosition { dx: 1, dy: 3 },
+                DeltaPosition { dx: 1, dy: 10 },
+                DeltaPosition { dx: 3, dy: 2 },
+                DeltaPosition { dx: 3, dy: 11 },
+                DeltaPosition { dx: 4, dy: 6 },
+                DeltaPosition { dx: 5, dy: 7 },
+                DeltaPosition { dx: 6, dy: 3 },
+                DeltaPosition { dx: 6, dy: 9 },
+            ],
+
+            display: "G",
+        }
+    }
+
     pub fn upper_k() -> Character {
         Character {
             width: 9,
@@ -177,6 +199,23 @@ mod letters {
         }
     }
 
+    pub fn upper_l() -> Character {
+        Character {
+            width: 7,
+
+            // Delta from the top left corner of the letters box.
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 0, dy: 6 },
+                DeltaPosition { dx: 0, dy: 10 },
+                DeltaPosition { dx: 2, dy: 11 },
+                DeltaPosition { dx: 4, dy: 11 },
+            ],
+
+            display: "L",
+        }
+    }
+
     pub fn upper_m() -> Character {
         Character {
             width: 10,
@@ -1076,6 +1115,130 @@ pub fn open_door() -> Text {
     }
 }
 
+pub fn open_gate() -> Text {
+    Text {
+        letters: vec![
+            (upper_o(), action_text_white()),
+            (lower_p(), action_text_white()),
+            (lower_e(), action_text_white()),
+            (lower_n(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_g(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_t(), action_text_blue()),
+            (lower_e(), action_text_blue()),
+            (space(), action_text_white()),
+            (forward_slash(), action_text_white()),
+        ],
+    }
+}
+
+pub fn climb_up_ladder() -> Text {
+    Text {
+        letters: vec![
+            (upper_c(), action_text_white()),
+            (lower_l(), action_text_white()),
+            (lower_i(), action_text_white()),
+            (lower_m(), action_text_white()),
+            (lower_b(), action_text_white()),
+            (hyphen(), action_text_white()),
+            (lower_u(), action_text_white()),
+            (lower_p(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_l(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_d(), action_text_blue()),
+            (lower_d(), action_text_blue()),
+            (lower_e(), action_text_blue()),
+            (lower_r(), action_text_blue()),
+            (space(), action_text_white()),
+            (forward_slash(), action_text_white()),
+        ],
+    }
+}
+
+pub fn climb_down_ladder() -> Text {
+    Text {
+        letters: vec![
+            (upper_c(), action_text_white()),
+            (lower_l(), action_text_white()),
+            (lower_i(), action_text_white()),
+            (lower_m(), action_text_white()),
+            (lower_b(), action_text_white()),
+            (hyphen(), action_text_white()),
+            (lower_d(), action_text_white()),
+            (lower_o(), action_text_white()),
+            (lower_w(), action_text_white()),
+            (lower_n(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_l(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_d(), action_text_blue()),
+            (lower_d(), action_text_blue()),
+            (lower_e(), action_text_blue()),
+            (lower_r(), action_text_blue()),
+            (space(), action_text_white()),
+            (forward_slash(), action_text_white()),
+        ],
+    }
+}
+
+pub fn climb_up_staircase() -> Text {
+    Text {
+        letters: vec![
+            (upper_c(), action_text_white()),
+            (lower_l(), action_text_white()),
+            (lower_i(), action_text_white()),
+            (lower_m(), action_text_white()),
+            (lower_b(), action_text_white()),
+            (hyphen(), action_text_white()),
+            (lower_u(), action_text_white()),
+            (lower_p(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_s(), action_text_blue()),
+            (lower_t(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_i(), action_text_blue()),
+            (lower_r(), action_text_blue()),
+            (lower_c(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_s(), action_text_blue()),
+            (lower_e(), action_text_blue()),
+            (space(), action_text_white()),
+            (forward_slash(), action_text_white()),
+        ],
+    }
+}
+
+pub fn climb_down_staircase() -> Text {
+    Text {
+        letters: vec![
+            (upper_c(), action_text_white()),
+            (lower_l(), action_text_white()),
+            (lower_i(), action_text_white()),
+            (lower_m(), action_text_white()),
+            (lower_b(), action_text_white()),
+            (hyphen(), action_text_white()),
+            (lower_d(), action_text_white()),
+            (lower_o(), action_text_white()),
+            (lower_w(), action_text_white()),
+            (lower_n(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_s(), action_text_blue()),
+            (lower_t(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_i(), action_text_blue()),
+            (lower_r(), action_text_blue()),
+            (lower_c(), action_text_blue()),
+            (lower_a(), action_text_blue()),
+            (lower_s(), action_text_blue()),
+            (lower_e(), action_text_blue()),
+            (space(), action_text_white()),
+            (forward_slash(), action_text_white()),
+        ],
+    }
+}
+
 pub fn chop_down_tree() -> Text {
     Text {
         letters: vec![
@@ -1178,6 +1341,65 @@ pub fn small_net_fishing_spot() -> Text {
     }
 }
 
+// Ground items are named in orange.
+pub fn take_cowhide() -> Text {
+    Text {
+        letters: vec![
+            (start(), action_text_white()),
+            (upper_t(), action_text_white()),
+            (lower_a(), action_text_white()),
+            (lower_k(), action_text_white()),
+            (lower_e(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_c(), action_text_orange()),
+            (lower_o(), action_text_orange()),
+            (lower_w(), action_text_orange()),
+            (lower_h(), action_text_orange()),
+            (lower_i(), action_text_orange()),
+            (lower_d(), action_text_orange()),
+            (lower_e(), action_text_orange()),
+        ],
+    }
+}
+
+pub fn take_bones() -> Text {
+    Text {
+        letters: vec![
+            (start(), action_text_white()),
+            (upper_t(), action_text_white()),
+            (lower_a(), action_text_white()),
+            (lower_k(), action_text_white()),
+            (lower_e(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_b(), action_text_orange()),
+            (lower_o(), action_text_orange()),
+            (lower_n(), action_text_orange()),
+            (lower_e(), action_text_orange()),
+            (lower_s(), action_text_orange()),
+        ],
+    }
+}
+
+pub fn take_feather() -> Text {
+    Text {
+        letters: vec![
+            (start(), action_text_white()),
+            (upper_t(), action_text_white()),
+            (lower_a(), action_text_white()),
+            (lower_k(), action_text_white()),
+            (lower_e(), action_text_white()),
+            (space(), action_text_white()),
+            (upper_f(), action_text_orange()),
+            (lower_e(), action_text_orange()),
+            (lower_a(), action_text_orange()),
+            (lower_t(), action_text_orange()),
+            (lower_h(), action_text_orange()),
+            (lower_e(), action_text_orange()),
+            (lower_r(), action_text_orange()),
+        ],
+    }
+}
+
 /// Check if the action described in the top left matches what we want to be
 /// doing.
 ///
@@ -1198,6 +1420,22 @@ pub fn check_action_letters(
     action_text.letters.len() > 10 * num_letter_mistmatches
 }
 
+/// Check for an entry in the right click menu. Entries are the same as the
+/// action text without the trailing " /".
+pub fn check_menu_letters(
+    frame: &impl Frame,
+    action_text: &Text,
+    entry_top_left: Position,
+) -> bool {
+    let mut letters = &action_text.letters[..];
+    if matches!(letters.last(), Some((letter, _)) if letter.display == "/") {
+        letters = &letters[..letters.len() - 2];
+    }
+    let num_letter_mistmatches =
+        check_action_letters_impl(frame, letters, entry_top_left, &[0, -1, 1, -2, 2]);
+    letters.len() > 10 * num_letter_mistmatches
+}
+
 /// Search all the charachters until the next space (action letter with no
 /// checkpoints). Then recursively call to this function from that point varying
 /// the x_offset passed (aka the size of the space between words).
@@ -20,7 +20,7 @@ fn main() {
     dbg!(&config);
 
     let mut capturer = screen::Capturer::new();
-    let screenhandler = screen::FrameHandler::new(config.screen_config);
+    let screenhandler = screen::FrameHandler::new(config.screen_config, &capturer.frame().unwrap());
 
     let text = &action_text::attack_al_kharid_warrior();
 
@@ -0,0 +1,65 @@
+/// Estimate how this client's colors differ from those in colors.rs and
+/// print how the fixed UI colors are shifted. Pass --calibrate-colors to the
+/// bots to apply the same estimate at startup.
+///
+/// Open the inventory and chatbox before running for the best estimate.
+use screen::calibration::ColorCalibration;
+use screen::{fuzzy_pixels, Capturer, Frame, FrameHandler, OwnedFrame};
+use std::error::Error;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Config {
+    #[structopt(flatten)]
+    pub screen_config: screen::Config,
+
+    #[structopt(
+        long,
+        about = "Screenshot of the game, as saved by Frame::save. Captures the \
+                 screen if not given."
+    )]
+    pub screenshot_fpath: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args();
+    dbg!(&config);
+
+    let frame = match &config.screenshot_fpath {
+        Some(fpath) => OwnedFrame::load(fpath),
+        None => Capturer::new().frame().unwrap().to_owned(),
+    };
+    let framehandler = FrameHandler::new(config.screen_config.clone(), &frame);
+    let calibration = ColorCalibration::estimate(&frame, &framehandler.locations);
+    let calibration = match calibration {
+        Some(calibration) => calibration,
+        None => {
+            println!("Unable to calibrate colors.");
+            return Ok(());
+        }
+    };
+    dbg!(&calibration);
+
+    for (name, fuzzy_pixel) in [
+        ("inventory_background", fuzzy_pixels::inventory_background()),
+        (
+            "inventory_icon_background_open",
+            fuzzy_pixels::inventory_icon_background_open(),
+        ),
+        (
+            "chatbox_corner_top_left",
+            fuzzy_pixels::chatbox_corner_top_left(),
+        ),
+        (
+            "chatbox_corner_bottom_right",
+            fuzzy_pixels::chatbox_corner_bottom_right(),
+        ),
+        ("bank_border_right", fuzzy_pixels::bank_border_right()),
+    ]
+    .iter()
+    {
+        println!("{}: {:?}", name, calibration.adjust(fuzzy_pixel));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+/// Find the game screen so that it doesn't need to be measured by hand.
+/// Prints the flags to pass to the bots. Open the inventory before running.
+use screen::{format_position, viewport, Capturer, OwnedFrame};
+use std::error::Error;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Config {
+    #[structopt(
+        long,
+        about = "Screenshot of the game, as saved by Frame::save. Captures the \
+                 screen if not given."
+    )]
+    pub screenshot_fpath: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args();
+    dbg!(&config);
+
+    let found = match &config.screenshot_fpath {
+        Some(fpath) => {
+            viewport::find_screen_config(&OwnedFrame::load(fpath), /*layout_mode=*/ None)
+        }
+        None => {
+            let mut capturer = Capturer::new();
+            let frame = capturer.frame().unwrap();
+            viewport::find_screen_config(&frame, /*layout_mode=*/ None)
+        }
+    };
+    match found {
+        Some(found) => println!(
+            "--screen-top-left {} --screen-bottom-right {} --layout {}",
+            format_position(found.screen_top_left.unwrap()),
+            format_position(found.screen_bottom_right.unwrap()),
+            found.layout.as_deref().unwrap(),
+        ),
+        None => println!("Unable to find the game screen."),
+    }
+
+    Ok(())
+}
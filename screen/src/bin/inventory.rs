@@ -11,7 +11,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     dbg!(&config);
 
     let mut capturer = Capturer::new();
-    let framehandler = FrameHandler::new(config.clone());
+    let framehandler = FrameHandler::new(config.clone(), &capturer.frame().unwrap());
     let frame = capturer.frame().unwrap();
 
     // To print out an inventory slot, call check_inventory_slot and uncomment
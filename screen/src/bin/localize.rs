@@ -0,0 +1,51 @@
+/// Run localization offline against a saved screenshot. Useful for checking a
+/// reference map before using it in a bot.
+use screen::localization::ReferenceMap;
+use screen::{FrameHandler, OwnedFrame};
+use std::error::Error;
+use structopt::StructOpt;
+use util::*;
+
+#[derive(Debug, StructOpt)]
+pub struct Config {
+    #[structopt(flatten)]
+    pub screen_config: screen::Config,
+
+    #[structopt(long, about = "Screenshot of the game, as saved by Frame::save.")]
+    pub screenshot_fpath: String,
+
+    #[structopt(long, about = "Reference map at minimap scale, north up.")]
+    pub reference_map_fpath: String,
+
+    #[structopt(long, about = "World tile at the top left of the reference map (x,y).")]
+    pub reference_map_top_left_tile: Position,
+
+    #[structopt(
+        long,
+        about = "Camera yaw when the screenshot was taken, clockwise from north.",
+        default_value = "0.0"
+    )]
+    pub yaw_degrees: f32,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::from_args();
+    dbg!(&config);
+
+    let frame = OwnedFrame::load(&config.screenshot_fpath);
+    let framehandler = FrameHandler::new(config.screen_config.clone(), &frame);
+    let reference = ReferenceMap::load(
+        &config.reference_map_fpath,
+        config.reference_map_top_left_tile,
+    );
+
+    let localization = reference.localize(
+        &frame,
+        framehandler.locations.minimap_middle(),
+        config.yaw_degrees,
+        /*search=*/ None,
+    );
+    dbg!(localization);
+
+    Ok(())
+}
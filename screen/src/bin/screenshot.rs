@@ -1,5 +1,5 @@
 // From scrap github repo. Here for my convenience.
-use screen::{pixels, Capturer, Frame, FrameHandler, Locations, OwnedFrame, Pixel};
+use screen::{pixels, Capturer, Frame, FrameHandler, Locations, Orb, OwnedFrame, Pixel};
 use structopt::StructOpt;
 use util::*;
 
@@ -64,6 +64,14 @@ fn marked_open_screen(cap: &mut Capturer, screenhandler: &FrameHandler) -> Owned
     surrounding_box(&mut frame, &screenhandler.locations.worldmap_icon());
     surrounding_box(&mut frame, &screenhandler.locations.run_icon());
     surrounding_box(&mut frame, &screenhandler.locations.compass_icon());
+    for orb in &[Orb::Hitpoints, Orb::Prayer, Orb::Run, Orb::SpecialAttack] {
+        dbg!(orb, screenhandler.orb_value(&frame, *orb));
+        surrounding_box(&mut frame, &screenhandler.locations.orb_icon(*orb));
+        frame.draw_red_box(
+            &screenhandler.locations.orb_text_top_left(*orb),
+            &Locations::ORB_TEXT_DIMENSIONS,
+        );
+    }
     surrounding_box(
         &mut frame,
         &screenhandler.locations.inventory_icon_background(),
@@ -196,6 +204,7 @@ fn marked_worldmap(cap: &mut Capturer, screenhandler: &FrameHandler) -> OwnedFra
 
 fn marked_inventories(cap: &mut Capturer, screenhandler: &FrameHandler) -> OwnedFrame {
     let mut frame = cap.frame().unwrap().to_owned();
+    dbg!(screen::layout::solve_layout(&frame, &screenhandler.locations).mismatches());
     dbg!(screenhandler.is_bank_open(&frame));
     dbg!(screenhandler.is_bank_quantity_all(&frame));
     dbg!(screenhandler.is_bank_quantity_one(&frame));
@@ -231,7 +240,7 @@ fn marked_inventories(cap: &mut Capturer, screenhandler: &FrameHandler) -> Owned
         &screenhandler.locations.bank_dimensions(),
     );
 
-    for i in 0..Locations::NUM_BANK_SLOTS {
+    for i in 0..screenhandler.locations.num_bank_slots() {
         surrounding_box(&mut frame, &screenhandler.locations.bank_slot_center(i));
     }
     surrounding_box(
@@ -261,7 +270,7 @@ fn main() {
     dbg!(&config);
 
     let mut capturer = screen::Capturer::new();
-    let screenhandler = screen::FrameHandler::new(config.screen_config);
+    let screenhandler = screen::FrameHandler::new(config.screen_config, &capturer.frame().unwrap());
 
     let frame = marked_open_screen(&mut capturer, &screenhandler);
     let mut ofpath = config.out_dir.clone();
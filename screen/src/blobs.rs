@@ -0,0 +1,291 @@
+/// Find targets on screen as groups of connected matching pixels.
+///
+/// Searching for a single matching pixel often lands on the edge of a target or
+/// on a stray pixel elsewhere which happens to match. Grouping the matches
+/// lets us ignore the noise and aim for the middle of the target.
+use crate::types::*;
+use crate::Frame;
+use util::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blob {
+    /// Number of matching pixels.
+    pub area: usize,
+
+    /// Bounding box of the blob.
+    pub top_left: Position,
+    pub dimensions: DeltaPosition,
+
+    /// Average position of the pixels in the blob. Not necessarily part of
+    /// the blob itself, e.g. for a ring shaped blob.
+    pub centroid: Position,
+}
+
+/// Order in which blobs are returned, best first.
+#[derive(Debug, Clone, Copy)]
+pub enum BlobRanking {
+    Largest,
+    ClosestTo(Position),
+}
+
+/// Find all groups of pixels matching any of 'fuzzy_pixels' within the box at
+/// 'top_left' with 'dimensions'. Pixels are connected to all 8 of their
+/// neighbors. Blobs smaller than 'min_area' are dropped.
+pub fn find_blobs(
+    frame: &impl Frame,
+    fuzzy_pixels: &[impl PixelMatcher],
+    top_left: &Position,
+    dimensions: &DeltaPosition,
+    min_area: usize,
+    ranking: BlobRanking,
+) -> Vec<Blob> {
+    let (width, height) = (dimensions.dx.max(0) as usize, dimensions.dy.max(0) as usize);
+    let index = |x: i32, y: i32| y as usize * width + x as usize;
+
+    let mut matches = vec![false; width * height];
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let pixel = frame.get_pixel(&(*top_left + DeltaPosition { dx: x, dy: y }));
+            matches[index(x, y)] = fuzzy_pixels.iter().any(|fp| fp.matches(&pixel));
+        }
+    }
+
+    let mut visited = vec![false; width * height];
+    let mut blobs = Vec::new();
+    for start_y in 0..height as i32 {
+        for start_x in 0..width as i32 {
+            if !matches[index(start_x, start_y)] || visited[index(start_x, start_y)] {
+                continue;
+            }
+
+            // Flood fill the component containing this pixel.
+            visited[index(start_x, start_y)] = true;
+            let mut stack = vec![(start_x, start_y)];
+            let (mut area, mut sum_x, mut sum_y) = (0, 0, 0);
+            let (mut min_x, mut min_y, mut max_x, mut max_y) = (start_x, start_y, start_x, start_y);
+            while let Some((x, y)) = stack.pop() {
+                area += 1;
+                sum_x += x as i64;
+                sum_y += y as i64;
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let (nx, ny) = (x + dx, y + dy);
+                        if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                            continue;
+                        }
+                        if matches[index(nx, ny)] && !visited[index(nx, ny)] {
+                            visited[index(nx, ny)] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+
+            if area < min_area {
+                continue;
+            }
+            blobs.push(Blob {
+                area,
+                top_left: *top_left
+                    + DeltaPosition {
+                        dx: min_x,
+                        dy: min_y,
+                    },
+                dimensions: DeltaPosition {
+                    dx: max_x - min_x + 1,
+                    dy: max_y - min_y + 1,
+                },
+                centroid: *top_left
+                    + DeltaPosition {
+                        dx: (sum_x / area as i64) as i32,
+                        dy: (sum_y / area as i64) as i32,
+                    },
+            });
+        }
+    }
+
+    rank_blobs(&mut blobs, ranking);
+    blobs
+}
+
+/// Sort 'blobs' so the best according to 'ranking' comes first.
+pub fn rank_blobs(blobs: &mut [Blob], ranking: BlobRanking) {
+    match ranking {
+        BlobRanking::Largest => blobs.sort_by_key(|blob| std::cmp::Reverse(blob.area)),
+        BlobRanking::ClosestTo(pos) => blobs.sort_by_key(|blob| (blob.centroid - pos).distance()),
+    }
+}
+
+/// Merge blobs whose bounding boxes are within 'max_gap' of each other, such
+/// as the letters of a word which are drawn as separate blobs. Merged blobs
+/// are returned in no particular order.
+pub fn merge_nearby_blobs(blobs: Vec<Blob>, max_gap: DeltaPosition) -> Vec<Blob> {
+    // Gap between the ranges [a0, a1] and [b0, b1], 0 if they overlap.
+    let gap = |a0: i32, a1: i32, b0: i32, b1: i32| (a0.max(b0) - a1.min(b1) - 1).max(0);
+    let is_near = |a: &Blob, b: &Blob| {
+        let a_end = a.top_left + a.dimensions;
+        let b_end = b.top_left + b.dimensions;
+        gap(a.top_left.x, a_end.x - 1, b.top_left.x, b_end.x - 1) <= max_gap.dx
+            && gap(a.top_left.y, a_end.y - 1, b.top_left.y, b_end.y - 1) <= max_gap.dy
+    };
+
+    let mut merged = Vec::<Blob>::new();
+    for mut blob in blobs {
+        // Absorb every existing group this blob bridges to, since it may
+        // connect groups which were apart until now.
+        let mut i = 0;
+        while i < merged.len() {
+            if is_near(&merged[i], &blob) {
+                let other = merged.swap_remove(i);
+                let top_left = Position {
+                    x: blob.top_left.x.min(other.top_left.x),
+                    y: blob.top_left.y.min(other.top_left.y),
+                };
+                let blob_end = blob.top_left + blob.dimensions;
+                let other_end = other.top_left + other.dimensions;
+                let end = Position {
+                    x: blob_end.x.max(other_end.x),
+                    y: blob_end.y.max(other_end.y),
+                };
+                let area = blob.area + other.area;
+                let weighted = |a: i32, b: i32| {
+                    ((a as f32 * blob.area as f32 + b as f32 * other.area as f32) / area as f32)
+                        .round() as i32
+                };
+                blob = Blob {
+                    area,
+                    top_left,
+                    dimensions: end - top_left,
+                    centroid: Position {
+                        x: weighted(blob.centroid.x, other.centroid.x),
+                        y: weighted(blob.centroid.y, other.centroid.y),
+                    },
+                };
+            } else {
+                i += 1;
+            }
+        }
+        merged.push(blob);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::fill;
+    use crate::OwnedFrame;
+
+    #[test]
+    fn find_and_rank_blobs() {
+        let (width, height) = (100, 100);
+        let mut frame = OwnedFrame::blank(width, height);
+        let red = Pixel {
+            blue: 0,
+            green: 0,
+            red: 255,
+        };
+        let red_fuzzy = FuzzyPixel {
+            blue_min: 0,
+            blue_max: 10,
+            green_min: 0,
+            green_max: 10,
+            red_min: 245,
+            red_max: 255,
+        };
+
+        // A big blob far away, a smaller blob nearby, and a stray pixel.
+        fill(
+            &mut frame,
+            Position { x: 5, y: 5 },
+            DeltaPosition { dx: 10, dy: 6 },
+            red,
+        );
+        fill(
+            &mut frame,
+            Position { x: 60, y: 60 },
+            DeltaPosition { dx: 4, dy: 4 },
+            red,
+        );
+        frame.recolor_pixel(&Position { x: 40, y: 40 }, &red);
+
+        let top_left = Position { x: 0, y: 0 };
+        let dimensions = DeltaPosition { dx: 100, dy: 100 };
+        let blobs = find_blobs(
+            &frame,
+            &[red_fuzzy],
+            &top_left,
+            &dimensions,
+            /*min_area=*/ 4,
+            BlobRanking::Largest,
+        );
+        assert_eq!(
+            blobs,
+            vec![
+                Blob {
+                    area: 60,
+                    top_left: Position { x: 5, y: 5 },
+                    dimensions: DeltaPosition { dx: 10, dy: 6 },
+                    centroid: Position { x: 9, y: 7 },
+                },
+                Blob {
+                    area: 16,
+                    top_left: Position { x: 60, y: 60 },
+                    dimensions: DeltaPosition { dx: 4, dy: 4 },
+                    centroid: Position { x: 61, y: 61 },
+                },
+            ]
+        );
+
+        let blobs = find_blobs(
+            &frame,
+            &[red_fuzzy],
+            &top_left,
+            &dimensions,
+            /*min_area=*/ 1,
+            BlobRanking::ClosestTo(Position { x: 50, y: 50 }),
+        );
+        assert_eq!(blobs.len(), 3);
+        assert_eq!(blobs[0].area, 1);
+        assert_eq!(blobs[1].area, 16);
+    }
+
+    #[test]
+    fn merge_letters_into_words() {
+        let letter = |x: i32, y: i32| Blob {
+            area: 10,
+            top_left: Position { x, y },
+            dimensions: DeltaPosition { dx: 4, dy: 8 },
+            centroid: Position { x: x + 2, y: y + 4 },
+        };
+        // Two words on one line, and a third word just below the first.
+        let blobs = vec![
+            letter(10, 10),
+            letter(15, 10),
+            letter(20, 10),
+            letter(60, 10),
+            letter(65, 10),
+            letter(10, 25),
+        ];
+        let mut words = merge_nearby_blobs(blobs, DeltaPosition { dx: 2, dy: 2 });
+        rank_blobs(&mut words, BlobRanking::Largest);
+        assert_eq!(
+            words[0],
+            Blob {
+                area: 30,
+                top_left: Position { x: 10, y: 10 },
+                dimensions: DeltaPosition { dx: 14, dy: 8 },
+                centroid: Position { x: 17, y: 14 },
+            }
+        );
+        assert_eq!(words[1].top_left, Position { x: 60, y: 10 });
+        assert_eq!(words[1].dimensions, DeltaPosition { dx: 9, dy: 8 });
+        assert_eq!(words[2].top_left, Position { x: 10, y: 25 });
+        assert_eq!(words.len(), 3);
+    }
+}
@@ -0,0 +1,476 @@
+/// Adjust the colors in colors.rs for clients with a different brightness or
+/// gamma than the one they were measured on.
+///
+/// We look at parts of the UI which always have the same color, compare them
+/// to the values in colors.rs, and fit a gain and offset for each channel.
+/// Any of those parts might be covered (an item in the inventory, the chatbox
+/// closed), so samples which don't fit the rest are dropped.
+///
+/// The calibration lives on FrameHandler, which reads frames through a
+/// CalibratedFrame that maps each pixel back to how the reference client
+/// would have drawn it before comparing.
+use crate::types::*;
+use crate::{fuzzy_pixels, Frame, Locations};
+use util::*;
+
+/// Samples further than this from the fit, in any channel, are treated as
+/// covered up and dropped.
+const MAX_RESIDUAL: f32 = 12.0;
+
+/// Generous bound used for the first pass, before we know the gain.
+const MAX_INITIAL_RESIDUAL: f32 = 40.0;
+
+/// Fewest samples we are willing to fit to.
+const MIN_SAMPLES: usize = 8;
+
+/// Brightness settings scale colors, but not by more than this.
+const MIN_GAIN: f32 = 0.5;
+const MAX_GAIN: f32 = 2.0;
+
+/// Spots on the chatbox's parchment, from chatbox_inner_top_left, which match
+/// chatbox_background unless a message is drawn over them.
+const CHATBOX_BACKGROUND_OFFSETS: [DeltaPosition; 6] = [
+    DeltaPosition { dx: 232, dy: 8 },
+    DeltaPosition { dx: 278, dy: 8 },
+    DeltaPosition { dx: 402, dy: 41 },
+    DeltaPosition { dx: 403, dy: 44 },
+    DeltaPosition { dx: 263, dy: 101 },
+    DeltaPosition { dx: 270, dy: 100 },
+];
+
+/// For each channel, the reference value for every value this client draws.
+struct ReferenceTables {
+    blue: [u8; 256],
+    green: [u8; 256],
+    red: [u8; 256],
+}
+
+impl ReferenceTables {
+    fn to_reference(&self, pixel: &Pixel) -> Pixel {
+        Pixel {
+            blue: self.blue[pixel.blue as usize],
+            green: self.green[pixel.green as usize],
+            red: self.red[pixel.red as usize],
+        }
+    }
+}
+
+/// 'frame' as the reference client would have drawn it, so that the colors in
+/// colors.rs match. See FrameHandler::calibrated.
+pub struct CalibratedFrame<'a, F: Frame> {
+    frame: &'a F,
+
+    /// None when there is nothing to adjust.
+    tables: Option<ReferenceTables>,
+}
+
+impl<'a, F: Frame> CalibratedFrame<'a, F> {
+    pub fn new(frame: &'a F, calibration: &ColorCalibration) -> CalibratedFrame<'a, F> {
+        // Don't adjust frames which were already adjusted.
+        let tables = if frame.is_calibrated() || *calibration == ColorCalibration::identity() {
+            None
+        } else {
+            Some(calibration.reference_tables())
+        };
+        CalibratedFrame { frame, tables }
+    }
+}
+
+impl<'a, F: Frame> Frame for CalibratedFrame<'a, F> {
+    fn width(&self) -> usize {
+        self.frame.width()
+    }
+    fn height(&self) -> usize {
+        self.frame.height()
+    }
+    fn is_bgr(&self) -> bool {
+        self.frame.is_bgr()
+    }
+    fn buffer(&self) -> &[u8] {
+        self.frame.buffer()
+    }
+
+    fn get_pixel(&self, pos: &Position) -> Pixel {
+        let pixel = self.frame.get_pixel(pos);
+        match &self.tables {
+            Some(tables) => tables.to_reference(&pixel),
+            None => pixel,
+        }
+    }
+
+    fn is_calibrated(&self) -> bool {
+        true
+    }
+}
+
+/// observed = gain * reference + offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelCalibration {
+    pub gain: f32,
+    pub offset: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorCalibration {
+    pub blue: ChannelCalibration,
+    pub green: ChannelCalibration,
+    pub red: ChannelCalibration,
+}
+
+impl ChannelCalibration {
+    fn apply(&self, value: f32) -> f32 {
+        self.gain * value + self.offset
+    }
+
+    /// Least squares fit of observed against reference values.
+    fn fit(samples: &[(f32, f32)]) -> ChannelCalibration {
+        let n = samples.len() as f32;
+        let mean_reference = samples.iter().map(|(r, _)| r).sum::<f32>() / n;
+        let mean_observed = samples.iter().map(|(_, o)| o).sum::<f32>() / n;
+        let covariance: f32 = samples
+            .iter()
+            .map(|(r, o)| (r - mean_reference) * (o - mean_observed))
+            .sum();
+        let variance: f32 = samples
+            .iter()
+            .map(|(r, _)| (r - mean_reference).powi(2))
+            .sum();
+
+        // All references the same color, so we can only tell the offset.
+        let gain = if variance < 1.0 {
+            1.0
+        } else {
+            covariance / variance
+        };
+        ChannelCalibration {
+            gain,
+            offset: mean_observed - gain * mean_reference,
+        }
+    }
+
+    /// Only shift by the median difference. Robust to outliers, which makes
+    /// it a good starting point.
+    fn fit_offset(samples: &[(f32, f32)]) -> ChannelCalibration {
+        let mut differences: Vec<f32> = samples.iter().map(|(r, o)| o - r).collect();
+        differences.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        ChannelCalibration {
+            gain: 1.0,
+            offset: differences[differences.len() / 2],
+        }
+    }
+
+    /// Invert 'apply' for every value the channel can take.
+    fn reference_table(&self) -> [u8; 256] {
+        let mut table = [0; 256];
+        for (observed, reference) in table.iter_mut().enumerate() {
+            *reference = ((observed as f32 - self.offset) / self.gain)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+        table
+    }
+
+    /// Map the range [min, max] measured on the reference client to this
+    /// client. The ends of the channel stay put so that ranges meaning "any
+    /// value" keep meaning that.
+    fn adjust_range(&self, min: u8, max: u8) -> (u8, u8) {
+        let adjust = |value: u8, round: fn(f32) -> f32| -> u8 {
+            if value == 0 || value == 255 {
+                value
+            } else {
+                round(self.apply(value as f32)).clamp(0.0, 255.0) as u8
+            }
+        };
+        (adjust(min, f32::floor), adjust(max, f32::ceil))
+    }
+}
+
+impl ColorCalibration {
+    pub fn identity() -> ColorCalibration {
+        let identity = ChannelCalibration {
+            gain: 1.0,
+            offset: 0.0,
+        };
+        ColorCalibration {
+            blue: identity,
+            green: identity,
+            red: identity,
+        }
+    }
+
+    /// Compare the fixed UI elements in 'frame' against colors.rs. Returns
+    /// None if too few of them were visible or the fit is implausible.
+    pub fn estimate(frame: &impl Frame, locations: &Locations) -> Option<ColorCalibration> {
+        let samples: Vec<(FuzzyPixel, Pixel)> = reference_positions(locations)
+            .into_iter()
+            .map(|(pos, expected)| (expected, frame.get_pixel(&pos)))
+            .collect();
+        Self::fit(&samples)
+    }
+
+    fn fit(samples: &[(FuzzyPixel, Pixel)]) -> Option<ColorCalibration> {
+        let mut calibration = Self::fit_channels(samples, ChannelCalibration::fit_offset);
+        let mut samples = samples.to_vec();
+        for max_residual in [MAX_INITIAL_RESIDUAL, MAX_RESIDUAL, MAX_RESIDUAL].iter() {
+            samples.retain(|sample| calibration.residual(sample) <= *max_residual);
+            if samples.len() < MIN_SAMPLES {
+                println!("Only {} samples to calibrate colors", samples.len());
+                return None;
+            }
+            calibration = Self::fit_channels(&samples, ChannelCalibration::fit);
+        }
+
+        let gains = [
+            calibration.blue.gain,
+            calibration.green.gain,
+            calibration.red.gain,
+        ];
+        if gains
+            .iter()
+            .any(|gain| !(MIN_GAIN..=MAX_GAIN).contains(gain))
+        {
+            println!("Implausible color calibration {:?}", calibration);
+            return None;
+        }
+        Some(calibration)
+    }
+
+    fn fit_channels(
+        samples: &[(FuzzyPixel, Pixel)],
+        fit: fn(&[(f32, f32)]) -> ChannelCalibration,
+    ) -> ColorCalibration {
+        let channel = |get: fn(&FuzzyPixel, &Pixel) -> (f32, f32)| {
+            fit(&samples
+                .iter()
+                .map(|(expected, pixel)| get(expected, pixel))
+                .collect::<Vec<_>>())
+        };
+        ColorCalibration {
+            blue: channel(|e, p| (midpoint(e.blue_min, e.blue_max), p.blue as f32)),
+            green: channel(|e, p| (midpoint(e.green_min, e.green_max), p.green as f32)),
+            red: channel(|e, p| (midpoint(e.red_min, e.red_max), p.red as f32)),
+        }
+    }
+
+    /// Largest difference in any channel between the pixel we saw and what
+    /// the calibration predicts.
+    fn residual(&self, (expected, pixel): &(FuzzyPixel, Pixel)) -> f32 {
+        let blue = self
+            .blue
+            .apply(midpoint(expected.blue_min, expected.blue_max));
+        let green = self
+            .green
+            .apply(midpoint(expected.green_min, expected.green_max));
+        let red = self.red.apply(midpoint(expected.red_min, expected.red_max));
+        (blue - pixel.blue as f32)
+            .abs()
+            .max((green - pixel.green as f32).abs())
+            .max((red - pixel.red as f32).abs())
+    }
+
+    /// 'fuzzy_pixel', measured on the reference client, as this client draws
+    /// it.
+    pub fn adjust(&self, fuzzy_pixel: &FuzzyPixel) -> FuzzyPixel {
+        let (blue_min, blue_max) = self
+            .blue
+            .adjust_range(fuzzy_pixel.blue_min, fuzzy_pixel.blue_max);
+        let (green_min, green_max) = self
+            .green
+            .adjust_range(fuzzy_pixel.green_min, fuzzy_pixel.green_max);
+        let (red_min, red_max) = self
+            .red
+            .adjust_range(fuzzy_pixel.red_min, fuzzy_pixel.red_max);
+        FuzzyPixel {
+            blue_min,
+            blue_max,
+            green_min,
+            green_max,
+            red_min,
+            red_max,
+        }
+    }
+
+    fn reference_tables(&self) -> ReferenceTables {
+        ReferenceTables {
+            blue: self.blue.reference_table(),
+            green: self.green.reference_table(),
+            red: self.red.reference_table(),
+        }
+    }
+}
+
+impl Default for ColorCalibration {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+fn midpoint(min: u8, max: u8) -> f32 {
+    (min as f32 + max as f32) / 2.0
+}
+
+/// Positions of UI elements with a known color, paired with that color.
+fn reference_positions(locations: &Locations) -> Vec<(Position, FuzzyPixel)> {
+    let mut positions = vec![(
+        locations.inventory_icon_background(),
+        fuzzy_pixels::inventory_icon_background_open(),
+    )];
+
+    // Items are rarely drawn in the top left of their slot.
+    for slot_index in 0..Locations::NUM_INVENTORY_SLOTS {
+        positions.push((
+            locations.inventory_slot_top_left(slot_index) + Locations::INVENTORY_SLOT_CHECK_SPACING,
+            fuzzy_pixels::inventory_background(),
+        ));
+    }
+
+    let top_left = locations.chatbox_inner_top_left();
+    let dimensions = locations.chatbox_inner_dimensions();
+    positions.extend(vec![
+        (top_left, fuzzy_pixels::chatbox_corner_top_left()),
+        (
+            Locations::to_bottom_left(top_left, dimensions),
+            fuzzy_pixels::chatbox_corner_bottom_left(),
+        ),
+        (
+            Locations::to_top_right(top_left, dimensions),
+            fuzzy_pixels::chatbox_corner_top_right(),
+        ),
+        (
+            Locations::to_bottom_right(top_left, dimensions),
+            fuzzy_pixels::chatbox_corner_bottom_right(),
+        ),
+    ]);
+    for offset in CHATBOX_BACKGROUND_OFFSETS.iter() {
+        positions.push((top_left + *offset, fuzzy_pixels::chatbox_background()));
+    }
+
+    // The corners of the bank checked by FrameHandler::is_bank_open. The top
+    // left is skipped since action text often covers it.
+    let expansion = DeltaPosition { dx: 3, dy: 3 };
+    let top_left = locations.bank_top_left() - expansion;
+    let dimensions = locations.bank_dimensions() + expansion * 2.0;
+    positions.extend(vec![
+        (
+            Locations::to_bottom_left(top_left, dimensions),
+            fuzzy_pixels::bank_border_bottom_left(),
+        ),
+        (
+            Locations::to_top_right(top_left, dimensions),
+            fuzzy_pixels::bank_border_right(),
+        ),
+        (
+            Locations::to_bottom_right(top_left, dimensions),
+            fuzzy_pixels::bank_border_right(),
+        ),
+    ]);
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_ignores_covered_elements() {
+        let references = [
+            fuzzy_pixels::inventory_background(),
+            fuzzy_pixels::inventory_icon_background_open(),
+            fuzzy_pixels::chatbox_corner_top_left(),
+            fuzzy_pixels::chatbox_corner_bottom_left(),
+            fuzzy_pixels::chatbox_corner_top_right(),
+            fuzzy_pixels::chatbox_corner_bottom_right(),
+        ];
+        // A brighter client: 1.2x plus 5 in every channel.
+        let brighten = |value: f32| (1.2 * value + 5.0).round() as u8;
+        let mut samples: Vec<(FuzzyPixel, Pixel)> = references
+            .iter()
+            .cycle()
+            .take(12)
+            .map(|expected| {
+                (
+                    *expected,
+                    Pixel {
+                        blue: brighten(midpoint(expected.blue_min, expected.blue_max)),
+                        green: brighten(midpoint(expected.green_min, expected.green_max)),
+                        red: brighten(midpoint(expected.red_min, expected.red_max)),
+                    },
+                )
+            })
+            .collect();
+        // Items covering some of the inventory.
+        for _ in 0..3 {
+            samples.push((
+                fuzzy_pixels::inventory_background(),
+                Pixel {
+                    blue: 200,
+                    green: 10,
+                    red: 90,
+                },
+            ));
+        }
+
+        let calibration = ColorCalibration::fit(&samples).unwrap();
+        for channel in [calibration.blue, calibration.green, calibration.red].iter() {
+            assert!((channel.gain - 1.2).abs() < 0.05, "{:?}", calibration);
+            assert!((channel.offset - 5.0).abs() < 3.0, "{:?}", calibration);
+        }
+
+        let background = calibration.adjust(&fuzzy_pixels::inventory_background());
+        assert_eq!(background.blue_min, 49);
+        assert_eq!(background.blue_max, 61);
+        assert_eq!(calibration.adjust(&fuzzy_pixels::black()).blue_min, 0);
+
+        // The brightened background maps back inside the original bounds.
+        let tables = calibration.reference_tables();
+        let background = fuzzy_pixels::inventory_background();
+        assert!(background.contains(&tables.to_reference(&samples[0].1)));
+
+        assert!(ColorCalibration::fit(&samples[..5]).is_none());
+    }
+
+    #[test]
+    fn estimate_from_screenshot() {
+        // Saved from the client colors.rs was measured on, with the
+        // inventory, chatbox and bank open.
+        let frame = crate::OwnedFrame::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/readme/screenshot_inventories.png"
+        ));
+        let locations = Locations::new(
+            Position { x: 12, y: 11 },
+            DeltaPosition { dx: 952, dy: 607 },
+        );
+        let calibration = ColorCalibration::estimate(&frame, &locations).unwrap();
+        for channel in [calibration.blue, calibration.green, calibration.red].iter() {
+            assert!((channel.gain - 1.0).abs() < 0.05, "{:?}", calibration);
+            assert!(channel.offset.abs() < 5.0, "{:?}", calibration);
+        }
+
+        // The same screenshot from a darker client.
+        let mut darker = frame.to_owned();
+        for y in 0..darker.height() as i32 {
+            for x in 0..darker.width() as i32 {
+                let pos = Position { x, y };
+                let pixel = darker.get_pixel(&pos);
+                let darken = |value: u8| (0.8 * value as f32).round() as u8;
+                darker.recolor_pixel(
+                    &pos,
+                    &Pixel {
+                        blue: darken(pixel.blue),
+                        green: darken(pixel.green),
+                        red: darken(pixel.red),
+                    },
+                );
+            }
+        }
+        let calibration = ColorCalibration::estimate(&darker, &locations).unwrap();
+        for channel in [calibration.blue, calibration.green, calibration.red].iter() {
+            assert!((channel.gain - 0.8).abs() < 0.05, "{:?}", calibration);
+        }
+        let pos = locations.chatbox_inner_top_left() + CHATBOX_BACKGROUND_OFFSETS[0];
+        assert!(!darker.check_loose_pixel(&pos, &fuzzy_pixels::chatbox_background()));
+        assert!(CalibratedFrame::new(&darker, &calibration)
+            .check_loose_pixel(&pos, &fuzzy_pixels::chatbox_background()));
+    }
+}
@@ -10,6 +10,10 @@ use crate::Locations;
 
 pub type InventorySlotPixels = [FuzzyPixel; Locations::NUM_CHECKS_PER_INVENTORY_SLOT];
 
+/// Like InventorySlotPixels, but for an item in a bank slot. Checks which
+/// can't be relied on in the bank are None. See bank_slot_pixels.
+pub type BankSlotPixels = [Option<FuzzyPixel>; Locations::NUM_CHECKS_PER_INVENTORY_SLOT];
+
 pub mod pixels {
     use crate::Pixel;
 
@@ -325,6 +329,49 @@ pub mod fuzzy_pixels {
         }
     }
 
+    /// Colors of the numbers written next to the status orbs. The color moves
+    /// from green to red as the orb empties.
+    pub fn orb_text_green() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 40,
+            green_min: 200,
+            green_max: 255,
+            red_min: 0,
+            red_max: 40,
+        }
+    }
+    pub fn orb_text_yellow() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 40,
+            green_min: 200,
+            green_max: 255,
+            red_min: 200,
+            red_max: 255,
+        }
+    }
+    pub fn orb_text_orange() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 40,
+            green_min: 110,
+            green_max: 170,
+            red_min: 200,
+            red_max: 255,
+        }
+    }
+    pub fn orb_text_red() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 40,
+            green_min: 0,
+            green_max: 40,
+            red_min: 200,
+            red_max: 255,
+        }
+    }
+
     pub fn enemy_healthbar_red() -> FuzzyPixel {
         FuzzyPixel {
             blue_min: 15,
@@ -346,6 +393,42 @@ pub mod fuzzy_pixels {
         }
     }
 
+    // The small healthbar drawn above the player (or an NPC) during combat.
+    // These are much brighter than the enemy healthbar in the top left.
+    pub fn overhead_healthbar_green() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 30,
+            green_min: 230,
+            green_max: 255,
+            red_min: 0,
+            red_max: 30,
+        }
+    }
+    pub fn overhead_healthbar_red() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 30,
+            green_min: 0,
+            green_max: 30,
+            red_min: 230,
+            red_max: 255,
+        }
+    }
+
+    // Text label RuneLite's Ground Items plugin draws above items on the list
+    // of highlighted items (default highlight color).
+    pub fn ground_item_label_purple() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 220,
+            blue_max: 255,
+            green_min: 0,
+            green_max: 30,
+            red_min: 150,
+            red_max: 190,
+        }
+    }
+
     /// Pixels used to identify things on the maps (minimap or worldmap).
     pub fn map_icon_bank_yellow() -> FuzzyPixel {
         FuzzyPixel {
@@ -567,6 +650,40 @@ pub mod fuzzy_pixels {
             red_max: 210,
         }
     }
+    // The red flag drawn on the minimap where we clicked to walk to. Note
+    // that items on the ground show up as similar red dots.
+    pub fn minimap_flag_red() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 30,
+            green_min: 0,
+            green_max: 30,
+            red_min: 200,
+            red_max: 255,
+        }
+    }
+    // Dots drawn on the minimap for other players, NPCs, and items on the
+    // ground. Item dots match minimap_flag_red.
+    pub fn minimap_dot_player_white() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 230,
+            blue_max: 255,
+            green_min: 230,
+            green_max: 255,
+            red_min: 230,
+            red_max: 255,
+        }
+    }
+    pub fn minimap_dot_npc_yellow() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 0,
+            blue_max: 60,
+            green_min: 220,
+            green_max: 255,
+            red_min: 220,
+            red_max: 255,
+        }
+    }
     pub fn map_border_white() -> FuzzyPixel {
         FuzzyPixel {
             blue_min: 237,
@@ -732,6 +849,120 @@ pub mod fuzzy_pixels {
         }
     }
 
+    // While the bank is open the shadows under items in the inventory turn
+    // this gray.
+    pub fn inventory_background_bank() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 49,
+            blue_max: 53,
+            green_min: 49,
+            green_max: 53,
+            red_min: 49,
+            red_max: 53,
+        }
+    }
+
+    /// Behind the items in the bank.
+    pub fn bank_background() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 48,
+            blue_max: 54,
+            green_min: 59,
+            green_max: 66,
+            red_min: 68,
+            red_max: 75,
+        }
+    }
+
+    /// The track the bank's scrollbar thumb slides along.
+    pub fn bank_scrollbar_track() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 22,
+            blue_max: 28,
+            green_min: 29,
+            green_max: 35,
+            red_min: 34,
+            red_max: 40,
+        }
+    }
+
+    /// Inner corners of the chatbox while it is open.
+    pub fn chatbox_corner_top_left() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 63,
+            blue_max: 71,
+            green_min: 76,
+            green_max: 85,
+            red_min: 84,
+            red_max: 93,
+        }
+    }
+    pub fn chatbox_corner_bottom_left() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 81,
+            blue_max: 90,
+            green_min: 99,
+            green_max: 109,
+            red_min: 108,
+            red_max: 119,
+        }
+    }
+    pub fn chatbox_corner_top_right() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 83,
+            blue_max: 87,
+            green_min: 103,
+            green_max: 107,
+            red_min: 112,
+            red_max: 116,
+        }
+    }
+    pub fn chatbox_corner_bottom_right() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 115,
+            blue_max: 119,
+            green_min: 143,
+            green_max: 147,
+            red_min: 155,
+            red_max: 159,
+        }
+    }
+    /// The parchment behind the chat messages. It is textured, so this only
+    /// matches some of it.
+    pub fn chatbox_background() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 148,
+            blue_max: 160,
+            green_min: 181,
+            green_max: 193,
+            red_min: 198,
+            red_max: 210,
+        }
+    }
+
+    /// Border just outside the bank window. Also the value for the top left
+    /// corner, which is often covered by action text.
+    pub fn bank_border_bottom_left() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 51,
+            blue_max: 62,
+            green_min: 55,
+            green_max: 66,
+            red_min: 54,
+            red_max: 65,
+        }
+    }
+    pub fn bank_border_right() -> FuzzyPixel {
+        FuzzyPixel {
+            blue_min: 58,
+            blue_max: 62,
+            green_min: 62,
+            green_max: 66,
+            red_min: 61,
+            red_max: 65,
+        }
+    }
+
     /// This is the red color that the inventory icon at the bottom of the
     /// screen turns when the inventory is open.
     pub fn inventory_icon_background_open() -> FuzzyPixel {
@@ -926,6 +1157,51 @@ pub mod fuzzy_pixels {
     }
 }
 
+/// Items are drawn the same in the bank as in the inventory, so we derive bank
+/// fingerprints from the inventory ones instead of recording them again.
+/// Checks which land on the inventory's background expect the bank's
+/// background instead, and the checks under the quantity are dropped.
+pub mod bank_slot_pixels {
+    use super::fuzzy_pixels::{bank_background, inventory_background, inventory_background_dark};
+    use crate::{BankSlotPixels, InventorySlotPixels};
+
+    /// The number of items in a bank slot is written over the top left of
+    /// the item, which covers the first row of checks.
+    const NUM_CHECKS_UNDER_QUANTITY: usize = 4;
+
+    /// With fewer checks on the item than this, items are too easily
+    /// mistaken for each other, e.g. any slot holding something of the right
+    /// color would match.
+    pub const MIN_NUM_CHECKS: usize = 2;
+
+    /// 'item' can be either the normal inventory pixels or the ones for
+    /// while the bank is open. Where the inventory shows through the item,
+    /// the bank shows its own background instead. Shadows are the same gray
+    /// as in the inventory while the bank is open.
+    pub fn from_inventory(item: &InventorySlotPixels) -> BankSlotPixels {
+        let background = [inventory_background(), inventory_background_dark()];
+        let mut pixels: BankSlotPixels = [None; crate::Locations::NUM_CHECKS_PER_INVENTORY_SLOT];
+        for (i, fuzzy_pixel) in item.iter().enumerate().skip(NUM_CHECKS_UNDER_QUANTITY) {
+            pixels[i] = Some(if background.contains(fuzzy_pixel) {
+                bank_background()
+            } else {
+                *fuzzy_pixel
+            });
+        }
+        pixels
+    }
+
+    /// Number of checks in 'pixels' which land on the item itself. Checks
+    /// of the background match any slot the item doesn't cover, so with too
+    /// few of these many slots would match, see MIN_NUM_CHECKS.
+    pub fn num_checks(pixels: &BankSlotPixels) -> usize {
+        pixels
+            .iter()
+            .filter(|pixel| matches!(pixel, Some(pixel) if *pixel != bank_background()))
+            .count()
+    }
+}
+
 /// We check each inventory slot at a set interval. Each item in the inventory
 /// has the same pixels regardless of which slot it is in. Use this to match
 /// them. See location.rs for details.
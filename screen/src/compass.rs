@@ -0,0 +1,134 @@
+/// Estimate the camera yaw from the compass icon next to the minimap.
+///
+/// There are no stored sprites of the compass. Instead we capture the compass
+/// while the camera is known to face north (right after pressing the compass)
+/// and then match rotated versions of that capture against later frames.
+use crate::types::*;
+use crate::Frame;
+use util::*;
+
+/// Radius around the middle of the compass icon that is captured. Stays inside
+/// the icon so that the background behind the minimap isn't included.
+pub const COMPASS_TEMPLATE_RADIUS: i32 = 12;
+
+/// The compass as it looks with the camera facing north.
+pub struct CompassTemplate {
+    /// Offset from the middle of the compass and the color there.
+    pub pixels: Vec<(DeltaPosition, Pixel)>,
+}
+
+impl CompassTemplate {
+    /// Capture the compass centered at 'middle'. Assumes the camera is facing
+    /// north.
+    pub fn capture(frame: &impl Frame, middle: Position) -> CompassTemplate {
+        let mut pixels = Vec::new();
+        for dy in -COMPASS_TEMPLATE_RADIUS..=COMPASS_TEMPLATE_RADIUS {
+            for dx in -COMPASS_TEMPLATE_RADIUS..=COMPASS_TEMPLATE_RADIUS {
+                let offset = DeltaPosition { dx, dy };
+                if offset.distance() > COMPASS_TEMPLATE_RADIUS {
+                    continue;
+                }
+                pixels.push((offset, frame.get_pixel(&(middle + offset))));
+            }
+        }
+        CompassTemplate { pixels }
+    }
+
+    /// Sum of squared differences between the template, rotated to match
+    /// 'yaw_degrees', and the compass in 'frame'. Lower is a better match.
+    fn mismatch(&self, frame: &impl Frame, middle: Position, yaw_degrees: f32) -> u64 {
+        // Turning the camera clockwise rotates the compass counterclockwise.
+        let angle = degrees_to_radians(-yaw_degrees);
+        self.pixels
+            .iter()
+            .map(|(offset, expected)| {
+                let actual = frame.get_pixel(&(middle + offset.rotate(angle)));
+                let diff = |a: u8, b: u8| (a as i64 - b as i64).pow(2) as u64;
+                diff(actual.blue, expected.blue)
+                    + diff(actual.green, expected.green)
+                    + diff(actual.red, expected.red)
+            })
+            .sum()
+    }
+
+    /// Estimate the camera yaw in degrees clockwise from north, [0, 360).
+    pub fn estimate_yaw(&self, frame: &impl Frame, middle: Position) -> f32 {
+        // Coarse search over every degree, then refine around the best match.
+        let mut best_yaw = (0..360)
+            .map(|yaw| yaw as f32)
+            .min_by_key(|&yaw| self.mismatch(frame, middle, yaw))
+            .unwrap();
+        best_yaw = (-10..=10)
+            .map(|step| best_yaw + step as f32 / 10.0)
+            .min_by_key(|&yaw| self.mismatch(frame, middle, yaw))
+            .unwrap();
+        best_yaw.rem_euclid(360.0)
+    }
+}
+
+/// Signed difference to turn from 'from_degrees' to 'to_degrees', in
+/// (-180, 180]. Positive means turning clockwise.
+pub fn yaw_difference(from_degrees: f32, to_degrees: f32) -> f32 {
+    let diff = (to_degrees - from_degrees).rem_euclid(360.0);
+    if diff > 180.0 {
+        diff - 360.0
+    } else {
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OwnedFrame;
+
+    const MIDDLE: Position = Position { x: 20, y: 20 };
+
+    // Draw a compass like pattern, rotated to how it would look with the camera
+    // at 'yaw_degrees'.
+    fn draw_compass(yaw_degrees: f32) -> OwnedFrame {
+        let (width, height) = (41, 41);
+        let mut frame = OwnedFrame::blank(width, height);
+
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let offset = Position { x, y } - MIDDLE;
+                // Undo the rotation to find where this pixel is on the north
+                // facing compass.
+                let north_offset = offset.rotate(degrees_to_radians(yaw_degrees));
+                let angle = north_offset.angle_rads();
+                let radius = north_offset.distance() as f32;
+                frame.recolor_pixel(
+                    &Position { x, y },
+                    &Pixel {
+                        blue: (radius * 10.0).min(255.0) as u8,
+                        green: (128.0 + 127.0 * angle.sin()) as u8,
+                        red: (128.0 + 127.0 * angle.cos()) as u8,
+                    },
+                );
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn estimate_rotations() {
+        let template = CompassTemplate::capture(&draw_compass(0.0), MIDDLE);
+        for &yaw in [0.0, 45.0, 90.0, 200.0, 315.0].iter() {
+            let estimate = template.estimate_yaw(&draw_compass(yaw), MIDDLE);
+            assert!(
+                yaw_difference(estimate, yaw).abs() < 3.0,
+                "yaw={} estimate={}",
+                yaw,
+                estimate
+            );
+        }
+    }
+
+    #[test]
+    fn yaw_difference_wraps() {
+        assert_eq!(yaw_difference(350.0, 10.0), 20.0);
+        assert_eq!(yaw_difference(10.0, 350.0), -20.0);
+        assert_eq!(yaw_difference(0.0, 180.0), 180.0);
+    }
+}
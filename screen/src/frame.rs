@@ -1,4 +1,3 @@
-use crate::types::*;
 /// This file holds frames, which are t base a list of u8's describing an image.
 ///
 /// The two types of frames are owned and unowned. This is due to scrap, which
@@ -6,11 +5,17 @@ use crate::types::*;
 /// want to keep this, is that when the bot is running we don't need to do any
 /// owning activities. For the sake of feedback it can be useful to mark up and
 /// save the image, which requires ownership.
+use crate::calibration::{CalibratedFrame, ColorCalibration};
+use crate::types::*;
 use crate::ActionText;
 use crate::Locations;
-use crate::{fuzzy_pixels, inventory_slot_pixels};
+use crate::Orb;
+use crate::{bank_slot_pixels, fuzzy_pixels, inventory_slot_pixels};
+use crate::{BankSlot, BankSlotPixels};
 use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::ErrorKind::WouldBlock;
 use std::io::Result;
 use std::ops::Deref;
@@ -63,7 +68,7 @@ pub trait Frame {
     fn check_loose_pixel_explicit(
         &self,
         pos: &Position,
-        expected_pixel: &FuzzyPixel,
+        expected_pixel: &impl PixelMatcher,
         tolerance: i32,
     ) -> bool {
         // dbg!(pos, expected_pixel);
@@ -80,14 +85,14 @@ pub trait Frame {
         }
         false
     }
-    fn check_loose_pixel(&self, pos: &Position, expected_pixel: &FuzzyPixel) -> bool {
+    fn check_loose_pixel(&self, pos: &Position, expected_pixel: &impl PixelMatcher) -> bool {
         self.check_loose_pixel_explicit(pos, expected_pixel, /*tolerance=*/ 1)
     }
 
     /// Search for a matching pixel in the bounds given bounds. This
     fn find_pixel_random(
         &self,
-        fuzzy_pixel: &FuzzyPixel,
+        fuzzy_pixel: &impl PixelMatcher,
         top_left: &Position,
         dimensions: &DeltaPosition,
     ) -> Option<Position> {
@@ -98,7 +103,7 @@ pub trait Frame {
             // every 1k searches.
             for _ in 0..batch_size {
                 let pos = random_position(top_left, dimensions);
-                if fuzzy_pixel.contains(&self.get_pixel(&pos)) {
+                if fuzzy_pixel.matches(&self.get_pixel(&pos)) {
                     return Some(pos);
                 }
             }
@@ -110,7 +115,7 @@ pub trait Frame {
     /// in polar coordinates.
     fn find_pixel_random_polar(
         &self,
-        fuzzy_pixel: FuzzyPixel,
+        fuzzy_pixel: impl PixelMatcher,
         middle: Position,
         radius: i32,
     ) -> Option<Position> {
@@ -121,7 +126,7 @@ pub trait Frame {
             // every 1k searches.
             for _ in 0..batch_size {
                 let pos = random_position_polar(middle, radius);
-                if fuzzy_pixel.contains(&self.get_pixel(&pos)) {
+                if fuzzy_pixel.matches(&self.get_pixel(&pos)) {
                     return Some(pos);
                 }
             }
@@ -134,6 +139,12 @@ pub trait Frame {
         self.width() * RAW_PIXEL_SIZE * pos.y as usize + pos.x as usize * RAW_PIXEL_SIZE
     }
 
+    /// Does get_pixel already compensate for a color calibration, see
+    /// calibration::CalibratedFrame.
+    fn is_calibrated(&self) -> bool {
+        false
+    }
+
     fn to_owned(&self) -> OwnedFrame {
         OwnedFrame {
             buffer: self.buffer().to_vec(),
@@ -256,6 +267,40 @@ impl OwnedFrame {
         self
     }
 
+    /// An all black frame, e.g. to draw on in tests.
+    pub fn blank(width: usize, height: usize) -> OwnedFrame {
+        OwnedFrame {
+            is_bgr: true,
+            width,
+            height,
+            buffer: vec![0; width * height * RAW_PIXEL_SIZE],
+        }
+    }
+
+    /// Load an image saved by Frame::save, or any other RGB(A) png. The frame
+    /// is returned in RGBA.
+    pub fn load(fpath: &str) -> OwnedFrame {
+        let decoder = png::Decoder::new(File::open(fpath).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer).unwrap();
+
+        let buffer = match info.color_type {
+            png::ColorType::RGBA => buffer,
+            png::ColorType::RGB => buffer
+                .chunks(3)
+                .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            color_type => panic!("Unsupported png color type {:?}", color_type),
+        };
+        OwnedFrame {
+            is_bgr: false,
+            width: info.width as usize,
+            height: info.height as usize,
+            buffer,
+        }
+    }
+
     /// Flip the image from either BGRA to RGBA or back. Always sets alpha to
     /// 255.
     pub fn flip(&mut self) -> &mut OwnedFrame {
@@ -444,28 +489,65 @@ impl Capturer {
 /// during play.
 pub struct FrameHandler {
     pub locations: crate::Locations,
+
+    /// Set by calibrate_compass, needed to read the camera yaw.
+    pub compass_template: Option<crate::compass::CompassTemplate>,
+
+    /// Set by calibrate_colors. Maps how this client draws colors onto the
+    /// colors in colors.rs, see calibrated.
+    pub color_calibration: ColorCalibration,
 }
 
 impl FrameHandler {
-    pub fn new(config: crate::Config) -> FrameHandler {
-        FrameHandler {
-            locations: Locations::new(
-                config.screen_top_left,
-                util::DeltaPosition {
-                    dx: config.screen_bottom_right.x - config.screen_top_left.x + 1,
-                    dy: config.screen_bottom_right.y - config.screen_top_left.y + 1,
-                },
-            ),
+    /// 'frame' is a screenshot of the game, used to find the screen and
+    /// calibrate colors if 'config' asks us to.
+    pub fn new(config: crate::Config, frame: &impl Frame) -> FrameHandler {
+        let mut framehandler = FrameHandler {
+            locations: config.locations(frame),
+            compass_template: None,
+            color_calibration: ColorCalibration::identity(),
+        };
+        if config.calibrate_colors && !framehandler.calibrate_colors(frame) {
+            println!("Unable to calibrate colors, is the inventory and chatbox open?");
         }
+        framehandler
     }
 
     pub fn check_action_text(&self, frame: &impl Frame, action_text: &ActionText) -> bool {
+        let frame = &self.calibrated(frame);
         crate::action_text::check_action_letters(
             frame,
             &action_text,
             self.locations.action_text_top_left(),
         )
     }
+    /// Find the entry matching 'action_text' in the right click menu opened
+    /// at 'menu_pos'. Returns a position on the entry to click.
+    pub fn find_right_click_menu_entry(
+        &self,
+        frame: &impl Frame,
+        menu_pos: Position,
+        action_text: &ActionText,
+    ) -> Option<Position> {
+        let frame = &self.calibrated(frame);
+        // We don't know how wide the menu is, so check each possible left
+        // edge.
+        for i in 0..Locations::RIGHT_CLICK_MENU_MAX_ENTRIES {
+            let y = menu_pos.y
+                + Locations::RIGHT_CLICK_MENU_TEXT_OFFSET_Y
+                + i * Locations::RIGHT_CLICK_MENU_ENTRY_HEIGHT;
+            let min_x = menu_pos.x - Locations::RIGHT_CLICK_MENU_MAX_HALF_WIDTH;
+            for x in min_x..menu_pos.x {
+                if crate::action_text::check_menu_letters(frame, action_text, Position { x, y }) {
+                    return Some(Position {
+                        x: menu_pos.x,
+                        y: y + Locations::RIGHT_CLICK_MENU_ENTRY_HEIGHT / 2,
+                    });
+                }
+            }
+        }
+        None
+    }
     pub fn mark_letters_and_save(
         &self,
         frame: &impl Frame,
@@ -484,6 +566,7 @@ impl FrameHandler {
     /// screen. Note there are times this icon is not visible, such as when the
     /// bank is open. (In that case the inventory is automatically open though).
     pub fn is_inventory_open(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         // Use check_loose_pixel because the background color of the icons is very
         // distinct between on and off and the satchel depicted is also a
         // significantly different color. If the image shifts, which it sometimes
@@ -510,9 +593,24 @@ impl FrameHandler {
         &self,
         frame: &impl Frame,
         slot_index: i32,
-        expected_colors: &[FuzzyPixel; Locations::NUM_CHECKS_PER_INVENTORY_SLOT],
+        expected_colors: &[impl PixelMatcher; Locations::NUM_CHECKS_PER_INVENTORY_SLOT],
     ) -> bool {
-        let top_left = self.locations.inventory_slot_top_left(slot_index);
+        self.check_slot(
+            frame,
+            self.locations.inventory_slot_top_left(slot_index),
+            expected_colors,
+        )
+    }
+
+    /// Check the pixels of a slot the size of an inventory slot starting at
+    /// 'top_left'. See check_inventory_slot.
+    fn check_slot(
+        &self,
+        frame: &impl Frame,
+        top_left: Position,
+        expected_colors: &[impl PixelMatcher; Locations::NUM_CHECKS_PER_INVENTORY_SLOT],
+    ) -> bool {
+        let frame = &self.calibrated(frame);
         let dimensions = self.locations.inventory_slot_dimensions();
 
         let past_bottom_right = &top_left + &dimensions;
@@ -528,8 +626,8 @@ impl FrameHandler {
                 {
                     // let pixel = frame.get_pixel(&pos);
                     // let dbgstr = format!(
-                    //     "slot_index={}, {:?}, {:?} {:?}",
-                    //     slot_index, pos, pixel, expected_colors[i]
+                    //     "top_left={:?}, {:?}, {:?} {:?}",
+                    //     top_left, pos, pixel, expected_colors[i]
                     // );
                     // dbg!(dbgstr);
                 }
@@ -550,6 +648,88 @@ impl FrameHandler {
         true
     }
 
+    /// Does the visible bank slot 'slot_index' hold the item. Assumes the
+    /// bank is open. See bank_slot_pixels.
+    pub fn check_bank_slot(
+        &self,
+        frame: &impl Frame,
+        slot_index: i32,
+        expected_colors: &BankSlotPixels,
+    ) -> bool {
+        if bank_slot_pixels::num_checks(expected_colors) < bank_slot_pixels::MIN_NUM_CHECKS {
+            return false;
+        }
+        self.check_slot(
+            frame,
+            self.locations.bank_slot_check_top_left(slot_index),
+            expected_colors,
+        )
+    }
+
+    /// Look through every visible bank slot for 'items', and return where each
+    /// one that was found is. 'tab' and 'rows_scrolled' describe what the bank
+    /// is showing, so that the slots returned are valid once the bank is moved.
+    /// If an item matches multiple slots we take the first.
+    pub fn scan_bank<K: Copy + Eq + Hash>(
+        &self,
+        frame: &impl Frame,
+        items: &[(K, BankSlotPixels)],
+        tab: i32,
+        rows_scrolled: i32,
+    ) -> HashMap<K, BankSlot> {
+        let mut found = HashMap::<K, BankSlot>::new();
+        for slot_index in 0..self.locations.num_bank_slots() {
+            for (item, expected_colors) in items.iter() {
+                if found.contains_key(item) {
+                    continue;
+                }
+                if self.check_bank_slot(frame, slot_index, expected_colors) {
+                    found.insert(
+                        *item,
+                        BankSlot {
+                            tab,
+                            index: slot_index + rows_scrolled * Locations::NUM_BANK_COLUMNS,
+                        },
+                    );
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    /// How many rows down the bank is scrolled, read from where the thumb
+    /// sits in the scrollbar. The thumb is as much shorter than the track as
+    /// the view is than the whole tab, so each pixel the thumb moves scrolls
+    /// the slots by view_height / thumb_height pixels. Returns None if the
+    /// thumb can't be found, e.g. the bank isn't open.
+    pub fn bank_rows_scrolled(&self, frame: &impl Frame) -> Option<i32> {
+        let frame = &self.calibrated(frame);
+        let top = self.locations.bank_scrollbar_top();
+        let bottom = self.locations.bank_scrollbar_bottom();
+        let track = fuzzy_pixels::bank_scrollbar_track();
+
+        // Find the single run of pixels along the track which aren't track.
+        let mut thumb: Option<(i32, i32)> = None;
+        let mut in_thumb = false;
+        for y in top.y..=bottom.y {
+            let is_track = track.matches(&frame.get_pixel(&Position { x: top.x, y }));
+            match (is_track, in_thumb, thumb) {
+                (false, false, None) => thumb = Some((y, y)),
+                (false, false, Some(_)) => return None,
+                (false, true, Some((start, _))) => thumb = Some((start, y)),
+                _ => (),
+            }
+            in_thumb = !is_track;
+        }
+        let (start, end) = thumb?;
+        let thumb_height = end - start + 1;
+
+        let scrolled_px = (start - top.y) * self.locations.bank_scroll_view_height() / thumb_height;
+        let row_height = self.locations.bank_slot_dimensions().dy;
+        Some((scrolled_px + row_height / 2) / row_height)
+    }
+
     pub fn is_inventory_slot_open(&self, frame: &impl Frame, slot_index: i32) -> bool {
         self.check_inventory_slot(frame, slot_index, &inventory_slot_pixels::empty())
     }
@@ -559,7 +739,7 @@ impl FrameHandler {
     pub fn first_matching_inventory_slot(
         &self,
         frame: &impl Frame,
-        expected_colors: &[FuzzyPixel; Locations::NUM_CHECKS_PER_INVENTORY_SLOT],
+        expected_colors: &[impl PixelMatcher; Locations::NUM_CHECKS_PER_INVENTORY_SLOT],
     ) -> Option<i32> {
         for i in 0..Locations::NUM_INVENTORY_SLOTS {
             if self.check_inventory_slot(frame, i, expected_colors) {
@@ -613,43 +793,16 @@ impl FrameHandler {
     /// changes depending on whether or not runelite is the active window. We
     /// program on the assumption it is.
     pub fn is_chatbox_open(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         Self::check_corners(
             frame,
             self.locations.chatbox_inner_top_left(),
             self.locations.chatbox_inner_dimensions(),
             [
-                FuzzyPixel {
-                    blue_min: 63,
-                    blue_max: 71,
-                    green_min: 76,
-                    green_max: 85,
-                    red_min: 84,
-                    red_max: 93,
-                },
-                FuzzyPixel {
-                    blue_min: 81,
-                    blue_max: 90,
-                    green_min: 99,
-                    green_max: 109,
-                    red_min: 108,
-                    red_max: 119,
-                },
-                FuzzyPixel {
-                    blue_min: 83,
-                    blue_max: 87,
-                    green_min: 103,
-                    green_max: 107,
-                    red_min: 112,
-                    red_max: 116,
-                },
-                FuzzyPixel {
-                    blue_min: 115,
-                    blue_max: 119,
-                    green_min: 143,
-                    green_max: 147,
-                    red_min: 155,
-                    red_max: 159,
-                },
+                fuzzy_pixels::chatbox_corner_top_left(),
+                fuzzy_pixels::chatbox_corner_bottom_left(),
+                fuzzy_pixels::chatbox_corner_top_right(),
+                fuzzy_pixels::chatbox_corner_bottom_right(),
             ],
         )
     }
@@ -661,6 +814,7 @@ impl FrameHandler {
     /// Note that the mouse hovering over the worldmap messes this up since it
     /// highlights the worldmap.
     pub fn is_worldmap_open(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         // The worldmap icon gets grayed when the worldmap is open.
         // Worldmap closed - 98, 101, 77
         // Worldmap open - 80, 91, 80
@@ -681,6 +835,7 @@ impl FrameHandler {
     /// changes depending on whether or not runelite is the active window. We
     /// program on the assumption it is.
     pub fn is_bank_open(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         // The bank dimensions are internal, which means the colors are
         // variable (top left can be covered by action text, right size is on
         // the map.) Creating an outer barier would put us outside the screen.
@@ -714,50 +869,416 @@ impl FrameHandler {
                     red_min: 0,
                     red_max: 255,
                 },
-                FuzzyPixel {
-                    blue_min: 51,
-                    blue_max: 62,
-                    green_min: 55,
-                    green_max: 66,
-                    red_min: 54,
-                    red_max: 65,
-                },
-                FuzzyPixel {
-                    blue_min: 58,
-                    blue_max: 62,
-                    green_min: 62,
-                    green_max: 66,
-                    red_min: 61,
-                    red_max: 65,
-                },
-                FuzzyPixel {
-                    blue_min: 58,
-                    blue_max: 62,
-                    green_min: 62,
-                    green_max: 66,
-                    red_min: 61,
-                    red_max: 65,
-                },
+                fuzzy_pixels::bank_border_bottom_left(),
+                fuzzy_pixels::bank_border_right(),
+                fuzzy_pixels::bank_border_right(),
             ],
         )
     }
 
+    /// Read the number shown next to one of the status orbs. Returns None if
+    /// the number couldn't be read, for instance if the mouse is hovering over
+    /// the orb and a tooltip covers it.
+    pub fn orb_value(&self, frame: &impl Frame, orb: Orb) -> Option<i32> {
+        let frame = &self.calibrated(frame);
+        crate::orbs::read_orb_value(
+            frame,
+            self.locations.orb_text_top_left(orb),
+            Locations::ORB_TEXT_DIMENSIONS,
+        )
+    }
+
+    /// Remember how the compass looks. Must be called while the camera is
+    /// facing north, e.g. right after pressing the compass.
+    pub fn calibrate_compass(&mut self, frame: &impl Frame) {
+        self.compass_template = Some(crate::compass::CompassTemplate::capture(
+            frame,
+            self.locations.compass_icon(),
+        ));
+    }
+
+    /// Estimate how this client's brightness shifts colors compared to those
+    /// in colors.rs, by looking at fixed parts of the UI. Works best with the
+    /// inventory and chatbox open. Keeps the old calibration and returns
+    /// false if there aren't enough UI elements to go on.
+    pub fn calibrate_colors(&mut self, frame: &impl Frame) -> bool {
+        match ColorCalibration::estimate(frame, &self.locations) {
+            Some(calibration) => {
+                println!("Calibrated colors: {:?}", calibration);
+                self.color_calibration = calibration;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 'frame' as the client colors.rs was measured on would have drawn it.
+    /// Read frames through this before matching them against colors.rs.
+    pub fn calibrated<'a, F: Frame>(&self, frame: &'a F) -> CalibratedFrame<'a, F> {
+        CalibratedFrame::new(frame, &self.color_calibration)
+    }
+
+    /// Compare where the UI is drawn against where 'locations' expects it,
+    /// printing any anchors which are off. If all anchors were found and
+    /// agree, switch to the screen bounds they imply. Returns true if the
+    /// layout matched or was fixed.
+    pub fn relayout(&mut self, frame: &impl Frame) -> bool {
+        let layout = crate::layout::solve_layout(frame, &self.locations);
+        let mismatches = layout.mismatches();
+        if mismatches.is_empty() {
+            return true;
+        }
+        for mismatch in mismatches.iter() {
+            println!("Layout mismatch: {:?}", mismatch);
+        }
+        if !layout.is_complete() {
+            return false;
+        }
+        println!(
+            "Moving screen to top_left={:?}, dimensions={:?}",
+            layout.locations.top_left, layout.locations.dimensions
+        );
+        self.locations = layout.locations;
+        true
+    }
+
+    /// Camera yaw in degrees clockwise from north, read from the compass.
+    /// Returns None if the compass hasn't been calibrated.
+    pub fn camera_yaw_degrees(&self, frame: &impl Frame) -> Option<f32> {
+        self.compass_template
+            .as_ref()
+            .map(|template| template.estimate_yaw(frame, self.locations.compass_icon()))
+    }
+
+    /// Estimate which tile the player is on by matching the minimap against
+    /// 'reference'. Requires the compass to be calibrated to compensate for
+    /// the minimap rotating with the camera.
+    pub fn localize(
+        &self,
+        frame: &impl Frame,
+        reference: &crate::localization::ReferenceMap,
+        search: Option<(Position, i32)>,
+    ) -> Option<crate::localization::Localization> {
+        let yaw_degrees = self.camera_yaw_degrees(frame)?;
+        reference.localize(frame, self.locations.minimap_middle(), yaw_degrees, search)
+    }
+
+    /// Find groups of pixels matching any of 'fuzzy_pixels' on the open
+    /// screen, best first according to 'ranking'.
+    pub fn find_open_screen_blobs(
+        &self,
+        frame: &impl Frame,
+        fuzzy_pixels: &[impl PixelMatcher],
+        min_area: usize,
+        ranking: crate::blobs::BlobRanking,
+    ) -> Vec<crate::blobs::Blob> {
+        let frame = &self.calibrated(frame);
+        crate::blobs::find_blobs(
+            frame,
+            fuzzy_pixels,
+            &self.locations.top_left,
+            &self.locations.open_screen_dimensions(),
+            min_area,
+            ranking,
+        )
+    }
+
+    /// Find the dots for other players, NPCs, and ground items on the
+    /// minimap.
+    pub fn find_minimap_dots(&self, frame: &impl Frame) -> crate::minimap_dots::MinimapDots {
+        let frame = &self.calibrated(frame);
+        crate::minimap_dots::find_minimap_dots(frame, self.locations.minimap_middle())
+    }
+
+    /// Fraction of hitpoints remaining according to the healthbar above the
+    /// player's head. Returns None if the healthbar isn't shown, which is the
+    /// case whenever we aren't in combat.
+    pub fn player_health_fraction(&self, frame: &impl Frame) -> Option<f32> {
+        let frame = &self.calibrated(frame);
+        Self::healthbar_fraction(
+            frame,
+            self.locations.player_healthbar_left(),
+            Locations::OVERHEAD_HEALTHBAR_WIDTH,
+            &fuzzy_pixels::overhead_healthbar_green(),
+            &fuzzy_pixels::overhead_healthbar_red(),
+        )
+    }
+
+    /// Find the healthbars drawn above characters in the open screen. These
+    /// are shown for any character in combat, including our own player.
+    /// Returns the top left of each healthbar found.
+    pub fn find_overhead_healthbars(&self, frame: &impl Frame) -> Vec<Position> {
+        let frame = &self.calibrated(frame);
+        let top_left = self.locations.top_left;
+        let past_bottom_right = top_left + self.locations.open_screen_dimensions();
+        let is_healthbar = |pos: &Position| {
+            let pixel = frame.get_pixel(pos);
+            fuzzy_pixels::overhead_healthbar_green().matches(&pixel)
+                || fuzzy_pixels::overhead_healthbar_red().matches(&pixel)
+        };
+
+        let mut healthbars = Vec::<Position>::new();
+        // Healthbars are a few pixels tall, so no need to check every row.
+        for y in (top_left.y..past_bottom_right.y).step_by(2) {
+            let mut run_start = None;
+            for x in top_left.x..=past_bottom_right.x {
+                let pos = Position { x, y };
+                if x < past_bottom_right.x && is_healthbar(&pos) {
+                    if run_start.is_none() {
+                        run_start = Some(pos);
+                    }
+                    continue;
+                }
+                let start = match run_start.take() {
+                    Some(start) => start,
+                    None => continue,
+                };
+
+                // Allow for part of the bar being covered, but ignore long
+                // stretches of green/red which are part of the scenery.
+                let min_len = 2 * Locations::OVERHEAD_HEALTHBAR_WIDTH / 3;
+                let max_len = Locations::OVERHEAD_HEALTHBAR_WIDTH + 2;
+                if !(min_len..=max_len).contains(&(x - start.x)) {
+                    continue;
+                }
+                let already_found = healthbars.iter().any(|found| {
+                    (found.x - start.x).abs() <= 2
+                        && start.y - found.y <= Locations::OVERHEAD_HEALTHBAR_HEIGHT
+                });
+                if !already_found {
+                    healthbars.push(start);
+                }
+            }
+        }
+        healthbars
+    }
+
+    /// Fraction of hitpoints remaining for the enemy we are fighting, based on
+    /// the green/red split of the healthbar in the top left of the screen.
+    /// Returns None if no enemy healthbar is shown.
+    pub fn enemy_health_fraction(&self, frame: &impl Frame) -> Option<f32> {
+        let frame = &self.calibrated(frame);
+        let left = self.locations.enemy_healthbar_left();
+        let right = self.locations.enemy_healthbar_right();
+        Self::healthbar_fraction(
+            frame,
+            left,
+            right.x - left.x + 1,
+            &fuzzy_pixels::enemy_healthbar_green(),
+            &fuzzy_pixels::enemy_healthbar_red(),
+        )
+    }
+
     pub fn is_bank_quantity_all(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         frame.check_loose_pixel(
             &self.locations.bank_quantity_all(),
             &fuzzy_pixels::bank_quantity_on(),
         )
     }
     pub fn is_bank_quantity_x(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         frame.check_loose_pixel(
             &self.locations.bank_quantity_x(),
             &fuzzy_pixels::bank_quantity_on(),
         )
     }
     pub fn is_bank_quantity_one(&self, frame: &impl Frame) -> bool {
+        let frame = &self.calibrated(frame);
         frame.check_loose_pixel(
             &self.locations.bank_quantity_one(),
             &fuzzy_pixels::bank_quantity_on(),
         )
     }
+
+    /// Measure the green portion of a horizontal healthbar 'len' pixels long,
+    /// starting at 'left'.
+    fn healthbar_fraction(
+        frame: &impl Frame,
+        left: Position,
+        len: i32,
+        green: &FuzzyPixel,
+        red: &FuzzyPixel,
+    ) -> Option<f32> {
+        let mut num_green = 0;
+        let mut num_red = 0;
+        for dx in 0..len {
+            let pixel = frame.get_pixel(&Position {
+                x: left.x + dx,
+                y: left.y,
+            });
+            if green.matches(&pixel) {
+                num_green += 1;
+            } else if red.matches(&pixel) {
+                num_red += 1;
+            }
+        }
+
+        // Require most of the bar to match so that a stray green or red pixel
+        // in the scene isn't mistaken for a healthbar.
+        if num_green + num_red < len / 2 {
+            return None;
+        }
+        Some(num_green as f32 / (num_green + num_red) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bank_slot_checks_only_count_the_item() {
+        let num_checks =
+            |item| bank_slot_pixels::num_checks(&bank_slot_pixels::from_inventory(&item));
+        assert_eq!(num_checks(inventory_slot_pixels::empty()), 0);
+        assert!(num_checks(inventory_slot_pixels::clay_bank()) < bank_slot_pixels::MIN_NUM_CHECKS);
+        assert!(
+            num_checks(inventory_slot_pixels::tomato_bank()) >= bank_slot_pixels::MIN_NUM_CHECKS
+        );
+    }
+
+    #[test]
+    fn scan_bank_finds_items() {
+        let (width, height) = (1000, 700);
+        let mut frame = OwnedFrame::blank(width, height);
+        let framehandler = FrameHandler {
+            locations: Locations::new(
+                Position { x: 0, y: 0 },
+                util::DeltaPosition { dx: 1000, dy: 700 },
+            ),
+            compass_template: None,
+            color_calibration: ColorCalibration::identity(),
+        };
+
+        // Draw tin ore in the 11th visible slot.
+        let tin_ore = bank_slot_pixels::from_inventory(&inventory_slot_pixels::tin_ore_bank());
+        let top_left = framehandler.locations.bank_slot_check_top_left(10);
+        let spacing = Locations::INVENTORY_SLOT_CHECK_SPACING;
+        for (i, fuzzy_pixel) in tin_ore.iter().enumerate() {
+            if let Some(fuzzy_pixel) = fuzzy_pixel {
+                let i = i as i32;
+                let pos = Position {
+                    x: top_left.x + spacing.dx * (1 + i % 4),
+                    y: top_left.y + spacing.dy * (1 + i / 4),
+                };
+                frame.recolor_pixel(
+                    &pos,
+                    &Pixel {
+                        blue: fuzzy_pixel.blue_min,
+                        green: fuzzy_pixel.green_min,
+                        red: fuzzy_pixel.red_min,
+                    },
+                );
+            }
+        }
+
+        let copper_ore =
+            bank_slot_pixels::from_inventory(&inventory_slot_pixels::copper_ore_bank());
+        let found = framehandler.scan_bank(
+            &frame,
+            &[("tin", tin_ore), ("copper", copper_ore)],
+            /*tab=*/ 0,
+            /*rows_scrolled=*/ 2,
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found["tin"], BankSlot { tab: 0, index: 26 });
+    }
+
+    #[test]
+    fn bank_rows_scrolled_follows_thumb() {
+        // Saved from the client with the bank open and scrolled to the top.
+        let mut frame = OwnedFrame::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/readme/screenshot_inventories.png"
+        ));
+        let framehandler = FrameHandler {
+            locations: Locations::new(
+                Position { x: 12, y: 11 },
+                util::DeltaPosition { dx: 952, dy: 607 },
+            ),
+            compass_template: None,
+            color_calibration: ColorCalibration::identity(),
+        };
+        assert_eq!(framehandler.bank_rows_scrolled(&frame), Some(0));
+
+        // Move the 24 pixel thumb 5 pixels down, which is 2 rows.
+        let top = framehandler.locations.bank_scrollbar_top();
+        let bottom = framehandler.locations.bank_scrollbar_bottom();
+        let track = Pixel {
+            blue: 25,
+            green: 32,
+            red: 37,
+        };
+        frame.draw_vertical_line(&top, bottom.y - top.y + 1, &track);
+        frame.draw_vertical_line(
+            &Position {
+                x: top.x,
+                y: top.y + 5,
+            },
+            24,
+            &Pixel {
+                blue: 67,
+                green: 92,
+                red: 106,
+            },
+        );
+        assert_eq!(framehandler.bank_rows_scrolled(&frame), Some(2));
+
+        // Without a thumb we can't tell.
+        frame.draw_vertical_line(&top, bottom.y - top.y + 1, &track);
+        assert_eq!(framehandler.bank_rows_scrolled(&frame), None);
+    }
+
+    #[test]
+    fn fixed_classic_bank() {
+        // The bank, chatbox and inventory from screenshot_inventories.png,
+        // placed where the fixed classic client draws them. They are drawn
+        // the same in every layout, only where they go changes.
+        let frame = OwnedFrame::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/layouts/fixed_classic_bank.png"
+        ));
+        let framehandler = FrameHandler {
+            locations: Locations::with_layout_mode(
+                Position { x: 0, y: 0 },
+                Locations::FIXED_CLASSIC_DIMENSIONS,
+                crate::LayoutMode::FixedClassic,
+            ),
+            compass_template: None,
+            color_calibration: ColorCalibration::identity(),
+        };
+        assert_eq!(framehandler.bank_rows_scrolled(&frame), Some(0));
+
+        let bank_slot = |item| bank_slot_pixels::from_inventory(&item);
+        let found = framehandler.scan_bank(
+            &frame,
+            &[
+                (
+                    "cooked_shrimp",
+                    bank_slot(inventory_slot_pixels::cooked_shrimp_bank()),
+                ),
+                (
+                    "raw_shrimp",
+                    bank_slot(inventory_slot_pixels::raw_shrimp_bank()),
+                ),
+                (
+                    "raw_anchovies",
+                    bank_slot(inventory_slot_pixels::raw_anchovies_bank()),
+                ),
+            ],
+            /*tab=*/ 0,
+            /*rows_scrolled=*/ 0,
+        );
+        assert_eq!(found["cooked_shrimp"], BankSlot::from(3));
+        assert_eq!(found["raw_shrimp"], BankSlot::from(30));
+        assert_eq!(found["raw_anchovies"], BankSlot::from(31));
+
+        // The chatbox's parchment.
+        let locations = &framehandler.locations;
+        assert!(frame.check_loose_pixel(
+            &(locations.chatbox_inner_top_left() + util::DeltaPosition { dx: 402, dy: 41 }),
+            &fuzzy_pixels::chatbox_background()
+        ));
+    }
 }
@@ -0,0 +1,418 @@
+/// Check where the UI is actually drawn against where Locations assumes it is.
+///
+/// Locations places everything at fixed offsets from the corners of the
+/// screen, measured on one machine. If the UI shifts (a different client
+/// version, a plugin drawing over the side panel) clicks silently land in the
+/// wrong place. Here we find a few anchors which are easy to recognize, see
+/// how far they are from where Locations expects them, and derive the screen
+/// bounds that would put them in the right place. Where the anchors are also
+/// tells us which LayoutMode the client is in.
+use crate::blobs::{find_blobs, Blob, BlobRanking};
+use crate::locations::LayoutMode;
+use crate::{fuzzy_pixels, Frame, Locations};
+use util::*;
+
+/// How far from the expected position we look for an anchor.
+const SEARCH_RADIUS: i32 = 40;
+
+/// Anchors within this many pixels of where we expect them are fine.
+pub const LAYOUT_TOLERANCE: i32 = 2;
+
+/// How far the inventory panel may be from its expected size.
+const MAX_INVENTORY_SIZE_ERROR: i32 = 4;
+
+/// An empty inventory is about 50k pixels. Leave room for items.
+const MIN_INVENTORY_AREA: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    /// Our own player's dot, always drawn in the middle of the minimap.
+    /// Anchored to the top right of the screen.
+    Minimap,
+    /// Background of the open inventory. Anchored to the bottom right.
+    InventoryPanel,
+    /// The inventory icon in the bottom icon bar, red while the inventory is
+    /// open. Anchored to the bottom right.
+    BottomIcons,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnchorMatch {
+    pub anchor: Anchor,
+    /// Where Locations expects the anchor.
+    pub expected: Position,
+    /// Where we found it, if at all.
+    pub found: Option<Position>,
+}
+
+impl AnchorMatch {
+    pub fn offset(&self) -> Option<DeltaPosition> {
+        self.found.map(|found| found - self.expected)
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        match self.offset() {
+            Some(offset) => {
+                offset.dx.abs() > LAYOUT_TOLERANCE || offset.dy.abs() > LAYOUT_TOLERANCE
+            }
+            None => true,
+        }
+    }
+}
+
+pub struct Layout {
+    pub anchors: Vec<AnchorMatch>,
+
+    /// Locations with the screen bounds moved to fit the anchors found.
+    /// Edges without an anchor (the left edge) are left as assumed.
+    pub locations: Locations,
+}
+
+impl Layout {
+    pub fn mismatches(&self) -> Vec<&AnchorMatch> {
+        self.anchors
+            .iter()
+            .filter(|anchor| anchor.is_mismatch())
+            .collect()
+    }
+
+    /// Were all anchors found, and do they agree with each other on where the
+    /// right and bottom edges of the screen are. If not, 'locations'
+    /// shouldn't be trusted.
+    pub fn is_complete(&self) -> bool {
+        let offsets: Vec<DeltaPosition> = self
+            .anchors
+            .iter()
+            .filter_map(|anchor| anchor.offset())
+            .collect();
+        if offsets.len() != self.anchors.len() {
+            return false;
+        }
+        let spread = |values: Vec<i32>| {
+            values.iter().max().unwrap() - values.iter().min().unwrap() <= LAYOUT_TOLERANCE
+        };
+        // The minimap is anchored to the top, so only says where the bottom is
+        // in fixed mode.
+        let bottom_dys = self
+            .anchors
+            .iter()
+            .filter(|anchor| anchor.anchor != Anchor::Minimap)
+            .filter_map(|anchor| anchor.offset())
+            .map(|offset| offset.dy)
+            .collect();
+        spread(offsets.iter().map(|offset| offset.dx).collect()) && spread(bottom_dys)
+    }
+}
+
+/// Find the background of the open inventory within the given box. Checks
+/// that it is about the expected size.
+pub fn find_inventory_panel(
+    frame: &impl Frame,
+    top_left: &Position,
+    dimensions: &DeltaPosition,
+) -> Option<Blob> {
+    let inventory = find_blobs(
+        frame,
+        &[
+            fuzzy_pixels::inventory_background(),
+            fuzzy_pixels::inventory_background_dark(),
+        ],
+        top_left,
+        dimensions,
+        MIN_INVENTORY_AREA,
+        BlobRanking::Largest,
+    )
+    .into_iter()
+    .next()?;
+
+    let expected = Locations::INVENTORY_INNER_DIMENSIONS;
+    if (inventory.dimensions.dx - expected.dx).abs() > MAX_INVENTORY_SIZE_ERROR
+        || (inventory.dimensions.dy - expected.dy).abs() > MAX_INVENTORY_SIZE_ERROR
+    {
+        println!(
+            "Inventory is an unexpected size: {:?}",
+            inventory.dimensions
+        );
+        return None;
+    }
+    Some(inventory)
+}
+
+/// Box around 'expected' to search for an anchor in, with room for the
+/// anchor's own size. Kept within the frame.
+fn search_box(
+    frame: &impl Frame,
+    expected: Position,
+    size: DeltaPosition,
+) -> (Position, DeltaPosition) {
+    let top_left = Position {
+        x: (expected.x - SEARCH_RADIUS).max(0),
+        y: (expected.y - SEARCH_RADIUS).max(0),
+    };
+    let past_bottom_right = Position {
+        x: (expected.x + size.dx + SEARCH_RADIUS).min(frame.width() as i32),
+        y: (expected.y + size.dy + SEARCH_RADIUS).min(frame.height() as i32),
+    };
+    (top_left, past_bottom_right - top_left)
+}
+
+fn find_anchor(frame: &impl Frame, locations: &Locations, anchor: Anchor) -> AnchorMatch {
+    let (expected, found) = match anchor {
+        Anchor::Minimap => {
+            let expected = locations.minimap_middle();
+            let (top_left, dimensions) =
+                search_box(frame, expected, DeltaPosition { dx: 0, dy: 0 });
+            let found = find_blobs(
+                frame,
+                &[fuzzy_pixels::minimap_dot_player_white()],
+                &top_left,
+                &dimensions,
+                /*min_area=*/ 4,
+                BlobRanking::ClosestTo(expected),
+            )
+            .into_iter()
+            .next()
+            .map(|blob| blob.centroid);
+            (expected, found)
+        }
+        Anchor::InventoryPanel => {
+            let expected = locations.inventory_inner_top_left();
+            let (top_left, dimensions) =
+                search_box(frame, expected, Locations::INVENTORY_INNER_DIMENSIONS);
+            let found =
+                find_inventory_panel(frame, &top_left, &dimensions).map(|blob| blob.top_left);
+            (expected, found)
+        }
+        Anchor::BottomIcons => {
+            let expected = locations.inventory_icon_middle();
+            let (top_left, dimensions) =
+                search_box(frame, expected, DeltaPosition { dx: 0, dy: 0 });
+            let found = find_blobs(
+                frame,
+                &[fuzzy_pixels::inventory_icon_background_open()],
+                &top_left,
+                &dimensions,
+                /*min_area=*/ 100,
+                BlobRanking::Largest,
+            )
+            .into_iter()
+            .next()
+            .map(|blob| blob.centroid);
+            (expected, found)
+        }
+    };
+    AnchorMatch {
+        anchor,
+        expected,
+        found,
+    }
+}
+
+/// Find the anchors in 'frame' and compare them to 'assumed'. Requires the
+/// inventory to be open.
+pub fn solve_layout(frame: &impl Frame, assumed: &Locations) -> Layout {
+    let anchors: Vec<AnchorMatch> = [Anchor::Minimap, Anchor::InventoryPanel, Anchor::BottomIcons]
+        .iter()
+        .map(|anchor| find_anchor(frame, assumed, *anchor))
+        .collect();
+
+    let mean = |offsets: Vec<i32>| {
+        if offsets.is_empty() {
+            0
+        } else {
+            (offsets.iter().sum::<i32>() as f32 / offsets.len() as f32).round() as i32
+        }
+    };
+    let offsets_of = |pick: &dyn Fn(&Anchor) -> bool, axis: fn(DeltaPosition) -> i32| {
+        mean(
+            anchors
+                .iter()
+                .filter(|anchor| pick(&anchor.anchor))
+                .filter_map(|anchor| anchor.offset().map(axis))
+                .collect(),
+        )
+    };
+    let right_dx = offsets_of(&|_| true, |offset| offset.dx);
+    let top_dy = offsets_of(&|anchor| *anchor == Anchor::Minimap, |offset| offset.dy);
+    let bottom_dy = offsets_of(&|anchor| *anchor != Anchor::Minimap, |offset| offset.dy);
+
+    let layout_mode = assumed.layout_mode;
+    let (top_left, dimensions) = if layout_mode == LayoutMode::FixedClassic {
+        // Fixed mode can't change size, so the whole screen moves.
+        let dy = offsets_of(&|_| true, |offset| offset.dy);
+        (
+            assumed.top_left + DeltaPosition { dx: right_dx, dy },
+            assumed.dimensions,
+        )
+    } else {
+        (
+            assumed.top_left + DeltaPosition { dx: 0, dy: top_dy },
+            assumed.dimensions
+                + DeltaPosition {
+                    dx: right_dx,
+                    dy: bottom_dy - top_dy,
+                },
+        )
+    };
+    let (top_left, dimensions) = if layout_mode.fits(dimensions) {
+        (top_left, dimensions)
+    } else {
+        println!(
+            "{:?} doesn't fit the derived screen {:?}",
+            layout_mode, dimensions
+        );
+        (assumed.top_left, assumed.dimensions)
+    };
+    Layout {
+        anchors,
+        locations: Locations::with_layout_mode(top_left, dimensions, layout_mode),
+    }
+}
+
+/// Which layout mode the game in 'frame' is drawn in, found by checking which
+/// one puts all the anchors where they are. Requires the inventory to be
+/// open.
+pub fn detect_layout_mode(
+    frame: &impl Frame,
+    top_left: Position,
+    dimensions: DeltaPosition,
+) -> Option<LayoutMode> {
+    LayoutMode::ALL
+        .iter()
+        .copied()
+        .filter(|layout_mode| layout_mode.fits(dimensions))
+        .find(|layout_mode| {
+            let locations = Locations::with_layout_mode(top_left, dimensions, *layout_mode);
+            solve_layout(frame, &locations).mismatches().is_empty()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::fill;
+    use crate::types::*;
+    use crate::OwnedFrame;
+
+    /// Draw the anchors where 'locations' puts them, with those anchored to
+    /// the bottom right moved by 'shift' and the minimap moved by 'shift.dx'.
+    fn draw_anchors(frame: &mut OwnedFrame, locations: &Locations, shift: DeltaPosition) {
+        fill(
+            frame,
+            locations.inventory_inner_top_left() + shift,
+            Locations::INVENTORY_INNER_DIMENSIONS,
+            Pixel {
+                blue: 42,
+                green: 53,
+                red: 61,
+            },
+        );
+        fill(
+            frame,
+            locations.inventory_icon_middle() + shift - DeltaPosition { dx: 12, dy: 12 },
+            DeltaPosition { dx: 25, dy: 25 },
+            Pixel {
+                blue: 30,
+                green: 40,
+                red: 115,
+            },
+        );
+        fill(
+            frame,
+            locations.minimap_middle()
+                + DeltaPosition {
+                    dx: shift.dx,
+                    dy: 0,
+                }
+                - DeltaPosition { dx: 1, dy: 1 },
+            DeltaPosition { dx: 3, dy: 3 },
+            Pixel {
+                blue: 255,
+                green: 255,
+                red: 255,
+            },
+        );
+    }
+
+    #[test]
+    fn detects_shifted_ui() {
+        let mut frame = OwnedFrame::blank(1000, 700);
+        let assumed = Locations::new(Position { x: 0, y: 0 }, DeltaPosition { dx: 1000, dy: 700 });
+
+        // Draw the UI as if the screen's right edge was 5 pixels further right
+        // and its bottom 3 pixels higher.
+        let shift = DeltaPosition { dx: 5, dy: -3 };
+        draw_anchors(&mut frame, &assumed, shift);
+
+        let layout = solve_layout(&frame, &assumed);
+        assert_eq!(layout.mismatches().len(), 3);
+        assert!(layout.is_complete());
+        assert_eq!(
+            layout.locations.inventory_inner_top_left(),
+            assumed.inventory_inner_top_left() + shift
+        );
+        assert_eq!(
+            layout.locations.minimap_middle(),
+            assumed.minimap_middle() + DeltaPosition { dx: 5, dy: 0 }
+        );
+
+        // The inventory icon is drawn lower than the inventory, so they
+        // disagree on where the bottom of the screen is.
+        let icon_top_left =
+            assumed.inventory_icon_middle() + shift - DeltaPosition { dx: 12, dy: 12 };
+        let icon_dimensions = DeltaPosition { dx: 25, dy: 25 };
+        fill(
+            &mut frame,
+            icon_top_left,
+            icon_dimensions,
+            Pixel {
+                blue: 0,
+                green: 0,
+                red: 0,
+            },
+        );
+        fill(
+            &mut frame,
+            icon_top_left + DeltaPosition { dx: 0, dy: 8 },
+            icon_dimensions,
+            Pixel {
+                blue: 30,
+                green: 40,
+                red: 115,
+            },
+        );
+        let layout = solve_layout(&frame, &assumed);
+        assert!(layout.anchors.iter().all(|anchor| anchor.found.is_some()));
+        assert!(!layout.is_complete());
+
+        // Nothing drawn, so nothing found.
+        let layout = solve_layout(&OwnedFrame::blank(1000, 700), &assumed);
+        assert!(!layout.is_complete());
+        assert_eq!(layout.locations.top_left, assumed.top_left);
+        assert_eq!(layout.locations.dimensions, assumed.dimensions);
+    }
+
+    #[test]
+    fn detects_layout_mode() {
+        let top_left = Position { x: 0, y: 0 };
+        for layout_mode in LayoutMode::ALL.iter() {
+            let dimensions = match layout_mode {
+                LayoutMode::ResizableModernOneRow => DeltaPosition { dx: 1000, dy: 700 },
+                _ => Locations::FIXED_CLASSIC_DIMENSIONS,
+            };
+            let locations = Locations::with_layout_mode(top_left, dimensions, *layout_mode);
+            let mut frame = OwnedFrame::blank(dimensions.dx as usize, dimensions.dy as usize);
+            draw_anchors(&mut frame, &locations, DeltaPosition { dx: 0, dy: 0 });
+            assert_eq!(
+                detect_layout_mode(&frame, top_left, dimensions),
+                Some(*layout_mode)
+            );
+        }
+
+        let dimensions = DeltaPosition { dx: 1000, dy: 700 };
+        assert_eq!(
+            detect_layout_mode(&OwnedFrame::blank(1000, 700), top_left, dimensions),
+            None
+        );
+    }
+}
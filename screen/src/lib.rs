@@ -1,25 +1,46 @@
 pub mod action_text;
+pub mod blobs;
+pub mod calibration;
 pub mod colors;
+pub mod compass;
 pub mod frame;
+pub mod layout;
+pub mod localization;
 pub mod locations;
+pub mod minimap_dots;
+pub mod motion;
+pub mod orbs;
+#[cfg(test)]
+mod test_util;
 pub mod types;
+pub mod viewport;
 
 pub use action_text::Text as ActionText;
 pub use colors::*;
 pub use frame::*;
-pub use locations::Locations;
+pub use locations::{BankSlot, LayoutMode, Locations};
+pub use orbs::Orb;
 pub use types::*;
 
 use structopt::StructOpt;
 #[derive(Debug, StructOpt, Clone)]
 pub struct Config {
+    #[structopt(
+        long,
+        possible_values = &["auto"],
+        about = "Pass 'auto' to find the screen bounds and layout from a \
+                 screenshot at startup instead of giving screen_top_left and \
+                 screen_bottom_right. Requires the inventory to be open."
+    )]
+    pub screen: Option<String>,
+
     #[structopt(
         long,
         about = "Top left position in pixels of the screen 'x,y'. This is the \
                  first pixel that changes color based on what is shown, not \
                  the top left pixel of the window."
     )]
-    pub screen_top_left: util::Position,
+    pub screen_top_left: Option<util::Position>,
 
     #[structopt(
         long,
@@ -27,5 +48,104 @@ pub struct Config {
                  the last pixel that changes color based on what is shown, \
                  not the bottom right pixel of the window."
     )]
-    pub screen_bottom_right: util::Position,
+    pub screen_bottom_right: Option<util::Position>,
+
+    #[structopt(
+        long,
+        possible_values = &[
+            "auto",
+            "fixed-classic",
+            "resizable-classic",
+            "resizable-modern",
+            "resizable-modern-two-rows",
+        ],
+        about = "Game client layout set in the game's settings. Pass 'auto' to \
+                 detect it from a screenshot at startup, which requires the \
+                 inventory to be open. Defaults to resizable modern."
+    )]
+    pub layout: Option<String>,
+
+    #[structopt(
+        long,
+        about = "Compare fixed parts of the UI against the colors we expect at \
+                 startup, and compensate for a brightness other than 2/4. \
+                 Requires the inventory and chatbox to be open."
+    )]
+    pub calibrate_colors: bool,
+}
+
+impl Config {
+    /// Layout given with '--layout', None if it is to be detected.
+    fn layout_mode(&self) -> Option<LayoutMode> {
+        match self.layout.as_deref() {
+            None | Some("auto") => None,
+            Some(name) => Some(name.parse().unwrap()),
+        }
+    }
+
+    /// Search 'frame', a screenshot, for the game. Also finds the layout,
+    /// unless '--layout' names one.
+    fn find_screen(&self, frame: &impl Frame) -> Config {
+        let found = viewport::find_screen_config(frame, self.layout_mode())
+            .expect("Unable to find the game screen, is the inventory open?");
+        println!(
+            "Found screen: --screen-top-left {} --screen-bottom-right {} --layout {}",
+            format_position(found.screen_top_left.unwrap()),
+            format_position(found.screen_bottom_right.unwrap()),
+            found.layout.as_deref().unwrap(),
+        );
+        found
+    }
+
+    /// Top left and bottom right of the screen. With '--screen auto' this
+    /// searches 'frame', a screenshot, for the game.
+    pub fn bounds(&self, frame: &impl Frame) -> (util::Position, util::Position) {
+        if self.screen.as_deref() == Some("auto") {
+            return self.find_screen(frame).bounds(frame);
+        }
+
+        (
+            self.screen_top_left
+                .expect("Pass --screen-top-left or --screen auto"),
+            self.screen_bottom_right
+                .expect("Pass --screen-bottom-right or --screen auto"),
+        )
+    }
+
+    /// Locations for the screen bounds and layout given. With '--screen auto'
+    /// or '--layout auto' this checks which layout fits 'frame', a
+    /// screenshot.
+    pub fn locations(&self, frame: &impl Frame) -> Locations {
+        if self.screen.as_deref() == Some("auto") {
+            // Finding the screen also tells us the layout.
+            return self.find_screen(frame).locations(frame);
+        }
+
+        let (top_left, bottom_right) = self.bounds(frame);
+        let dimensions = util::DeltaPosition {
+            dx: bottom_right.x - top_left.x + 1,
+            dy: bottom_right.y - top_left.y + 1,
+        };
+        match self.layout.as_deref() {
+            None => Locations::new(top_left, dimensions),
+            Some("auto") => match layout::detect_layout_mode(frame, top_left, dimensions) {
+                Some(layout_mode) => {
+                    println!("Found layout: --layout {}", layout_mode.name());
+                    Locations::with_layout_mode(top_left, dimensions, layout_mode)
+                }
+                None => {
+                    println!("Unable to detect the layout, assuming resizable modern.");
+                    Locations::new(top_left, dimensions)
+                }
+            },
+            Some(_) => {
+                Locations::with_layout_mode(top_left, dimensions, self.layout_mode().unwrap())
+            }
+        }
+    }
+}
+
+/// Format 'pos' the way Position is parsed on the command line, "x,y".
+pub fn format_position(pos: util::Position) -> String {
+    format!("{},{}", pos.x, pos.y)
 }
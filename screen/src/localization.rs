@@ -0,0 +1,363 @@
+/// Figure out where the player is in the world by matching the minimap against
+/// a reference map.
+///
+/// The reference map is an image of (part of) the world drawn the way the
+/// minimap draws it, north up, at Locations::MINIMAP_PIXELS_PER_TILE. The
+/// simplest way to make one is to stitch together minimap screenshots taken
+/// facing north.
+use crate::types::*;
+use crate::{Frame, Locations, OwnedFrame};
+use util::*;
+
+/// Radius of the minimap used for matching. Stays clear of the orbs and the
+/// compass which overlap the edge of the minimap.
+pub const LOCALIZATION_RADIUS: i32 = Locations::MINIMAP_RADIUS - 12;
+
+/// Ignore the middle of the minimap where the player's dot is drawn.
+const PLAYER_DOT_RADIUS: i32 = 3;
+
+/// Number of best coarse matches which are searched at full resolution.
+const NUM_COARSE_MATCHES_TO_REFINE: usize = 5;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Localization {
+    /// World tile the player is standing on.
+    pub tile: Position,
+
+    /// Normalized cross-correlation of the best match, [0, 1]. Values near 1
+    /// mean the minimap matched the reference map closely.
+    pub confidence: f32,
+}
+
+pub struct ReferenceMap {
+    pub map: OwnedFrame,
+
+    /// World tile drawn at the top left of 'map'. Note that world tiles count
+    /// y upwards (north), while the image counts y downwards.
+    pub top_left_tile: Position,
+
+    /// Brightness of each pixel in 'map', cached since we read it constantly.
+    brightness: Vec<f32>,
+}
+
+/// The minimap turned to face north, as offsets from the middle of the
+/// minimap and the brightness there.
+struct MinimapPatch {
+    pixels: Vec<(DeltaPosition, f32)>,
+}
+
+fn brightness(pixel: &Pixel) -> f32 {
+    (pixel.red as f32 + pixel.green as f32 + pixel.blue as f32) / 3.0
+}
+
+impl MinimapPatch {
+    /// Only take every 'spacing' pixels to speed up coarse searches.
+    fn capture(
+        frame: &impl Frame,
+        minimap_middle: Position,
+        yaw_degrees: f32,
+        spacing: i32,
+    ) -> MinimapPatch {
+        // Turning the camera clockwise rotates the minimap counterclockwise.
+        let angle = degrees_to_radians(-yaw_degrees);
+        let mut pixels = Vec::new();
+        for dy in (-LOCALIZATION_RADIUS..=LOCALIZATION_RADIUS).step_by(spacing as usize) {
+            for dx in (-LOCALIZATION_RADIUS..=LOCALIZATION_RADIUS).step_by(spacing as usize) {
+                let offset = DeltaPosition { dx, dy };
+                let distance = offset.distance();
+                if distance > LOCALIZATION_RADIUS || distance <= PLAYER_DOT_RADIUS {
+                    continue;
+                }
+                let pixel = frame.get_pixel(&(minimap_middle + offset.rotate(angle)));
+                pixels.push((offset, brightness(&pixel)));
+            }
+        }
+        MinimapPatch { pixels }
+    }
+}
+
+impl ReferenceMap {
+    pub fn new(map: OwnedFrame, top_left_tile: Position) -> ReferenceMap {
+        let mut brightness_values = Vec::with_capacity(map.width * map.height);
+        for y in 0..map.height as i32 {
+            for x in 0..map.width as i32 {
+                brightness_values.push(brightness(&map.get_pixel(&Position { x, y })));
+            }
+        }
+        ReferenceMap {
+            map,
+            top_left_tile,
+            brightness: brightness_values,
+        }
+    }
+
+    pub fn load(fpath: &str, top_left_tile: Position) -> ReferenceMap {
+        Self::new(OwnedFrame::load(fpath), top_left_tile)
+    }
+
+    pub fn map_pos_to_tile(&self, pos: &Position) -> Position {
+        Position {
+            x: self.top_left_tile.x + pos.x / Locations::MINIMAP_PIXELS_PER_TILE,
+            y: self.top_left_tile.y - pos.y / Locations::MINIMAP_PIXELS_PER_TILE,
+        }
+    }
+
+    /// Center of 'tile' in the map.
+    pub fn tile_to_map_pos(&self, tile: &Position) -> Position {
+        let pixels_per_tile = Locations::MINIMAP_PIXELS_PER_TILE;
+        Position {
+            x: (tile.x - self.top_left_tile.x) * pixels_per_tile + pixels_per_tile / 2,
+            y: (self.top_left_tile.y - tile.y) * pixels_per_tile + pixels_per_tile / 2,
+        }
+    }
+
+    fn brightness_at(&self, pos: &Position) -> f32 {
+        self.brightness[pos.y as usize * self.map.width + pos.x as usize]
+    }
+
+    /// Normalized cross-correlation between 'patch' and the map centered at
+    /// 'middle'.
+    fn correlation(&self, patch: &MinimapPatch, middle: Position) -> f32 {
+        let n = patch.pixels.len() as f32;
+        let patch_mean = patch.pixels.iter().map(|(_, b)| b).sum::<f32>() / n;
+
+        let mut patch_variance = 0.0;
+        let mut map_sum = 0.0;
+        let mut map_sum_squares = 0.0;
+        let mut cross = 0.0;
+        for (offset, patch_brightness) in patch.pixels.iter() {
+            let patch_value = patch_brightness - patch_mean;
+            let map_value = self.brightness_at(&(middle + *offset));
+            patch_variance += patch_value * patch_value;
+            map_sum += map_value;
+            map_sum_squares += map_value * map_value;
+            cross += patch_value * map_value;
+        }
+        let map_variance = map_sum_squares - map_sum * map_sum / n;
+        if patch_variance <= 0.0 || map_variance <= 0.0 {
+            return 0.0;
+        }
+        cross / (patch_variance * map_variance).sqrt()
+    }
+
+    /// Find the map position in 'candidates' which best matches 'patch'.
+    fn best_match(
+        &self,
+        patch: &MinimapPatch,
+        candidates: impl Iterator<Item = Position>,
+    ) -> Option<(Position, f32)> {
+        candidates
+            .map(|pos| (pos, self.correlation(patch, pos)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// Estimate where the player is from the minimap in 'frame'.
+    ///
+    /// 'search' optionally limits the search to within a number of tiles of
+    /// a given tile, such as the last known location. Searching the entire
+    /// map works, but is slow for large maps.
+    pub fn localize(
+        &self,
+        frame: &impl Frame,
+        minimap_middle: Position,
+        yaw_degrees: f32,
+        search: Option<(Position, i32)>,
+    ) -> Option<Localization> {
+        // The patch must fit entirely within the map.
+        let mut min = Position {
+            x: LOCALIZATION_RADIUS,
+            y: LOCALIZATION_RADIUS,
+        };
+        let mut max = Position {
+            x: self.map.width as i32 - LOCALIZATION_RADIUS - 1,
+            y: self.map.height as i32 - LOCALIZATION_RADIUS - 1,
+        };
+        if let Some((tile, radius_tiles)) = search {
+            let middle = self.tile_to_map_pos(&tile);
+            let radius = radius_tiles * Locations::MINIMAP_PIXELS_PER_TILE;
+            min = Position {
+                x: std::cmp::max(min.x, middle.x - radius),
+                y: std::cmp::max(min.y, middle.y - radius),
+            };
+            max = Position {
+                x: std::cmp::min(max.x, middle.x + radius),
+                y: std::cmp::min(max.y, middle.y + radius),
+            };
+        }
+        if min.x > max.x || min.y > max.y {
+            return None;
+        }
+
+        // Coarse search every tile with a sparse patch, then refine around the
+        // best match at full resolution.
+        let step = Locations::MINIMAP_PIXELS_PER_TILE;
+        let coarse_patch = MinimapPatch::capture(frame, minimap_middle, yaw_degrees, step);
+        let coarse_candidates = (min.y..=max.y).step_by(step as usize).flat_map(|y| {
+            (min.x..=max.x)
+                .step_by(step as usize)
+                .map(move |x| Position { x, y })
+        });
+        let mut coarse_matches: Vec<(Position, f32)> = coarse_candidates
+            .map(|pos| (pos, self.correlation(&coarse_patch, pos)))
+            .collect();
+        // The coarse grid can miss the true position by a couple of pixels,
+        // which may let a lookalike spot win, so refine the best few.
+        coarse_matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        coarse_matches.truncate(NUM_COARSE_MATCHES_TO_REFINE);
+
+        let fine_patch = MinimapPatch::capture(frame, minimap_middle, yaw_degrees, 2);
+        let fine_candidates = coarse_matches.iter().flat_map(|&(coarse_pos, _)| {
+            (-step..=step).flat_map(move |dy| {
+                (-step..=step).map(move |dx| Position {
+                    x: coarse_pos.x + dx,
+                    y: coarse_pos.y + dy,
+                })
+            })
+        });
+        let fine_candidates = fine_candidates
+            .filter(|pos| pos.x >= min.x && pos.y >= min.y && pos.x <= max.x && pos.y <= max.y);
+        let (pos, correlation) = self.best_match(&fine_patch, fine_candidates)?;
+
+        Some(Localization {
+            tile: self.map_pos_to_tile(&pos),
+            confidence: correlation.max(0.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::noise;
+
+    const MINIMAP_MIDDLE: Position = Position { x: 80, y: 80 };
+
+    // A map made of blocks of pseudo random brightness.
+    fn reference_map() -> ReferenceMap {
+        let mut map = OwnedFrame::blank(240, 240);
+        for y in 0..240 {
+            for x in 0..240 {
+                let value = noise(x / 6, y / 6);
+                map.recolor_pixel(
+                    &Position { x, y },
+                    &Pixel {
+                        blue: value,
+                        green: value,
+                        red: value,
+                    },
+                );
+            }
+        }
+        ReferenceMap::new(map, Position { x: 3200, y: 3300 })
+    }
+
+    // Draw the minimap as it would look standing at 'map_pos' with the camera
+    // at 'yaw_degrees'.
+    fn draw_minimap(reference: &ReferenceMap, map_pos: Position, yaw_degrees: f32) -> OwnedFrame {
+        let mut frame = OwnedFrame::blank(161, 161);
+        let angle = degrees_to_radians(yaw_degrees);
+        for y in 0..161 {
+            for x in 0..161 {
+                let screen_offset = Position { x, y } - MINIMAP_MIDDLE;
+                if screen_offset.distance() > Locations::MINIMAP_RADIUS {
+                    continue;
+                }
+                let world_pos = map_pos + screen_offset.rotate(angle);
+                frame.recolor_pixel(&Position { x, y }, &reference.map.get_pixel(&world_pos));
+            }
+        }
+        // The player's dot.
+        frame.recolor_pixel(
+            &MINIMAP_MIDDLE,
+            &Pixel {
+                blue: 255,
+                green: 255,
+                red: 255,
+            },
+        );
+        frame
+    }
+
+    #[test]
+    fn localize_facing_north() {
+        let reference = reference_map();
+        let tile = Position { x: 3220, y: 3270 };
+        let frame = draw_minimap(&reference, reference.tile_to_map_pos(&tile), 0.0);
+
+        let localization = reference
+            .localize(&frame, MINIMAP_MIDDLE, 0.0, None)
+            .unwrap();
+        assert_eq!(localization.tile, tile);
+        assert!(localization.confidence > 0.9, "{:?}", localization);
+    }
+
+    #[test]
+    fn localize_rotated_with_search() {
+        let reference = reference_map();
+        let tile = Position { x: 3225, y: 3265 };
+        let frame = draw_minimap(&reference, reference.tile_to_map_pos(&tile), 30.0);
+
+        let localization = reference
+            .localize(
+                &frame,
+                MINIMAP_MIDDLE,
+                30.0,
+                Some((Position { x: 3222, y: 3268 }, 5)),
+            )
+            .unwrap();
+        assert!(
+            (localization.tile - tile).distance() <= 1,
+            "{:?}",
+            localization
+        );
+        assert!(localization.confidence > 0.5, "{:?}", localization);
+    }
+
+    #[test]
+    fn localize_screenshot() {
+        // Both saved from the client near the Draynor bank, facing north. The
+        // red boxes in the open screen screenshot put the game screen at
+        // (12, 11) to (963, 617).
+        let load = |name: &str| {
+            OwnedFrame::load(&format!(
+                "{}/../data/readme/screenshot_{}.png",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ))
+        };
+        let minimap_middle = Locations::new(
+            Position { x: 12, y: 11 },
+            DeltaPosition { dx: 952, dy: 607 },
+        )
+        .minimap_middle();
+
+        // Use the minimap from one screenshot as the reference map, lined up so
+        // that the player is in the middle of tile (3200, 3300).
+        let half_width = Locations::MINIMAP_RADIUS + Locations::MINIMAP_PIXELS_PER_TILE / 2;
+        let mut map = load("inventories");
+        map.crop(
+            minimap_middle
+                - DeltaPosition {
+                    dx: half_width,
+                    dy: half_width,
+                },
+            DeltaPosition {
+                dx: 2 * half_width + 1,
+                dy: 2 * half_width + 1,
+            },
+        );
+        let top_left_tile = Position {
+            x: 3200 - half_width / Locations::MINIMAP_PIXELS_PER_TILE,
+            y: 3300 + half_width / Locations::MINIMAP_PIXELS_PER_TILE,
+        };
+        let reference = ReferenceMap::new(map, top_left_tile);
+
+        // By the other screenshot the player had moved a tile south east. The
+        // minimap also shows other players and NPCs which moved in between.
+        let localization = reference
+            .localize(&load("open_screen"), minimap_middle, 0.0, None)
+            .unwrap();
+        assert_eq!(localization.tile, Position { x: 3201, y: 3299 });
+        assert!(localization.confidence > 0.9, "{:?}", localization);
+    }
+}
@@ -1,5 +1,214 @@
+use crate::Orb;
+use std::str::FromStr;
 use util::*;
 
+/// How the game client arranges its UI, chosen in the game's settings under
+/// "Game client layout".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutMode {
+    /// The original client, which is always 765x503.
+    FixedClassic,
+    /// Resizable, but with the side panel drawn as one block in the bottom
+    /// right like in fixed mode, with a row of icons above and below it.
+    ResizableClassic,
+    /// Resizable, with the side panel icons along the bottom of the screen.
+    /// Once the screen is wide enough the icons fit in 1 row.
+    ResizableModernOneRow,
+    /// Resizable modern on a screen too narrow for 1 row of icons, so they
+    /// are stacked in 2 rows in the bottom right.
+    ResizableModernTwoRows,
+}
+
+/// Offsets which change between layout modes. Each is given from the corner
+/// of the screen that part of the UI is anchored to.
+struct LayoutGeometry {
+    /// From the top right of the screen to the top right of the minimap and
+    /// the orbs around it.
+    minimap_top_right: DeltaPosition,
+    /// From the bottom right of the screen to the top left of the inner
+    /// inventory.
+    inventory_inner_top_left: DeltaPosition,
+    /// From the bottom right of the screen to the top left of the first
+    /// (combat) icon.
+    bottom_icons_top_left: DeltaPosition,
+    bottom_icons_per_row: i32,
+    /// Distance from the top of one row of icons to the top of the next.
+    bottom_icons_row_spacing: i32,
+    /// From the bottom left of the screen to the top left of the chatbox,
+    /// including its border.
+    chatbox_outer_top_left: DeltaPosition,
+    /// From the top left of the screen to the top left of the game view, and
+    /// its size, if it doesn't stretch with the screen. Interfaces like the
+    /// bank are centered on it. When None they are centered on the space left
+    /// of the minimap and above the chatbox instead.
+    fixed_viewport: Option<(DeltaPosition, DeltaPosition)>,
+}
+
+impl LayoutMode {
+    pub const ALL: [LayoutMode; 4] = [
+        LayoutMode::FixedClassic,
+        LayoutMode::ResizableClassic,
+        LayoutMode::ResizableModernOneRow,
+        LayoutMode::ResizableModernTwoRows,
+    ];
+
+    /// Name used to select the mode on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayoutMode::FixedClassic => "fixed-classic",
+            LayoutMode::ResizableClassic => "resizable-classic",
+            LayoutMode::ResizableModernOneRow => "resizable-modern",
+            LayoutMode::ResizableModernTwoRows => "resizable-modern-two-rows",
+        }
+    }
+
+    /// The classic layouts always show the chatbox area and keep the side
+    /// panel in a block, instead of overlaying them on the game.
+    pub fn is_classic(&self) -> bool {
+        matches!(
+            self,
+            LayoutMode::FixedClassic | LayoutMode::ResizableClassic
+        )
+    }
+
+    /// Can the game be drawn in this mode on a screen of this size.
+    pub fn fits(&self, dimensions: DeltaPosition) -> bool {
+        let fixed = Locations::FIXED_CLASSIC_DIMENSIONS;
+        match self {
+            LayoutMode::FixedClassic => dimensions == fixed,
+            LayoutMode::ResizableModernOneRow => {
+                dimensions.dx >= Locations::MIN_SCREEN_WIDTH && dimensions.dy >= fixed.dy
+            }
+            _ => dimensions.dx >= fixed.dx && dimensions.dy >= fixed.dy,
+        }
+    }
+
+    /// Offset from the top left of the inner inventory to the bottom right of
+    /// the screen.
+    pub fn inventory_to_bottom_right(&self) -> DeltaPosition {
+        let offset = self.geometry().inventory_inner_top_left;
+        DeltaPosition {
+            dx: -offset.dx,
+            dy: -offset.dy,
+        }
+    }
+
+    /// Offset from the top right of the screen to the middle of the minimap.
+    pub fn minimap_middle_offset(&self) -> DeltaPosition {
+        self.geometry().minimap_top_right + Locations::MINIMAP_MIDDLE_OFFSET
+    }
+
+    fn geometry(&self) -> LayoutGeometry {
+        match self {
+            // The classic side panel sits in the bottom right, so in fixed
+            // mode the minimap is pushed left of the panel's frame.
+            LayoutMode::FixedClassic | LayoutMode::ResizableClassic => LayoutGeometry {
+                minimap_top_right: DeltaPosition {
+                    dx: if *self == LayoutMode::FixedClassic {
+                        -39
+                    } else {
+                        0
+                    },
+                    dy: 0,
+                },
+                inventory_inner_top_left: DeltaPosition { dx: -212, dy: -297 },
+                bottom_icons_top_left: DeltaPosition { dx: -243, dy: -334 },
+                bottom_icons_per_row: 7,
+                bottom_icons_row_spacing: 298,
+                chatbox_outer_top_left: DeltaPosition { dx: 0, dy: -164 },
+                fixed_viewport: if *self == LayoutMode::FixedClassic {
+                    Some((
+                        DeltaPosition { dx: 4, dy: 4 },
+                        DeltaPosition { dx: 512, dy: 334 },
+                    ))
+                } else {
+                    None
+                },
+            },
+            LayoutMode::ResizableModernOneRow => LayoutGeometry {
+                minimap_top_right: DeltaPosition { dx: 0, dy: 0 },
+                inventory_inner_top_left: DeltaPosition { dx: -197, dy: -304 },
+                bottom_icons_top_left: DeltaPosition { dx: -428, dy: -35 },
+                bottom_icons_per_row: Locations::NUM_BOTTOM_ICONS,
+                bottom_icons_row_spacing: 0,
+                chatbox_outer_top_left: DeltaPosition { dx: 0, dy: -164 },
+                fixed_viewport: None,
+            },
+            // Same as 1 row, but the inventory sits on top of the extra row.
+            LayoutMode::ResizableModernTwoRows => LayoutGeometry {
+                minimap_top_right: DeltaPosition { dx: 0, dy: 0 },
+                inventory_inner_top_left: DeltaPosition { dx: -197, dy: -340 },
+                bottom_icons_top_left: DeltaPosition { dx: -230, dy: -71 },
+                bottom_icons_per_row: 7,
+                bottom_icons_row_spacing: 36,
+                chatbox_outer_top_left: DeltaPosition { dx: 0, dy: -164 },
+                fixed_viewport: None,
+            },
+        }
+    }
+}
+
+/// Where an item is in the bank. 'index' counts across rows within 'tab',
+/// starting from the top left, and may be beyond what fits on the screen.
+/// Tab 0 is the main tab, which shows every item in the bank.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BankSlot {
+    pub tab: i32,
+    pub index: i32,
+}
+
+impl BankSlot {
+    pub fn row(&self) -> i32 {
+        self.index / Locations::NUM_BANK_COLUMNS
+    }
+}
+
+/// A plain slot index refers to the main tab.
+impl From<i32> for BankSlot {
+    fn from(index: i32) -> Self {
+        BankSlot { tab: 0, index }
+    }
+}
+
+impl FromStr for BankSlot {
+    type Err = String;
+
+    /// Input is either "index" for the main tab or "tab,index".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<i32>()
+                .map_err(|e| format!("Invalid bank slot '{}': {}", s, e))
+        };
+        let parts: Vec<&str> = s.trim().split(',').collect();
+        let slot = match parts.as_slice() {
+            [index] => BankSlot::from(parse(index)?),
+            [tab, index] => BankSlot {
+                tab: parse(tab)?,
+                index: parse(index)?,
+            },
+            _ => return Err(format!("Expected 'index' or 'tab,index', got '{}'", s)),
+        };
+        if !(0..Locations::NUM_BANK_TABS).contains(&slot.tab) || slot.index < 0 {
+            return Err(format!("Bank slot '{}' is out of range", s));
+        }
+        Ok(slot)
+    }
+}
+
+impl FromStr for LayoutMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LayoutMode::ALL
+            .iter()
+            .find(|mode| mode.name() == s)
+            .copied()
+            .ok_or(format!("Unknown layout mode '{}'", s))
+    }
+}
+
 // TODO: refactor away from simple, compound, abstract. Move to just grouping by
 // topic: Bank, map, etc.
 
@@ -17,6 +226,8 @@ pub struct Locations {
     // Width and height of the screen, such that the bottom right pixel is
     // (top_left + dimensions - 1).
     pub dimensions: DeltaPosition,
+
+    pub layout_mode: LayoutMode,
 }
 
 impl Locations {
@@ -25,14 +236,40 @@ impl Locations {
     pub const BOTTOM_ICONS_DIMENSIONS: DeltaPosition = DeltaPosition { dx: 33, dy: 35 };
     pub const NUM_BOTTOM_ICONS: i32 = 13;
 
+    // Narrowest screen on which resizable modern fits all the icons in the
+    // bottom right in 1 row.
+    pub const MIN_SCREEN_WIDTH: i32 = 948;
+
+    // Size of the fixed mode client. Resizable modes can't be made smaller.
+    pub const FIXED_CLASSIC_DIMENSIONS: DeltaPosition = DeltaPosition { dx: 765, dy: 503 };
+
+    /// Locations for resizable modern, which the bot was originally written
+    /// for. The icons go in 1 or 2 rows depending on the screen width.
     pub fn new(top_left: Position, dimensions: DeltaPosition) -> Locations {
-        // We are tied to the assumption that the screen is wide enough for all
-        // the icons in the bottom right to fit in 1 row.
-        assert!(dimensions.dx > 947);
+        let layout_mode = if LayoutMode::ResizableModernOneRow.fits(dimensions) {
+            LayoutMode::ResizableModernOneRow
+        } else {
+            LayoutMode::ResizableModernTwoRows
+        };
+        Self::with_layout_mode(top_left, dimensions, layout_mode)
+    }
+
+    pub fn with_layout_mode(
+        top_left: Position,
+        dimensions: DeltaPosition,
+        layout_mode: LayoutMode,
+    ) -> Locations {
+        assert!(
+            layout_mode.fits(dimensions),
+            "{:?} doesn't fit a screen of {:?}",
+            layout_mode,
+            dimensions
+        );
 
         Locations {
             top_left,
             dimensions,
+            layout_mode,
         }
     }
 
@@ -64,6 +301,12 @@ impl Locations {
         Self::to_top_right(self.top_left, self.dimensions)
     }
 
+    // The minimap and orbs are drawn the same in every layout, but in fixed
+    // mode they don't reach the corner of the screen.
+    fn minimap_top_right(&self) -> Position {
+        self.top_right() + self.layout_mode.geometry().minimap_top_right
+    }
+
     fn bottom_right(&self) -> Position {
         Self::to_bottom_right(self.top_left, self.dimensions)
     }
@@ -88,6 +331,35 @@ impl Locations {
             y: self.top_left.y + 45,
         }
     }
+    // The healthbar above the player's head. Only shown during combat, and
+    // only in this position while the camera is at the default pitch and zoom
+    // (after pressing the compass) since the player is then drawn at the
+    // middle of the screen.
+    pub const OVERHEAD_HEALTHBAR_WIDTH: i32 = 30;
+    pub const OVERHEAD_HEALTHBAR_HEIGHT: i32 = 5;
+    // How far below an overhead healthbar the character it belongs to is
+    // drawn. Used to tell which character on screen a healthbar belongs to.
+    pub const OVERHEAD_HEALTHBAR_OWNER_HEIGHT: i32 = 70;
+    pub fn player_healthbar_left(&self) -> Position {
+        let mid = self.mid_screen();
+        Position {
+            x: mid.x - Self::OVERHEAD_HEALTHBAR_WIDTH / 2,
+            y: mid.y - 38,
+        }
+    }
+    // The right click menu opens with its header under the mouse and is
+    // centered on the mouse horizontally. Its width depends on the longest
+    // entry.
+    pub const RIGHT_CLICK_MENU_HEADER_HEIGHT: i32 = 19;
+    pub const RIGHT_CLICK_MENU_ENTRY_HEIGHT: i32 = 15;
+    pub const RIGHT_CLICK_MENU_MAX_ENTRIES: i32 = 10;
+    pub const RIGHT_CLICK_MENU_MAX_HALF_WIDTH: i32 = 120;
+    // Top left of the text in the first entry, relative to where the menu was
+    // opened, ignoring the unknown horizontal offset.
+    pub const RIGHT_CLICK_MENU_TEXT_OFFSET_Y: i32 = Self::RIGHT_CLICK_MENU_HEADER_HEIGHT - 1;
+    // Ground item labels are drawn above the item, so hover this far below
+    // the label to reach the item itself.
+    pub const GROUND_ITEM_LABEL_OFFSET: DeltaPosition = DeltaPosition { dx: 0, dy: 15 };
     pub fn midpoint(top_left: Position, dimensions: DeltaPosition) -> Position {
         Position {
             x: top_left.x + (dimensions.dx as f32 / 2.0).round() as i32,
@@ -208,25 +480,21 @@ impl Locations {
 
     // Locations given in reference to the bottom left corner of the screen.
     pub fn all_chat_button(&self) -> Position {
-        let Position { x, y } = self.bottom_left();
+        let Position { x, y } = self.chatbox_outer_top_left();
         Position {
             x: x + 17,
-            y: y - 12,
+            y: y + 152,
         }
     }
     pub fn chatbox_outer_top_left(&self) -> Position {
-        let Position { x, y } = self.bottom_left();
-        Position { x: x, y: y - 164 }
+        self.bottom_left() + self.layout_mode.geometry().chatbox_outer_top_left
     }
     pub fn chatbox_outer_dimensions(&self) -> DeltaPosition {
         DeltaPosition { dx: 519, dy: 142 }
     }
     pub fn chatbox_inner_top_left(&self) -> Position {
-        let Position { x, y } = self.bottom_left();
-        Position {
-            x: x + 7,
-            y: y - 157,
-        }
+        let Position { x, y } = self.chatbox_outer_top_left();
+        Position { x: x + 7, y: y + 7 }
     }
     pub fn chatbox_inner_dimensions(&self) -> DeltaPosition {
         DeltaPosition { dx: 506, dy: 129 }
@@ -250,19 +518,55 @@ impl Locations {
     ///   chatbox. It extends to fill this space up until a certain max height,
     ///   and then space is added symmetrically above and below.
     /// - Horizontally the bank is centered around the center of the worldmap.
+    ///
+    /// In fixed mode the same holds within the game view instead of the
+    /// whole screen, see bank_area.
     pub const BANK_BORDER_WIDTH: i32 = 6;
     pub const NUM_BANK_COLUMNS: i32 = 8;
-    /// Maximum number of rows that we can assume to have access to. This number
-    /// can be much larger if the screen is expanded, but the bot will only use
-    /// the first 5.
-    pub const NUM_BANK_ROWS: i32 = 5;
-    pub const NUM_BANK_SLOTS: i32 = Self::NUM_BANK_COLUMNS * Self::NUM_BANK_ROWS;
+    /// Rows that are visible even on the smallest screen. See num_bank_rows
+    /// for the actual number.
+    pub const MIN_NUM_BANK_ROWS: i32 = 5;
+    pub const MIN_NUM_BANK_SLOTS: i32 = Self::NUM_BANK_COLUMNS * Self::MIN_NUM_BANK_ROWS;
+    /// The main tab plus 9 custom tabs.
+    pub const NUM_BANK_TABS: i32 = 10;
+    const BANK_TITLE_BAR_HEIGHT: i32 = 23;
+    const BANK_TABS_HEIGHT: i32 = 40;
+    const BANK_TAB_WIDTH: i32 = 40;
+    const BANK_INCINERATOR_WIDTH: i32 = 46;
+    /// Search, quantity and deposit buttons below the slots.
+    const BANK_BOTTOM_BAR_HEIGHT: i32 = 72;
+    /// The arrows at either end of the scrollbar.
+    const BANK_SCROLLBAR_ARROW_HEIGHT: i32 = 16;
+    const BANK_SCROLLBAR_WIDTH: i32 = 16;
+    /// The scrollbar starts a little below the top of the first slot and
+    /// ends above the buttons at the bottom of the bank.
+    const BANK_SCROLLBAR_TOP_OFFSET: i32 = 2;
+    const BANK_BELOW_SCROLLBAR_HEIGHT: i32 = 38;
     /// The bank expands to fill up more vertical space until the inside area is
     /// 788 pixels high.
     pub const BANK_MAX_HEIGHT: i32 = 788;
 
-    /// This gets the vertical distance from either the top of the screen or the
-    /// top of te chatbox to the first internal pixel of the bank (within the
+    /// The space the bank is fitted into: it is centered horizontally on it
+    /// and stretches vertically to fill it. Ends just above the chatbox.
+    fn bank_area(&self) -> (Position, DeltaPosition) {
+        if let Some((offset, dimensions)) = self.layout_mode.geometry().fixed_viewport {
+            return (self.top_left + offset, dimensions);
+        }
+        // Due to a difference in rounding centering the bank works better when
+        // we subtract 1 from the worldmap width.
+        let top_left = Position {
+            x: self.worldmap_top_left().x,
+            y: self.top_left.y,
+        };
+        let dimensions = DeltaPosition {
+            dx: self.worldmap_dimensions().dx - 1,
+            dy: self.chatbox_outer_top_left().y - self.top_left.y,
+        };
+        (top_left, dimensions)
+    }
+
+    /// This gets the vertical distance from either the top of the bank_area or
+    /// the bottom of it to the first internal pixel of the bank (within the
     /// border).
     fn bank_top_offset(&self) -> i32 {
         // There is always at least 2 pixels between the top of the screen and
@@ -270,7 +574,7 @@ impl Locations {
         // 1 pixel between the top of the chatbox and the bottom of the bank
         // border.
         let min_vertical_offset = 3 + 2 * Self::BANK_BORDER_WIDTH;
-        let total_vertical_space = self.chatbox_outer_top_left().y - self.top_left.y;
+        let total_vertical_space = self.bank_area().1.dy;
 
         let height = total_vertical_space - min_vertical_offset;
         if height > Self::BANK_MAX_HEIGHT {
@@ -279,61 +583,136 @@ impl Locations {
             2 + Self::BANK_BORDER_WIDTH
         }
     }
-    // The bank box extends from the top of the screen until the top of the
+    // The bank box extends from the top of the bank_area until the top of the
     // chatbox up until a max height. Then space is added above and below it
-    // symmetrically between the top of the screen and the top of the
-    // chatbox.
+    // symmetrically.
     pub fn bank_dimensions(&self) -> DeltaPosition {
         // The spacing above the bank is 1 pixel larger than the spacing below the bank.
         let vertical_spacing = 2 * self.bank_top_offset() - 1;
-        let total_vertical_space = self.chatbox_outer_top_left().y - self.top_left.y;
+        let total_vertical_space = self.bank_area().1.dy;
         DeltaPosition {
             dx: 476,
             dy: total_vertical_space - vertical_spacing,
         }
     }
     pub fn bank_top_left(&self) -> Position {
-        // Due to a difference in rounding centering the bank works better when
-        // we subtract 1 from the worldmap width.
-        let DeltaPosition { dx, dy } = self.worldmap_dimensions();
-        let Position { x, y: _y } =
-            Self::midpoint(self.worldmap_top_left(), DeltaPosition { dx: dx - 1, dy });
+        let (top_left, dimensions) = self.bank_area();
+        let Position { x, y: _y } = Self::midpoint(top_left, dimensions);
         Position {
             x: x - (self.bank_dimensions().dx as f32 / 2.0).round() as i32,
-            y: self.top_left.y + self.bank_top_offset(),
+            y: top_left.y + self.bank_top_offset(),
         }
     }
     pub fn bank_slot_dimensions(&self) -> DeltaPosition {
         DeltaPosition { dx: 48, dy: 36 }
     }
+    /// Top left of the first slot, when the bank is scrolled all the way up.
+    fn bank_slots_top_left(&self) -> Position {
+        let Position { x, y } = self.bank_top_left();
+        // There is a border of open space in the inventory where items are
+        // never put. So there is an offset from the top left corner of the
+        // inventory to where teh first slot is placed.
+        Position {
+            x: x + Self::BANK_INCINERATOR_WIDTH,
+            y: y + Self::BANK_TITLE_BAR_HEIGHT + Self::BANK_BORDER_WIDTH + Self::BANK_TABS_HEIGHT,
+        }
+    }
+    /// Number of rows of slots which are fully visible. The bank grows with
+    /// the screen so this can be much more than MIN_NUM_BANK_ROWS.
+    pub fn num_bank_rows(&self) -> i32 {
+        let slots_height = self.bank_dimensions().dy
+            - Self::BANK_TITLE_BAR_HEIGHT
+            - Self::BANK_BORDER_WIDTH
+            - Self::BANK_TABS_HEIGHT
+            - Self::BANK_BOTTOM_BAR_HEIGHT;
+        std::cmp::max(
+            slots_height / self.bank_slot_dimensions().dy,
+            Self::MIN_NUM_BANK_ROWS,
+        )
+    }
+    pub fn num_bank_slots(&self) -> i32 {
+        Self::NUM_BANK_COLUMNS * self.num_bank_rows()
+    }
+    /// How many rows the bank must be scrolled down from the top for 'slot'
+    /// to be visible. We scroll as little as possible, so the slot ends up
+    /// in the last visible row.
+    pub fn bank_rows_to_scroll(&self, slot: &BankSlot) -> i32 {
+        std::cmp::max(slot.row() - self.num_bank_rows() + 1, 0)
+    }
+    pub fn bank_tab_center(&self, tab: i32) -> Position {
+        assert!(tab < Self::NUM_BANK_TABS);
+        let Position { x, y } = self.bank_slots_top_left();
+        Position {
+            x: x + tab * Self::BANK_TAB_WIDTH + Self::BANK_TAB_WIDTH / 2,
+            y: y - Self::BANK_TABS_HEIGHT / 2,
+        }
+    }
+    /// The scrollbar runs down the right side of the slots, between the up
+    /// and down arrows. Top and bottom are the ends of the track the thumb
+    /// moves along.
+    pub fn bank_scrollbar_top(&self) -> Position {
+        let Position { x, y: _y } =
+            Self::to_top_right(self.bank_top_left(), self.bank_dimensions());
+        Position {
+            x: x - Self::BANK_SCROLLBAR_WIDTH / 2,
+            y: self.bank_slots_top_left().y
+                + Self::BANK_SCROLLBAR_TOP_OFFSET
+                + Self::BANK_SCROLLBAR_ARROW_HEIGHT,
+        }
+    }
+    pub fn bank_scrollbar_bottom(&self) -> Position {
+        let Position { x, y } = Self::to_bottom_right(self.bank_top_left(), self.bank_dimensions());
+        Position {
+            x: x - Self::BANK_SCROLLBAR_WIDTH / 2,
+            y: y - Self::BANK_BELOW_SCROLLBAR_HEIGHT - Self::BANK_SCROLLBAR_ARROW_HEIGHT,
+        }
+    }
+    /// Height of the part of the bank that scrolls, which is as tall as the
+    /// scrollbar including its arrows. Can be a fraction of a row more than
+    /// num_bank_rows.
+    pub fn bank_scroll_view_height(&self) -> i32 {
+        self.bank_scrollbar_bottom().y - self.bank_scrollbar_top().y
+            + 1
+            + 2 * Self::BANK_SCROLLBAR_ARROW_HEIGHT
+    }
     /// Only make use of the bank slot center. This is different than the
     /// inventory which uses top_left and dimensions so that we can analyze the
     /// contents. This is because I am a bit less confident my bank locations
     /// scale perfectly and the numbers for items messes with the analysis. I
     /// don't think it will be critical for me to analyze items and instead I
     /// can just give an explicit slot_index for an item.
+    ///
+    /// 'slot_index' counts from the first visible slot, so it is the same as
+    /// the BankSlot index while the bank is scrolled all the way up.
     pub fn bank_slot_center(&self, slot_index: i32) -> Position {
-        const BANK_TITLE_BAR_HEIGHT: i32 = 23;
-        const BANK_TABS_HEIGHT: i32 = 40;
-        const BANK_INCINERATOR_WIDTH: i32 = 46;
-
-        assert!(slot_index < Self::NUM_BANK_SLOTS);
+        assert!(slot_index < self.num_bank_slots());
         let row = slot_index / Self::NUM_BANK_COLUMNS;
         let col = slot_index - row * Self::NUM_BANK_COLUMNS;
 
-        let Position { x, y } = self.bank_top_left();
+        let Position { x: x0, y: y0 } = self.bank_slots_top_left();
         let DeltaPosition { dx, dy } = self.bank_slot_dimensions();
-
-        // There is a border of open space in the inventory where items are
-        // never put. So there is an offset from the top left corner of the
-        // inventory to where teh first slot is placed.
-        let x0 = x + BANK_INCINERATOR_WIDTH;
-        let y0 = y + BANK_TITLE_BAR_HEIGHT + Self::BANK_BORDER_WIDTH + BANK_TABS_HEIGHT;
         Position {
             x: x0 + col * dx + dx / 2,
             y: y0 + row * dy + dy / 2,
         }
     }
+    /// Items are drawn centered in their slot, the same as in the inventory.
+    /// This is where an inventory slot would start if it held the item in
+    /// this bank slot, so that we can use the inventory's checks to tell
+    /// which item it is.
+    pub fn bank_slot_check_top_left(&self, slot_index: i32) -> Position {
+        let Position { x, y } = self.bank_slot_center(slot_index);
+        let DeltaPosition { dx, dy } = self.inventory_slot_dimensions();
+        Position {
+            x: x - (dx as f32 / 2.0).round() as i32,
+            y: y - (dy as f32 / 2.0).round() as i32,
+        }
+    }
+    /// Center of 'slot' once the bank has been scrolled by
+    /// bank_rows_to_scroll.
+    pub fn scrolled_bank_slot_center(&self, slot: &BankSlot) -> Position {
+        self.bank_slot_center(slot.index - self.bank_rows_to_scroll(slot) * Self::NUM_BANK_COLUMNS)
+    }
     pub fn bank_deposit_inventory(&self) -> Position {
         let Position { x, y } = Self::to_bottom_right(self.bank_top_left(), self.bank_dimensions());
         Position {
@@ -364,29 +743,50 @@ impl Locations {
     }
 
     // Locations given in reference to the top right corner of the screen.
+    // These are all drawn around the minimap.
 
     /// Minimap Plus top left is used to create a box around the mini map and
     /// the icons around it such as health and compass etc.
     pub fn minimap_plus_top_left(&self) -> Position {
-        let Position { x, y } = self.top_right();
+        let Position { x, y } = self.minimap_top_right();
         Position { x: x - 210, y }
     }
     pub fn minimap_plus_dimensions(&self) -> DeltaPosition {
         DeltaPosition { dx: 211, dy: 173 }
     }
+    const MINIMAP_MIDDLE_OFFSET: DeltaPosition = DeltaPosition { dx: -82, dy: 84 };
     /// The minimap is circular, so we analyze it using polar coordinates, middle & radius.
     pub fn minimap_middle(&self) -> Position {
-        let Position { x, y } = self.top_right();
-        Position {
-            x: x - 82,
-            y: y + 84,
-        }
+        self.minimap_top_right() + Self::MINIMAP_MIDDLE_OFFSET
     }
     /// The minimap radius is to the beginning of the green & blue part of the
     /// worldmap icon. This is to avoid an issue of looking for a blue/green and
     /// accidentally clicking the worldmap.
     pub const MINIMAP_RADIUS: i32 = 72;
+    // Size of a game tile on the minimap at the default minimap zoom.
+    pub const MINIMAP_PIXELS_PER_TILE: i32 = 4;
     pub const MINIMAP_SMALL_RADIUS: i32 = Self::MINIMAP_RADIUS / 6;
+
+    /// Convert an offset in game tiles (dx east, dy north) to an offset on the
+    /// minimap. The minimap turns with the camera, so we need the camera yaw
+    /// (degrees clockwise from north).
+    pub fn tiles_to_minimap_offset(tiles: DeltaPosition, yaw_degrees: f32) -> DeltaPosition {
+        let north_up = DeltaPosition {
+            dx: tiles.dx * Self::MINIMAP_PIXELS_PER_TILE,
+            dy: -tiles.dy * Self::MINIMAP_PIXELS_PER_TILE,
+        };
+        // Turning the camera clockwise rotates the minimap counterclockwise.
+        north_up.rotate(degrees_to_radians(-yaw_degrees))
+    }
+    /// Inverse of tiles_to_minimap_offset, rounded to the nearest tile.
+    pub fn minimap_offset_to_tiles(offset: DeltaPosition, yaw_degrees: f32) -> DeltaPosition {
+        let north_up = offset.rotate(degrees_to_radians(yaw_degrees));
+        let pixels_per_tile = Self::MINIMAP_PIXELS_PER_TILE as f32;
+        DeltaPosition {
+            dx: (north_up.dx as f32 / pixels_per_tile).round() as i32,
+            dy: (-north_up.dy as f32 / pixels_per_tile).round() as i32,
+        }
+    }
     /// When we find something interesting in the minimap we often want to check
     /// the adjacent pixels to confirm this is not an abberant pixel. Should be
     /// about the same as the radius of an icon on the map.
@@ -394,24 +794,71 @@ impl Locations {
 
     pub const WROLDMAP_ICON_RADIUS: i32 = 12;
     pub fn worldmap_icon(&self) -> Position {
-        let Position { x, y } = self.top_right();
+        let Position { x, y } = self.minimap_top_right();
         Position {
             x: x - 19,
             y: y + 140,
         }
     }
     pub fn compass_icon(&self) -> Position {
-        let Position { x, y } = self.top_right();
+        let Position { x, y } = self.minimap_top_right();
         Position {
             x: x - 158,
             y: y + 23,
         }
     }
     pub fn run_icon(&self) -> Position {
-        let Position { x, y } = self.top_right();
-        Position {
-            x: x - 159,
-            y: y + 132,
+        self.orb_icon(Orb::Run)
+    }
+
+    /// The status orbs run down the left side of the minimap. Each has an
+    /// icon (e.g. the boot for run energy), and to the left of that a number
+    /// giving its current value.
+    pub fn orb_icon(&self, orb: Orb) -> Position {
+        let Position { x, y } = self.minimap_top_right();
+        match orb {
+            Orb::Hitpoints => Position {
+                x: x - 169,
+                y: y + 61,
+            },
+            Orb::Prayer => Position {
+                x: x - 166,
+                y: y + 98,
+            },
+            Orb::Run => Position {
+                x: x - 159,
+                y: y + 132,
+            },
+            Orb::SpecialAttack => Position {
+                x: x - 133,
+                y: y + 155,
+            },
+        }
+    }
+    /// Box that the orb's number is written in. The number is centered in
+    /// this box so its exact position depends on the number of digits. The
+    /// top of the box is the top of the digits, since we don't search
+    /// vertically for them.
+    pub const ORB_TEXT_DIMENSIONS: DeltaPosition = DeltaPosition { dx: 21, dy: 9 };
+    pub fn orb_text_top_left(&self, orb: Orb) -> Position {
+        let Position { x, y } = self.minimap_top_right();
+        match orb {
+            Orb::Hitpoints => Position {
+                x: x - 201,
+                y: y + 64,
+            },
+            Orb::Prayer => Position {
+                x: x - 201,
+                y: y + 98,
+            },
+            Orb::Run => Position {
+                x: x - 192,
+                y: y + 130,
+            },
+            Orb::SpecialAttack => Position {
+                x: x - 170,
+                y: y + 155,
+            },
         }
     }
 
@@ -432,24 +879,18 @@ impl Locations {
     pub const NUM_CHECKS_PER_INVENTORY_SLOT: usize = 12;
 
     pub fn inventory_outer_top_left(&self) -> Position {
-        let Position { x, y } = self.bottom_right();
-        Position {
-            x: x - 202,
-            y: y - 309,
-        }
+        let Position { x, y } = self.inventory_inner_top_left();
+        Position { x: x - 5, y: y - 5 }
     }
     pub fn inventory_outer_dimensions(&self) -> DeltaPosition {
         DeltaPosition { dx: 202, dy: 273 }
     }
     pub fn inventory_inner_top_left(&self) -> Position {
-        let Position { x, y } = self.bottom_right();
-        Position {
-            x: x - 197,
-            y: y - 304,
-        }
+        self.bottom_right() + self.layout_mode.geometry().inventory_inner_top_left
     }
+    pub const INVENTORY_INNER_DIMENSIONS: DeltaPosition = DeltaPosition { dx: 191, dy: 262 };
     pub fn inventory_inner_dimensions(&self) -> DeltaPosition {
-        DeltaPosition { dx: 191, dy: 262 }
+        Self::INVENTORY_INNER_DIMENSIONS
     }
     pub fn inventory_slot_dimensions(&self) -> DeltaPosition {
         DeltaPosition { dx: 42, dy: 36 }
@@ -478,21 +919,21 @@ impl Locations {
     }
 
     // At the bottom of the screen, to the right of the chat icons are icons for
-    // many different features with menus (inventory, combat, etc.). This
-    // assumes the screen is wide enough to show all of these icons in 1 row.
+    // many different features with menus (inventory, combat, etc.). Depending
+    // on the layout these are in 1 row, or split in 2 rows.
     fn leftmost_bottom_icon_top_left(&self) -> Position {
-        let Position { x, y } = self.bottom_right();
-        Position {
-            x: x - 428,
-            y: y - 35,
-        }
+        self.bottom_right() + self.layout_mode.geometry().bottom_icons_top_left
     }
     // icon_index is 0 indexed (starting at combat).
     fn bottom_icon_top_left(&self, icon_index: i32) -> Position {
+        let geometry = self.layout_mode.geometry();
+        let row = icon_index / geometry.bottom_icons_per_row;
+        let col = icon_index - row * geometry.bottom_icons_per_row;
+
         let Position { x, y } = self.leftmost_bottom_icon_top_left();
         Position {
-            x: x + icon_index * Self::BOTTOM_ICONS_DIMENSIONS.dx,
-            y,
+            x: x + col * Self::BOTTOM_ICONS_DIMENSIONS.dx,
+            y: y + row * geometry.bottom_icons_row_spacing,
         }
     }
     // An offset of (4, 4) seems to give a consistent pixel color identifying
@@ -505,6 +946,9 @@ impl Locations {
     pub fn inventory_icon_background(&self) -> Position {
         self.bottom_icon_background(3)
     }
+    pub fn inventory_icon_middle(&self) -> Position {
+        Self::midpoint(self.bottom_icon_top_left(3), Self::BOTTOM_ICONS_DIMENSIONS)
+    }
 
     // Create boxes used for searching for things in the open screen.
     pub fn open_screen_dimensions(&self) -> DeltaPosition {
@@ -516,9 +960,15 @@ impl Locations {
                 self.minimap_plus_top_left().x,
             ) - self.top_left.x
                 + 1,
-            // We assume that the chatbox is closed in which case the icons on the
-            // bottom extend up higher than the chat buttons.
-            dy: self.leftmost_bottom_icon_top_left().y - self.top_left.y + 1,
+            // In the modern layouts we assume that the chatbox is closed in
+            // which case the icons on the bottom extend up higher than the
+            // chat buttons. The classic layouts always draw the chatbox.
+            dy: if self.layout_mode.is_classic() {
+                self.chatbox_outer_top_left().y
+            } else {
+                self.leftmost_bottom_icon_top_left().y
+            } - self.top_left.y
+                + 1,
         }
     }
     pub fn open_screen_search_boxes(&self) -> Vec<(Position, DeltaPosition)> {
@@ -562,4 +1012,149 @@ impl Locations {
         }
         ret
     }
+
+    /// Rough measure of how far 'pos' on the open screen is from the player,
+    /// who stands in the middle of the screen. Only useful for comparing
+    /// positions against each other. Like open_screen_search_boxes this
+    /// assumes the camera looks down at an angle, so a step up the screen
+    /// covers about twice the ground of a step to the side or down.
+    pub fn estimated_distance_from_player(&self, pos: &Position) -> i32 {
+        let offset = *pos - self.mid_screen();
+        DeltaPosition {
+            dx: offset.dx / 2,
+            dy: if offset.dy < 0 {
+                offset.dy
+            } else {
+                offset.dy / 2
+            },
+        }
+        .distance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimated_distance_from_player() {
+        let locations =
+            Locations::new(Position { x: 0, y: 0 }, DeltaPosition { dx: 1000, dy: 800 });
+        let mid = locations.mid_screen();
+        let above = locations.estimated_distance_from_player(&Position {
+            x: mid.x,
+            y: mid.y - 60,
+        });
+        let below = locations.estimated_distance_from_player(&Position {
+            x: mid.x,
+            y: mid.y + 100,
+        });
+        let beside = locations.estimated_distance_from_player(&Position {
+            x: mid.x + 100,
+            y: mid.y,
+        });
+        assert_eq!(locations.estimated_distance_from_player(&mid), 0);
+        assert!(below < above, "{} {}", below, above);
+        assert_eq!(below, beside);
+    }
+
+    #[test]
+    fn layout_modes() {
+        let fixed = Locations::with_layout_mode(
+            Position { x: 0, y: 0 },
+            Locations::FIXED_CLASSIC_DIMENSIONS,
+            LayoutMode::FixedClassic,
+        );
+        assert_eq!(
+            fixed.inventory_slot_top_left(0),
+            Position { x: 563, y: 213 }
+        );
+        // The inventory icon is in the top row of icons, above the inventory.
+        assert!(fixed.inventory_icon_middle().y < fixed.inventory_inner_top_left().y);
+        // The open screen reaches down to the chatbox, past the top row of
+        // icons.
+        assert!(fixed.open_screen_dimensions().dy > fixed.inventory_icon_middle().y);
+        // The bank is centered on the 512x334 game view, not the whole client.
+        assert_eq!(fixed.bank_top_left(), Position { x: 22, y: 12 });
+        assert_eq!(fixed.bank_dimensions(), DeltaPosition { dx: 476, dy: 319 });
+        assert_eq!(fixed.chatbox_inner_top_left(), Position { x: 7, y: 345 });
+
+        let narrow = Locations::new(Position { x: 0, y: 0 }, DeltaPosition { dx: 800, dy: 600 });
+        assert_eq!(narrow.layout_mode, LayoutMode::ResizableModernTwoRows);
+        // The inventory icon is 4th in the top row, and the second row
+        // starts back at the left.
+        assert_eq!(
+            narrow.bottom_icon_top_left(3).y + 36,
+            narrow.bottom_icon_top_left(7).y
+        );
+        assert_eq!(
+            narrow.bottom_icon_top_left(0).x,
+            narrow.bottom_icon_top_left(7).x
+        );
+
+        let wide = Locations::new(Position { x: 0, y: 0 }, DeltaPosition { dx: 1000, dy: 600 });
+        assert_eq!(wide.layout_mode, LayoutMode::ResizableModernOneRow);
+        assert_eq!(
+            wide.inventory_inner_top_left().y - 36,
+            narrow.inventory_inner_top_left().y
+        );
+
+        for mode in LayoutMode::ALL.iter() {
+            assert_eq!(mode.name().parse::<LayoutMode>(), Ok(*mode));
+        }
+    }
+
+    #[test]
+    fn bank_rows_grow_with_screen() {
+        let small = Locations::new(Position { x: 0, y: 0 }, DeltaPosition { dx: 1000, dy: 503 });
+        assert_eq!(small.num_bank_rows(), Locations::MIN_NUM_BANK_ROWS);
+        let large = Locations::new(
+            Position { x: 0, y: 0 },
+            DeltaPosition { dx: 1000, dy: 1000 },
+        );
+        assert!(large.num_bank_rows() > Locations::MIN_NUM_BANK_ROWS);
+
+        // Slots which fit are clicked where they are, later ones end up in
+        // the last row.
+        let visible = BankSlot::from(Locations::MIN_NUM_BANK_SLOTS - 1);
+        assert_eq!(small.bank_rows_to_scroll(&visible), 0);
+        assert_eq!(
+            small.scrolled_bank_slot_center(&visible),
+            small.bank_slot_center(visible.index)
+        );
+        let hidden: BankSlot = "3,60".parse().unwrap();
+        assert_eq!(hidden, BankSlot { tab: 3, index: 60 });
+        assert_eq!("12".parse(), Ok(BankSlot::from(12)));
+        assert!("10,3".parse::<BankSlot>().is_err());
+        assert!("1,2,3".parse::<BankSlot>().is_err());
+        assert!("-1".parse::<BankSlot>().is_err());
+        assert_eq!(small.bank_rows_to_scroll(&hidden), 3);
+        assert_eq!(
+            small.scrolled_bank_slot_center(&hidden),
+            small.bank_slot_center(36)
+        );
+    }
+
+    #[test]
+    fn tiles_to_minimap_facing_north() {
+        let offset = Locations::tiles_to_minimap_offset(DeltaPosition { dx: 5, dy: 5 }, 0.0);
+        // North east is up and to the right on the minimap.
+        assert_eq!(offset, DeltaPosition { dx: 20, dy: -20 });
+    }
+
+    #[test]
+    fn tiles_to_minimap_facing_east() {
+        // Facing east, north is to the left on the minimap.
+        let offset = Locations::tiles_to_minimap_offset(DeltaPosition { dx: 0, dy: 3 }, 90.0);
+        assert_eq!(offset, DeltaPosition { dx: -12, dy: 0 });
+    }
+
+    #[test]
+    fn minimap_to_tiles_round_trip() {
+        for &yaw in [0.0, 45.0, 170.0, 300.0].iter() {
+            let tiles = DeltaPosition { dx: -4, dy: 7 };
+            let offset = Locations::tiles_to_minimap_offset(tiles, yaw);
+            assert_eq!(Locations::minimap_offset_to_tiles(offset, yaw), tiles);
+        }
+    }
 }
@@ -0,0 +1,223 @@
+/// Find the dots the minimap draws for other players, NPCs, and items on the
+/// ground.
+///
+/// Each dot is a small square of a single color with a black shadow. Map
+/// icons can contain the same colors, so we only count small clusters of
+/// matching pixels which have a shadow.
+use crate::types::*;
+use crate::{fuzzy_pixels, Frame, Locations};
+use util::*;
+
+/// Our own player is drawn as a white square in the middle of the minimap.
+const OWN_PLAYER_RADIUS: i32 = 3;
+
+/// Pixels within this distance (in x and y) of a dot are part of that dot.
+const DOT_MERGE_DISTANCE: i32 = 2;
+
+/// Number of matching pixels a dot can have. Fewer is likely noise, more is
+/// likely part of a map icon.
+const MIN_DOT_PIXELS: usize = 2;
+const MAX_DOT_PIXELS: usize = 12;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MinimapDotKind {
+    Player,
+    Npc,
+    Item,
+}
+
+impl MinimapDotKind {
+    fn fuzzy_pixel(&self) -> FuzzyPixel {
+        match self {
+            MinimapDotKind::Player => fuzzy_pixels::minimap_dot_player_white(),
+            MinimapDotKind::Npc => fuzzy_pixels::minimap_dot_npc_yellow(),
+            MinimapDotKind::Item => fuzzy_pixels::minimap_flag_red(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MinimapDot {
+    pub kind: MinimapDotKind,
+    /// Middle of the dot on screen.
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MinimapDots {
+    pub dots: Vec<MinimapDot>,
+}
+
+impl MinimapDots {
+    pub fn count(&self, kind: MinimapDotKind) -> usize {
+        self.dots.iter().filter(|dot| dot.kind == kind).count()
+    }
+
+    /// Number of dots of 'kind' within 'radius' pixels of 'pos'. Useful to
+    /// see how many other players are competing for a resource.
+    pub fn count_near(&self, kind: MinimapDotKind, pos: &Position, radius: i32) -> usize {
+        self.dots
+            .iter()
+            .filter(|dot| dot.kind == kind && (dot.position - *pos).distance() <= radius)
+            .count()
+    }
+
+    /// Is 'pos' part of any dot.
+    pub fn is_on_dot(&self, pos: &Position) -> bool {
+        self.dots.iter().any(|dot| {
+            (dot.position.x - pos.x).abs() <= DOT_MERGE_DISTANCE
+                && (dot.position.y - pos.y).abs() <= DOT_MERGE_DISTANCE
+        })
+    }
+}
+
+/// Pixels which matched a single kind of dot, grouped by proximity.
+struct Cluster {
+    kind: MinimapDotKind,
+    pixels: Vec<Position>,
+}
+
+impl Cluster {
+    fn is_adjacent(&self, pos: &Position) -> bool {
+        self.pixels.iter().any(|pixel| {
+            (pixel.x - pos.x).abs() <= DOT_MERGE_DISTANCE
+                && (pixel.y - pos.y).abs() <= DOT_MERGE_DISTANCE
+        })
+    }
+
+    /// Dots are drawn with a black shadow below and to the right.
+    fn has_shadow(&self, frame: &impl Frame) -> bool {
+        self.pixels.iter().any(|pixel| {
+            fuzzy_pixels::black()
+                .matches(&frame.get_pixel(&(*pixel + DeltaPosition { dx: 1, dy: 1 })))
+        })
+    }
+
+    fn middle(&self) -> Position {
+        let n = self.pixels.len() as i32;
+        Position {
+            x: self.pixels.iter().map(|pos| pos.x).sum::<i32>() / n,
+            y: self.pixels.iter().map(|pos| pos.y).sum::<i32>() / n,
+        }
+    }
+}
+
+/// Scan the minimap centered at 'minimap_middle' for dots.
+pub fn find_minimap_dots(frame: &impl Frame, minimap_middle: Position) -> MinimapDots {
+    let kinds = [
+        MinimapDotKind::Player,
+        MinimapDotKind::Npc,
+        MinimapDotKind::Item,
+    ];
+    let radius = Locations::MINIMAP_RADIUS;
+
+    let mut clusters = Vec::<Cluster>::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let offset = DeltaPosition { dx, dy };
+            let distance = offset.distance();
+            if distance > radius || distance <= OWN_PLAYER_RADIUS {
+                continue;
+            }
+            let pos = minimap_middle + offset;
+            let pixel = frame.get_pixel(&pos);
+            let kind = match kinds.iter().find(|kind| kind.fuzzy_pixel().matches(&pixel)) {
+                Some(kind) => *kind,
+                None => continue,
+            };
+            match clusters
+                .iter_mut()
+                .find(|cluster| cluster.kind == kind && cluster.is_adjacent(&pos))
+            {
+                Some(cluster) => cluster.pixels.push(pos),
+                None => clusters.push(Cluster {
+                    kind,
+                    pixels: vec![pos],
+                }),
+            }
+        }
+    }
+
+    MinimapDots {
+        dots: clusters
+            .iter()
+            .filter(|cluster| {
+                (MIN_DOT_PIXELS..=MAX_DOT_PIXELS).contains(&cluster.pixels.len())
+                    && cluster.has_shadow(frame)
+            })
+            .map(|cluster| MinimapDot {
+                kind: cluster.kind,
+                position: cluster.middle(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OwnedFrame;
+
+    const MINIMAP_MIDDLE: Position = Position { x: 80, y: 80 };
+
+    fn draw_square(frame: &mut OwnedFrame, middle: Position, half_size: i32, pixel: Pixel) {
+        for dy in -half_size..=half_size {
+            for dx in -half_size..=half_size {
+                frame.recolor_pixel(&(middle + DeltaPosition { dx, dy }), &pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn find_dots() {
+        let (width, height) = (161, 161);
+        let mut frame = OwnedFrame::blank(width, height);
+        let white = Pixel {
+            blue: 255,
+            green: 255,
+            red: 255,
+        };
+        let yellow = Pixel {
+            blue: 0,
+            green: 255,
+            red: 255,
+        };
+        let red = Pixel {
+            blue: 0,
+            green: 0,
+            red: 255,
+        };
+
+        // Our own player, ignored.
+        draw_square(&mut frame, MINIMAP_MIDDLE, 1, white);
+        draw_square(&mut frame, Position { x: 90, y: 80 }, 1, white);
+        draw_square(&mut frame, Position { x: 60, y: 50 }, 1, yellow);
+        draw_square(&mut frame, Position { x: 66, y: 50 }, 1, yellow);
+        draw_square(&mut frame, Position { x: 100, y: 110 }, 1, red);
+        // Too big to be a dot.
+        draw_square(&mut frame, Position { x: 80, y: 120 }, 4, white);
+        // Part of an icon, with no shadow.
+        let orange = Pixel {
+            blue: 40,
+            green: 170,
+            red: 240,
+        };
+        draw_square(&mut frame, Position { x: 40, y: 80 }, 3, orange);
+        draw_square(&mut frame, Position { x: 40, y: 80 }, 1, yellow);
+
+        let dots = find_minimap_dots(&frame, MINIMAP_MIDDLE);
+        assert_eq!(dots.count(MinimapDotKind::Player), 1);
+        assert_eq!(dots.count(MinimapDotKind::Npc), 2);
+        assert_eq!(dots.count(MinimapDotKind::Item), 1);
+        assert!(dots.dots.contains(&MinimapDot {
+            kind: MinimapDotKind::Player,
+            position: Position { x: 90, y: 80 },
+        }));
+        assert_eq!(
+            dots.count_near(MinimapDotKind::Npc, &Position { x: 60, y: 50 }, 3),
+            1
+        );
+        assert!(dots.is_on_dot(&Position { x: 101, y: 111 }));
+        assert!(!dots.is_on_dot(&Position { x: 110, y: 110 }));
+    }
+}
@@ -0,0 +1,344 @@
+/// Measure how the player moves by watching the minimap scroll.
+///
+/// The player's dot always stays in the middle of the minimap, so when the
+/// player moves the map under it shifts the opposite way. We find that shift
+/// between two captures of the minimap using phase correlation.
+///
+/// This assumes the camera doesn't turn between captures since that rotates
+/// the minimap instead of shifting it.
+use crate::{Frame, Locations};
+use util::*;
+
+/// Width and height of the square cropped out of the middle of the minimap.
+/// Must be a power of 2 for the FFT, and small enough that the crop stays
+/// within the minimap circle.
+pub const MOTION_CROP_SIZE: usize = 64;
+
+/// Ignore the middle of the minimap where the player's dot is drawn, since it
+/// never moves.
+const PLAYER_DOT_RADIUS: i32 = 3;
+
+/// Below this the minimap didn't have enough detail to measure the shift, e.g.
+/// when standing in a large open field.
+pub const MIN_MOTION_CONFIDENCE: f32 = 0.15;
+
+/// Brightness of a square from the middle of the minimap, ready to be compared
+/// against another crop.
+#[derive(Clone)]
+pub struct MinimapCrop {
+    values: Vec<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Motion {
+    /// How far the player moved, in minimap pixels. The minimap itself
+    /// shifted the opposite way.
+    pub player_offset: DeltaPosition,
+
+    /// Height of the phase correlation peak, [0, 1]. Values near 1 mean the
+    /// two crops were the same image shifted.
+    pub confidence: f32,
+}
+
+impl Motion {
+    pub fn is_confident(&self) -> bool {
+        self.confidence >= MIN_MOTION_CONFIDENCE
+    }
+
+    pub fn distance_tiles(&self) -> f32 {
+        let DeltaPosition { dx, dy } = self.player_offset;
+        ((dx * dx + dy * dy) as f32).sqrt() / Locations::MINIMAP_PIXELS_PER_TILE as f32
+    }
+
+    /// Offset moved in game tiles (dx east, dy north).
+    pub fn tiles(&self, yaw_degrees: f32) -> DeltaPosition {
+        Locations::minimap_offset_to_tiles(self.player_offset, yaw_degrees)
+    }
+}
+
+impl MinimapCrop {
+    pub fn capture(frame: &impl Frame, minimap_middle: Position) -> MinimapCrop {
+        let size = MOTION_CROP_SIZE as i32;
+        let top_left = minimap_middle
+            - DeltaPosition {
+                dx: size / 2,
+                dy: size / 2,
+            };
+        let mut values = Vec::with_capacity(MOTION_CROP_SIZE * MOTION_CROP_SIZE);
+        for y in 0..size {
+            for x in 0..size {
+                let pixel = frame.get_pixel(&(top_left + DeltaPosition { dx: x, dy: y }));
+                values.push((pixel.red as f32 + pixel.green as f32 + pixel.blue as f32) / 3.0);
+            }
+        }
+
+        // Replace the player's dot with the average so it doesn't pull the
+        // estimate towards not moving.
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        for dy in -PLAYER_DOT_RADIUS..=PLAYER_DOT_RADIUS {
+            for dx in -PLAYER_DOT_RADIUS..=PLAYER_DOT_RADIUS {
+                let (x, y) = ((size / 2 + dx) as usize, (size / 2 + dy) as usize);
+                values[y * MOTION_CROP_SIZE + x] = mean;
+            }
+        }
+
+        // Remove the mean and fade out the edges. Otherwise the edges of the
+        // crop look like a strong feature that never moves.
+        let window = |i: usize| {
+            0.5 - 0.5
+                * (2.0 * std::f32::consts::PI * i as f32 / (MOTION_CROP_SIZE - 1) as f32).cos()
+        };
+        for y in 0..MOTION_CROP_SIZE {
+            for x in 0..MOTION_CROP_SIZE {
+                let value = &mut values[y * MOTION_CROP_SIZE + x];
+                *value = (*value - mean) * window(x) * window(y);
+            }
+        }
+        MinimapCrop { values }
+    }
+}
+
+/// In place radix 2 FFT. 'inverse' does not scale the result.
+fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = re.len();
+
+    // Bit reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * std::f32::consts::PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f32).cos(), (angle * k as f32).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// 2D FFT of a MOTION_CROP_SIZE square, rows then columns.
+fn fft_2d(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = MOTION_CROP_SIZE;
+    for row in 0..n {
+        let range = row * n..(row + 1) * n;
+        fft(&mut re[range.clone()], &mut im[range], inverse);
+    }
+    let mut col_re = vec![0.0; n];
+    let mut col_im = vec![0.0; n];
+    for col in 0..n {
+        for row in 0..n {
+            col_re[row] = re[row * n + col];
+            col_im[row] = im[row * n + col];
+        }
+        fft(&mut col_re, &mut col_im, inverse);
+        for row in 0..n {
+            re[row * n + col] = col_re[row];
+            im[row * n + col] = col_im[row];
+        }
+    }
+}
+
+/// Estimate how the player moved between 'before' and 'after'.
+pub fn estimate_motion(before: &MinimapCrop, after: &MinimapCrop) -> Motion {
+    let n = MOTION_CROP_SIZE;
+    let (mut before_re, mut before_im) = (before.values.clone(), vec![0.0; n * n]);
+    let (mut after_re, mut after_im) = (after.values.clone(), vec![0.0; n * n]);
+    fft_2d(&mut before_re, &mut before_im, false);
+    fft_2d(&mut after_re, &mut after_im, false);
+
+    // Normalized cross power spectrum, after * conj(before). Its inverse is a
+    // peak at the shift from 'before' to 'after'.
+    let mut re = vec![0.0; n * n];
+    let mut im = vec![0.0; n * n];
+    for i in 0..n * n {
+        let cross_re = after_re[i] * before_re[i] + after_im[i] * before_im[i];
+        let cross_im = after_im[i] * before_re[i] - after_re[i] * before_im[i];
+        let magnitude = (cross_re * cross_re + cross_im * cross_im).sqrt();
+        if magnitude > 1e-3 {
+            re[i] = cross_re / magnitude;
+            im[i] = cross_im / magnitude;
+        }
+    }
+    fft_2d(&mut re, &mut im, true);
+
+    let (peak_index, peak) = re
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        .unwrap();
+
+    // Shifts past the middle wrap around to negative.
+    let unwrap = |i: usize| {
+        if i > n / 2 {
+            i as i32 - n as i32
+        } else {
+            i as i32
+        }
+    };
+    let map_shift = DeltaPosition {
+        dx: unwrap(peak_index % n),
+        dy: unwrap(peak_index / n),
+    };
+    Motion {
+        player_offset: DeltaPosition {
+            dx: -map_shift.dx,
+            dy: -map_shift.dy,
+        },
+        confidence: (peak / (n * n) as f32).clamp(0.0, 1.0),
+    }
+}
+
+/// Keeps track of how far the player has moved across a series of frames.
+pub struct MinimapOdometer {
+    previous: Option<MinimapCrop>,
+
+    /// Total distance moved since the odometer was created or reset.
+    pub distance_tiles: f32,
+
+    /// Where the player is relative to where they started, in minimap pixels.
+    pub player_offset: DeltaPosition,
+}
+
+impl MinimapOdometer {
+    pub fn new() -> MinimapOdometer {
+        MinimapOdometer {
+            previous: None,
+            distance_tiles: 0.0,
+            player_offset: DeltaPosition { dx: 0, dy: 0 },
+        }
+    }
+
+    /// Measure from 'start' instead of from the first frame passed to update.
+    pub fn starting_from(start: MinimapCrop) -> MinimapOdometer {
+        MinimapOdometer {
+            previous: Some(start),
+            ..MinimapOdometer::new()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.distance_tiles = 0.0;
+        self.player_offset = DeltaPosition { dx: 0, dy: 0 };
+    }
+
+    /// Compare 'frame' against the last frame passed in. Returns None on the
+    /// first call since there is nothing to compare against.
+    pub fn update(&mut self, frame: &impl Frame, minimap_middle: Position) -> Option<Motion> {
+        let crop = MinimapCrop::capture(frame, minimap_middle);
+        let motion = self
+            .previous
+            .as_ref()
+            .map(|previous| estimate_motion(previous, &crop));
+        self.previous = Some(crop);
+
+        if let Some(motion) = motion {
+            if motion.is_confident() {
+                self.distance_tiles += motion.distance_tiles();
+                self.player_offset = self.player_offset + motion.player_offset;
+            }
+        }
+        motion
+    }
+}
+
+impl Default for MinimapOdometer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::noise;
+    use crate::types::*;
+    use crate::OwnedFrame;
+
+    const MINIMAP_MIDDLE: Position = Position { x: 80, y: 80 };
+
+    // Draw blocky terrain as seen with the player at 'player' (in pixels).
+    fn draw_minimap(player: DeltaPosition) -> OwnedFrame {
+        let (width, height) = (161, 161);
+        let mut frame = OwnedFrame::blank(width, height);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let (world_x, world_y) = (x + player.dx + 1000, y + player.dy + 1000);
+                let value = noise(world_x / 5, world_y / 5);
+                frame.recolor_pixel(
+                    &Position { x, y },
+                    &Pixel {
+                        blue: value,
+                        green: value,
+                        red: value,
+                    },
+                );
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn standing_still() {
+        let still = DeltaPosition { dx: 0, dy: 0 };
+        let before = MinimapCrop::capture(&draw_minimap(still), MINIMAP_MIDDLE);
+        let after = MinimapCrop::capture(&draw_minimap(still), MINIMAP_MIDDLE);
+        let motion = estimate_motion(&before, &after);
+        assert_eq!(motion.player_offset, still);
+        assert!(motion.is_confident(), "{:?}", motion);
+    }
+
+    #[test]
+    fn odometer_follows_player() {
+        let mut odometer = MinimapOdometer::new();
+        assert_eq!(
+            odometer.update(
+                &draw_minimap(DeltaPosition { dx: 0, dy: 0 }),
+                MINIMAP_MIDDLE
+            ),
+            None
+        );
+
+        // Walk 2 tiles north east, then 2 tiles north.
+        let motion = odometer
+            .update(
+                &draw_minimap(DeltaPosition { dx: 8, dy: -8 }),
+                MINIMAP_MIDDLE,
+            )
+            .unwrap();
+        assert_eq!(motion.player_offset, DeltaPosition { dx: 8, dy: -8 });
+        assert_eq!(motion.tiles(0.0), DeltaPosition { dx: 2, dy: 2 });
+        assert!(motion.is_confident(), "{:?}", motion);
+
+        let motion = odometer
+            .update(
+                &draw_minimap(DeltaPosition { dx: 8, dy: -16 }),
+                MINIMAP_MIDDLE,
+            )
+            .unwrap();
+        assert_eq!(motion.tiles(0.0), DeltaPosition { dx: 0, dy: 2 });
+        assert!((odometer.distance_tiles - 4.83).abs() < 0.01);
+        assert_eq!(odometer.player_offset, DeltaPosition { dx: 8, dy: -16 });
+    }
+}
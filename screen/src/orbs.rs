@@ -0,0 +1,451 @@
+/// The status orbs around the minimap (hitpoints, prayer, run energy and
+/// special attack) each display their current value as a small number to the
+/// left of the icon. We read these numbers in a similar way to action text, by
+/// checking a handful of pixels per digit.
+///
+/// The numbers change color based on how full the orb is (green when full,
+/// moving through yellow and orange to red when nearly empty) so we accept any
+/// of those colors for a digit's pixels.
+use crate::fuzzy_pixels::{orb_text_green, orb_text_orange, orb_text_red, orb_text_yellow};
+use crate::types::*;
+use crate::Frame;
+use util::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Orb {
+    Hitpoints,
+    Prayer,
+    Run,
+    SpecialAttack,
+}
+
+/// A single digit drawn in the orb font.
+pub struct Digit {
+    /// How wide is the digit, including the space before the next digit.
+    pub width: i32,
+
+    /// Points which are expected to be drawn in the orb text color. Given as
+    /// the offset from the top left of the digit's box.
+    pub checkpoints: Vec<DeltaPosition>,
+
+    /// Points which are expected to not be drawn. Several digits are subsets
+    /// of each other (e.g. 3 and 8) so we need these to tell them apart.
+    pub empty_checkpoints: Vec<DeltaPosition>,
+
+    pub value: i32,
+}
+
+/// The orbs never show more than 3 digits (hitpoints and prayer top out at 99,
+/// run energy and special attack at 100).
+pub const MAX_ORB_DIGITS: i32 = 3;
+
+pub mod digits {
+    use super::Digit;
+    use util::*;
+
+    pub fn zero() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 1, dy: 0 },
+                DeltaPosition { dx: 3, dy: 0 },
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 4, dy: 2 },
+                DeltaPosition { dx: 0, dy: 5 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 1, dy: 7 },
+                DeltaPosition { dx: 3, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 2, dy: 3 },
+                DeltaPosition { dx: 2, dy: 4 },
+            ],
+            value: 0,
+        }
+    }
+
+    pub fn one() -> Digit {
+        Digit {
+            width: 4,
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 1 },
+                DeltaPosition { dx: 1, dy: 0 },
+                DeltaPosition { dx: 1, dy: 3 },
+                DeltaPosition { dx: 1, dy: 5 },
+                DeltaPosition { dx: 1, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 4 },
+                DeltaPosition { dx: 2, dy: 4 },
+            ],
+            value: 1,
+        }
+    }
+
+    pub fn two() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 1 },
+                DeltaPosition { dx: 2, dy: 0 },
+                DeltaPosition { dx: 4, dy: 1 },
+                DeltaPosition { dx: 3, dy: 3 },
+                DeltaPosition { dx: 1, dy: 5 },
+                DeltaPosition { dx: 0, dy: 7 },
+                DeltaPosition { dx: 2, dy: 7 },
+                DeltaPosition { dx: 4, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 4 },
+                DeltaPosition { dx: 4, dy: 5 },
+            ],
+            value: 2,
+        }
+    }
+
+    pub fn three() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 0 },
+                DeltaPosition { dx: 2, dy: 0 },
+                DeltaPosition { dx: 4, dy: 1 },
+                DeltaPosition { dx: 2, dy: 3 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 0, dy: 7 },
+                DeltaPosition { dx: 2, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 0, dy: 5 },
+            ],
+            value: 3,
+        }
+    }
+
+    pub fn four() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 3, dy: 0 },
+                DeltaPosition { dx: 1, dy: 3 },
+                DeltaPosition { dx: 0, dy: 5 },
+                DeltaPosition { dx: 2, dy: 5 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 3, dy: 3 },
+                DeltaPosition { dx: 3, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 0 },
+                DeltaPosition { dx: 1, dy: 7 },
+            ],
+            value: 4,
+        }
+    }
+
+    pub fn five() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 0 },
+                DeltaPosition { dx: 2, dy: 0 },
+                DeltaPosition { dx: 4, dy: 0 },
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 2, dy: 3 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 0, dy: 7 },
+                DeltaPosition { dx: 2, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 4, dy: 2 },
+                DeltaPosition { dx: 0, dy: 5 },
+            ],
+            value: 5,
+        }
+    }
+
+    pub fn six() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 2, dy: 0 },
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 0, dy: 5 },
+                DeltaPosition { dx: 2, dy: 3 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 1, dy: 7 },
+                DeltaPosition { dx: 3, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 4, dy: 1 },
+                DeltaPosition { dx: 2, dy: 5 },
+            ],
+            value: 6,
+        }
+    }
+
+    pub fn seven() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 0 },
+                DeltaPosition { dx: 2, dy: 0 },
+                DeltaPosition { dx: 4, dy: 0 },
+                DeltaPosition { dx: 3, dy: 3 },
+                DeltaPosition { dx: 2, dy: 5 },
+                DeltaPosition { dx: 2, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 3 },
+                DeltaPosition { dx: 0, dy: 7 },
+            ],
+            value: 7,
+        }
+    }
+
+    pub fn eight() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 1, dy: 0 },
+                DeltaPosition { dx: 3, dy: 0 },
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 4, dy: 2 },
+                DeltaPosition { dx: 2, dy: 3 },
+                DeltaPosition { dx: 0, dy: 5 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 1, dy: 7 },
+                DeltaPosition { dx: 3, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 2, dy: 1 },
+                DeltaPosition { dx: 2, dy: 6 },
+            ],
+            value: 8,
+        }
+    }
+
+    pub fn nine() -> Digit {
+        Digit {
+            width: 6,
+            checkpoints: vec![
+                DeltaPosition { dx: 1, dy: 0 },
+                DeltaPosition { dx: 3, dy: 0 },
+                DeltaPosition { dx: 0, dy: 2 },
+                DeltaPosition { dx: 4, dy: 2 },
+                DeltaPosition { dx: 2, dy: 4 },
+                DeltaPosition { dx: 4, dy: 5 },
+                DeltaPosition { dx: 2, dy: 7 },
+            ],
+            empty_checkpoints: vec![
+                DeltaPosition { dx: 0, dy: 6 },
+                DeltaPosition { dx: 2, dy: 2 },
+            ],
+            value: 9,
+        }
+    }
+
+    pub fn all() -> Vec<Digit> {
+        vec![
+            zero(),
+            one(),
+            two(),
+            three(),
+            four(),
+            five(),
+            six(),
+            seven(),
+            eight(),
+            nine(),
+        ]
+    }
+}
+
+/// The orb text is drawn in a single color which depends on how full the orb
+/// is.
+pub fn is_orb_text(pixel: &Pixel) -> bool {
+    orb_text_green().matches(pixel)
+        || orb_text_yellow().matches(pixel)
+        || orb_text_orange().matches(pixel)
+        || orb_text_red().matches(pixel)
+}
+
+/// Check if 'digit' is drawn with its top left corner at 'top_left'. Returns
+/// the number of checkpoints matched, or None if the digit doesn't match.
+fn match_digit(frame: &impl Frame, digit: &Digit, top_left: Position) -> Option<usize> {
+    for DeltaPosition { dx, dy } in digit.checkpoints.iter() {
+        let pos = Position {
+            x: top_left.x + dx,
+            y: top_left.y + dy,
+        };
+        if !is_orb_text(&frame.get_pixel(&pos)) {
+            return None;
+        }
+    }
+    for DeltaPosition { dx, dy } in digit.empty_checkpoints.iter() {
+        let pos = Position {
+            x: top_left.x + dx,
+            y: top_left.y + dy,
+        };
+        if is_orb_text(&frame.get_pixel(&pos)) {
+            return None;
+        }
+    }
+    Some(digit.checkpoints.len())
+}
+
+/// Read the number written in the box starting at 'text_top_left'.
+///
+/// The number is not always in the same place within the box (it is centered
+/// so 1 digit numbers are further right than 3 digit numbers). We therefore
+/// try each digit at every column in the box, and then keep the most specific
+/// matches which don't overlap each other.
+///
+/// Returns None if no digits were found, for instance if the orb is hidden.
+pub fn read_orb_value(
+    frame: &impl Frame,
+    text_top_left: Position,
+    text_dimensions: DeltaPosition,
+) -> Option<i32> {
+    let all_digits = digits::all();
+
+    // (x, digit_index, num_checkpoints_matched)
+    let mut candidates = Vec::<(i32, usize, usize)>::new();
+    for dx in 0..text_dimensions.dx {
+        let top_left = Position {
+            x: text_top_left.x + dx,
+            y: text_top_left.y,
+        };
+        for (i, digit) in all_digits.iter().enumerate() {
+            if let Some(score) = match_digit(frame, digit, top_left) {
+                candidates.push((top_left.x, i, score));
+            }
+        }
+    }
+
+    // Greedily take the most specific matches first. A digit with fewer
+    // checkpoints can match on part of a larger digit (a 1 on the side of a
+    // 4), so prefer the digit which explains more of the pixels.
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.2));
+    let mut chosen = Vec::<(i32, usize)>::new();
+    for (x, i, _) in candidates {
+        let width = all_digits[i].width;
+        let overlaps = chosen.iter().any(|(other_x, other_i)| {
+            let other_width = all_digits[*other_i].width;
+            x < other_x + other_width - 1 && *other_x < x + width - 1
+        });
+        if !overlaps {
+            chosen.push((x, i));
+        }
+        if chosen.len() as i32 == MAX_ORB_DIGITS {
+            break;
+        }
+    }
+    if chosen.is_empty() {
+        return None;
+    }
+
+    chosen.sort_by_key(|(x, _)| *x);
+    Some(
+        chosen
+            .iter()
+            .fold(0, |value, (_, i)| 10 * value + all_digits[*i].value),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Locations, OwnedFrame};
+
+    const TEXT_TOP_LEFT: Position = Position { x: 2, y: 2 };
+    const TEXT_DIMENSIONS: DeltaPosition = Locations::ORB_TEXT_DIMENSIONS;
+
+    // Draw 'value' into a blank frame in the orb font, offset 'x_offset' into
+    // the text box.
+    fn draw_value(value: &str, x_offset: i32) -> OwnedFrame {
+        let (width, height) = (30, 14);
+        let mut frame = OwnedFrame::blank(width, height);
+
+        let all_digits = digits::all();
+        let mut x = TEXT_TOP_LEFT.x + x_offset;
+        for c in value.chars() {
+            let digit = &all_digits[c.to_digit(10).unwrap() as usize];
+            for DeltaPosition { dx, dy } in digit.checkpoints.iter() {
+                frame.recolor_pixel(
+                    &Position {
+                        x: x + dx,
+                        y: TEXT_TOP_LEFT.y + dy,
+                    },
+                    &Pixel {
+                        blue: 0,
+                        green: 255,
+                        red: 0,
+                    },
+                );
+            }
+            x += digit.width;
+        }
+        frame
+    }
+
+    #[test]
+    fn read_each_digit() {
+        for value in 0..10 {
+            let frame = draw_value(&value.to_string(), 3);
+            assert_eq!(
+                read_orb_value(&frame, TEXT_TOP_LEFT, TEXT_DIMENSIONS),
+                Some(value)
+            );
+        }
+    }
+
+    #[test]
+    fn read_multiple_digits() {
+        for value in &["10", "47", "99", "100", "83", "256"] {
+            let frame = draw_value(value, 1);
+            assert_eq!(
+                read_orb_value(&frame, TEXT_TOP_LEFT, TEXT_DIMENSIONS),
+                Some(value.parse::<i32>().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn empty_box() {
+        let frame = draw_value("", 0);
+        assert_eq!(read_orb_value(&frame, TEXT_TOP_LEFT, TEXT_DIMENSIONS), None);
+    }
+
+    #[test]
+    fn read_screenshot() {
+        // Saved from the client with hitpoints 22, prayer 1, and full run
+        // energy and special attack. The red boxes in the open screen
+        // screenshot put the game screen at (12, 11) to (963, 617).
+        let frame = OwnedFrame::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/readme/screenshot_inventories.png"
+        ));
+        let locations = Locations::new(
+            Position { x: 12, y: 11 },
+            DeltaPosition { dx: 952, dy: 607 },
+        );
+        for (orb, value) in &[
+            (Orb::Hitpoints, 22),
+            (Orb::Prayer, 1),
+            (Orb::Run, 100),
+            (Orb::SpecialAttack, 100),
+        ] {
+            assert_eq!(
+                read_orb_value(
+                    &frame,
+                    locations.orb_text_top_left(*orb),
+                    Locations::ORB_TEXT_DIMENSIONS
+                ),
+                Some(*value),
+                "{:?}",
+                orb
+            );
+        }
+    }
+}
@@ -0,0 +1,22 @@
+/// Helpers for drawing frames in tests.
+use crate::{OwnedFrame, Pixel};
+use util::*;
+
+/// Color the box at 'top_left' with 'dimensions' in 'pixel'.
+pub fn fill(frame: &mut OwnedFrame, top_left: Position, dimensions: DeltaPosition, pixel: Pixel) {
+    for dy in 0..dimensions.dy {
+        for dx in 0..dimensions.dx {
+            frame.recolor_pixel(&(top_left + DeltaPosition { dx, dy }), &pixel);
+        }
+    }
+}
+
+/// Pseudo random brightness for (x, y). Used to draw terrain with enough
+/// detail to be matched, divide x and y to get blocks instead of pixels.
+pub fn noise(x: i32, y: i32) -> u8 {
+    let mut hash = (x as u32).wrapping_mul(73856093) ^ (y as u32).wrapping_mul(19349663);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1e995);
+    hash ^= hash >> 15;
+    (hash % 256) as u8
+}
@@ -42,6 +42,150 @@ impl FuzzyPixel {
     }
 }
 
+/// Anything that can decide whether a pixel is the color we are looking for.
+/// FuzzyPixel is a box in BGR space, which breaks down when the game gets
+/// lighter or darker, e.g. with the time of day or the brightness setting.
+/// HsvPixel and LabPixel are more forgiving of that.
+pub trait PixelMatcher {
+    fn matches(&self, pixel: &Pixel) -> bool;
+}
+
+impl<T: PixelMatcher + ?Sized> PixelMatcher for &T {
+    fn matches(&self, pixel: &Pixel) -> bool {
+        (**self).matches(pixel)
+    }
+}
+
+/// None matches any pixel. Used to skip checks which can't be relied on.
+impl<T: PixelMatcher> PixelMatcher for Option<T> {
+    fn matches(&self, pixel: &Pixel) -> bool {
+        match self {
+            Some(matcher) => matcher.matches(pixel),
+            None => true,
+        }
+    }
+}
+
+impl PixelMatcher for FuzzyPixel {
+    fn matches(&self, pixel: &Pixel) -> bool {
+        FuzzyPixel::matches(self, pixel)
+    }
+}
+
+/// Bounds on hue, saturation, and value. Hue is in degrees [0, 360) and wraps
+/// around, so hue_min > hue_max matches the hues through 0 (reds).
+/// Saturation and value are [0, 255]. Both min and max are included in
+/// matching.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct HsvPixel {
+    pub hue_min: u16,
+    pub hue_max: u16,
+    pub saturation_min: u8,
+    pub saturation_max: u8,
+    pub value_min: u8,
+    pub value_max: u8,
+}
+
+impl PixelMatcher for HsvPixel {
+    fn matches(&self, pixel: &Pixel) -> bool {
+        let (hue, saturation, value) = pixel.to_hsv();
+        let hue_matches = if self.hue_min <= self.hue_max {
+            hue >= self.hue_min && hue <= self.hue_max
+        } else {
+            hue >= self.hue_min || hue <= self.hue_max
+        };
+        hue_matches
+            && saturation >= self.saturation_min
+            && saturation <= self.saturation_max
+            && value >= self.value_min
+            && value <= self.value_max
+    }
+}
+
+/// Matches pixels within 'max_distance' of a reference color in CIELAB space,
+/// where distance roughly follows how different two colors look. A distance
+/// of about 2 is barely noticeable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabPixel {
+    pub lightness: f32,
+    pub a: f32,
+    pub b: f32,
+    pub max_distance: f32,
+}
+
+impl LabPixel {
+    pub fn around(pixel: &Pixel, max_distance: f32) -> LabPixel {
+        let (lightness, a, b) = pixel.to_lab();
+        LabPixel {
+            lightness,
+            a,
+            b,
+            max_distance,
+        }
+    }
+}
+
+impl PixelMatcher for LabPixel {
+    fn matches(&self, pixel: &Pixel) -> bool {
+        let (lightness, a, b) = pixel.to_lab();
+        let distance_squared =
+            (lightness - self.lightness).powi(2) + (a - self.a).powi(2) + (b - self.b).powi(2);
+        distance_squared <= self.max_distance * self.max_distance
+    }
+}
+
+impl Pixel {
+    /// Hue in degrees [0, 360), saturation and value [0, 255].
+    pub fn to_hsv(&self) -> (u16, u8, u8) {
+        let max = self.red.max(self.green).max(self.blue);
+        let min = self.red.min(self.green).min(self.blue);
+        let chroma = (max - min) as i32;
+        if chroma == 0 {
+            return (0, 0, max);
+        }
+
+        let (red, green, blue) = (self.red as i32, self.green as i32, self.blue as i32);
+        let hue = if max == self.red {
+            60 * (green - blue) / chroma
+        } else if max == self.green {
+            120 + 60 * (blue - red) / chroma
+        } else {
+            240 + 60 * (red - green) / chroma
+        };
+        (
+            hue.rem_euclid(360) as u16,
+            (255 * chroma / max as i32) as u8,
+            max,
+        )
+    }
+
+    /// CIELAB (lightness, a, b) assuming sRGB with a D65 white point.
+    pub fn to_lab(&self) -> (f32, f32, f32) {
+        let linear = |channel: u8| {
+            let c = channel as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let (r, g, b) = (linear(self.red), linear(self.green), linear(self.blue));
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+        let f = |t: f32| {
+            if t > 0.008856 {
+                t.cbrt()
+            } else {
+                7.787 * t + 16.0 / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+}
+
 impl FromStr for FuzzyPixel {
     type Err = ParseIntError;
 
@@ -59,3 +203,84 @@ impl FromStr for FuzzyPixel {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hsv_matches_across_brightness() {
+        // Orange at day and the same orange darker at night.
+        let day = Pixel {
+            blue: 40,
+            green: 140,
+            red: 240,
+        };
+        let night = Pixel {
+            blue: 20,
+            green: 70,
+            red: 120,
+        };
+        assert_eq!(day.to_hsv(), (30, 212, 240));
+
+        let orange = HsvPixel {
+            hue_min: 20,
+            hue_max: 40,
+            saturation_min: 150,
+            saturation_max: 255,
+            value_min: 80,
+            value_max: 255,
+        };
+        assert!(orange.matches(&day));
+        assert!(orange.matches(&night));
+        assert!(!orange.matches(&Pixel {
+            blue: 240,
+            green: 140,
+            red: 40,
+        }));
+
+        // Hue wraps around through red.
+        let red = HsvPixel {
+            hue_min: 340,
+            hue_max: 20,
+            ..orange
+        };
+        assert!(red.matches(&Pixel {
+            blue: 10,
+            green: 0,
+            red: 200,
+        }));
+        assert!(!red.matches(&day));
+    }
+
+    #[test]
+    fn lab_matches_similar_colors() {
+        let white = Pixel {
+            blue: 255,
+            green: 255,
+            red: 255,
+        };
+        let (lightness, a, b) = white.to_lab();
+        assert!((lightness - 100.0).abs() < 0.1);
+        assert!(a.abs() < 0.1 && b.abs() < 0.1);
+
+        let grass = LabPixel::around(
+            &Pixel {
+                blue: 30,
+                green: 120,
+                red: 60,
+            },
+            /*max_distance=*/ 10.0,
+        );
+        assert!(grass.matches(&Pixel {
+            blue: 35,
+            green: 125,
+            red: 62,
+        }));
+        assert!(!grass.matches(&Pixel {
+            blue: 120,
+            green: 60,
+            red: 30,
+        }));
+    }
+}
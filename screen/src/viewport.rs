@@ -0,0 +1,361 @@
+/// Find where the game is drawn within a screenshot, so that the screen bounds
+/// don't have to be measured by hand.
+///
+/// The inventory is the anchor: its background is a large area of a single
+/// color and it sits at a fixed offset from the bottom right of the game,
+/// which depends on the layout. From there we walk outwards until we reach
+/// the client's border, which is drawn as solid lines unlike anything in the
+/// game itself. The top is the exception, since the sky can be a solid color
+/// too. Instead we find the minimap, which is a fixed offset from the top
+/// right.
+use crate::blobs::{find_blobs, BlobRanking};
+use crate::layout::{find_inventory_panel, solve_layout};
+use crate::{fuzzy_pixels, Config, Frame, LayoutMode, Locations};
+use util::*;
+
+/// How much the channels of a border line may vary.
+const BORDER_TOLERANCE: i32 = 3;
+
+/// How far the right and bottom borders may be from where the inventory puts
+/// them.
+const MAX_ANCHOR_ERROR: i32 = 4;
+
+/// Are all 'len' pixels from 'start' in steps of 'step' the same color.
+fn is_uniform_line(frame: &impl Frame, start: Position, step: DeltaPosition, len: i32) -> bool {
+    let first = frame.get_pixel(&start);
+    (1..len).all(|i| {
+        let pixel = frame.get_pixel(&(start + step * i as f32));
+        (pixel.blue as i32 - first.blue as i32).abs() <= BORDER_TOLERANCE
+            && (pixel.green as i32 - first.green as i32).abs() <= BORDER_TOLERANCE
+            && (pixel.red as i32 - first.red as i32).abs() <= BORDER_TOLERANCE
+    })
+}
+
+fn in_frame(frame: &impl Frame, pos: &Position) -> bool {
+    pos.x >= 0 && pos.y >= 0 && pos.x < frame.width() as i32 && pos.y < frame.height() as i32
+}
+
+/// Walk from 'start' in 'direction' until we hit a uniform line parallel to
+/// 'along' of length 'len'. Returns the last position before the line, or the
+/// last position in the frame if there is no border.
+fn walk_to_border(
+    frame: &impl Frame,
+    start: Position,
+    direction: DeltaPosition,
+    along: DeltaPosition,
+    len: i32,
+) -> Position {
+    let mut pos = start;
+    loop {
+        let next = pos + direction;
+        if !in_frame(frame, &next) || is_uniform_line(frame, next, along, len) {
+            return pos;
+        }
+        pos = next;
+    }
+}
+
+/// Is there a border just past 'expected', the last position of the game in
+/// 'direction', give or take MAX_ANCHOR_ERROR. Only looks near 'expected'
+/// since the UI between the inventory and the edge has uniform lines of its
+/// own. If the game runs right up to the edge of the screenshot there is no
+/// border to go on.
+fn has_border_near(
+    frame: &impl Frame,
+    expected: Position,
+    direction: DeltaPosition,
+    along: DeltaPosition,
+    len: i32,
+) -> bool {
+    (-MAX_ANCHOR_ERROR..=MAX_ANCHOR_ERROR).any(|offset| {
+        let pos = expected + direction * (offset + 1) as f32;
+        !in_frame(frame, &pos) || is_uniform_line(frame, pos, along, len)
+    })
+}
+
+/// Is 'y' the first row of the game between 'left' and 'right'. The row above
+/// is the client's border, unless the game starts at the top of the
+/// screenshot. The game's first row is the minimap's frame, which is never
+/// uniform.
+fn is_top_edge(frame: &impl Frame, y: i32, left: i32, right: i32) -> bool {
+    let along = DeltaPosition { dx: 1, dy: 0 };
+    let len = right - left + 1;
+    (y == 0 || is_uniform_line(frame, Position { x: left, y: y - 1 }, along, len))
+        && !is_uniform_line(frame, Position { x: left, y }, along, len)
+}
+
+/// Find the top of the game, given where the rest of it is. Our player's dot
+/// is drawn in the middle of the minimap, which is 'minimap_middle_offset'
+/// from the top right. Other players' dots can line up with ours, so each dot
+/// is only trusted if there is a border where it puts the top.
+fn find_top(
+    frame: &impl Frame,
+    left: i32,
+    bottom_right: Position,
+    minimap_middle_offset: DeltaPosition,
+) -> Option<i32> {
+    let minimap_x = bottom_right.x + minimap_middle_offset.dx;
+    let search_top_left = Position {
+        x: minimap_x - MAX_ANCHOR_ERROR,
+        y: 0,
+    };
+    let dots = find_blobs(
+        frame,
+        &[fuzzy_pixels::minimap_dot_player_white()],
+        &search_top_left,
+        &DeltaPosition {
+            dx: 2 * MAX_ANCHOR_ERROR + 1,
+            dy: bottom_right.y,
+        },
+        /*min_area=*/ 4,
+        BlobRanking::ClosestTo(search_top_left),
+    );
+    dots.iter().find_map(|dot| {
+        let expected = dot.centroid.y - minimap_middle_offset.dy;
+        (expected - MAX_ANCHOR_ERROR..=expected + MAX_ANCHOR_ERROR)
+            .find(|y| *y >= 0 && is_top_edge(frame, *y, left, bottom_right.x))
+    })
+}
+
+/// Screen bounds and layout of the game within 'frame'. Only tries
+/// 'layout_mode' if given, otherwise every layout. Requires the inventory to
+/// be open. Returns None if the game can't be found.
+pub fn find_screen_config(frame: &impl Frame, layout_mode: Option<LayoutMode>) -> Option<Config> {
+    let inventory = find_inventory_panel(
+        frame,
+        &Position { x: 0, y: 0 },
+        &DeltaPosition {
+            dx: frame.width() as i32,
+            dy: frame.height() as i32,
+        },
+    )?;
+
+    let (width, height) = (inventory.dimensions.dx, inventory.dimensions.dy);
+    let left = walk_to_border(
+        frame,
+        inventory.top_left,
+        DeltaPosition { dx: -1, dy: 0 },
+        DeltaPosition { dx: 0, dy: 1 },
+        height,
+    );
+
+    // Each layout puts the inventory a different distance from the bottom
+    // right. Keep the layouts whose bottom right lines up with the border and
+    // whose minimap leads to the top border.
+    let layout_modes = match layout_mode {
+        Some(layout_mode) => vec![layout_mode],
+        None => LayoutMode::ALL.to_vec(),
+    };
+    let candidates: Vec<(LayoutMode, Position, Position)> = layout_modes
+        .into_iter()
+        .filter_map(|layout_mode| {
+            let bottom_right = inventory.top_left + layout_mode.inventory_to_bottom_right();
+            if !in_frame(frame, &bottom_right) {
+                println!(
+                    "{:?} puts the bottom right outside the screenshot, {:?}",
+                    layout_mode, bottom_right
+                );
+                return None;
+            }
+            let right_border = has_border_near(
+                frame,
+                Position {
+                    x: bottom_right.x,
+                    y: inventory.top_left.y,
+                },
+                DeltaPosition { dx: 1, dy: 0 },
+                DeltaPosition { dx: 0, dy: 1 },
+                height,
+            );
+            let bottom_border = has_border_near(
+                frame,
+                Position {
+                    x: inventory.top_left.x,
+                    y: bottom_right.y,
+                },
+                DeltaPosition { dx: 0, dy: 1 },
+                DeltaPosition { dx: 1, dy: 0 },
+                width,
+            );
+            if !right_border || !bottom_border {
+                println!(
+                    "No border where the inventory puts the bottom right for {:?}, {:?}",
+                    layout_mode, bottom_right
+                );
+                return None;
+            }
+            let top = match find_top(
+                frame,
+                left.x,
+                bottom_right,
+                layout_mode.minimap_middle_offset(),
+            ) {
+                Some(top) => top,
+                None => {
+                    println!("Unable to find the minimap for {:?}", layout_mode);
+                    return None;
+                }
+            };
+            let top_left = Position { x: left.x, y: top };
+            let dimensions = bottom_right - top_left + DeltaPosition { dx: 1, dy: 1 };
+            if !layout_mode.fits(dimensions) {
+                println!("{:?} doesn't fit a screen of {:?}", layout_mode, dimensions);
+                return None;
+            }
+            Some((layout_mode, top_left, bottom_right))
+        })
+        .collect();
+
+    // Without borders (the game fills the screenshot) several layouts can
+    // fit, so prefer the one which finds the rest of the UI where it expects.
+    let (layout_mode, top_left, bottom_right) = candidates
+        .iter()
+        .find(|(layout_mode, top_left, bottom_right)| {
+            let dimensions = *bottom_right - *top_left + DeltaPosition { dx: 1, dy: 1 };
+            let locations = Locations::with_layout_mode(*top_left, dimensions, *layout_mode);
+            solve_layout(frame, &locations).mismatches().is_empty()
+        })
+        .or_else(|| candidates.first())
+        .copied()?;
+    Some(Config {
+        screen: None,
+        screen_top_left: Some(top_left),
+        screen_bottom_right: Some(bottom_right),
+        layout: Some(layout_mode.name().to_string()),
+        calibrate_colors: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::noise;
+    use crate::types::*;
+    use crate::OwnedFrame;
+
+    /// A window of 'width' x 'height' with the game drawn between 'top_left'
+    /// and 'bottom_right' for 'layout_mode'. The inventory is open and empty,
+    /// and our player's dot is in the middle of the minimap.
+    fn draw_window(
+        width: usize,
+        height: usize,
+        top_left: Position,
+        bottom_right: Position,
+        layout_mode: LayoutMode,
+    ) -> OwnedFrame {
+        let mut frame = OwnedFrame::blank(width, height);
+        let inventory_top_left = bottom_right - layout_mode.inventory_to_bottom_right();
+        let inventory_bottom_right = inventory_top_left + DeltaPosition { dx: 190, dy: 261 };
+        let minimap_middle = Position {
+            x: bottom_right.x,
+            y: top_left.y,
+        } + layout_mode.minimap_middle_offset();
+
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let pixel =
+                    if x < top_left.x || y < top_left.y || x > bottom_right.x || y > bottom_right.y
+                    {
+                        // Client border.
+                        Pixel {
+                            blue: 30,
+                            green: 30,
+                            red: 30,
+                        }
+                    } else if x >= inventory_top_left.x
+                        && y >= inventory_top_left.y
+                        && x <= inventory_bottom_right.x
+                        && y <= inventory_bottom_right.y
+                    {
+                        Pixel {
+                            blue: 42,
+                            green: 53,
+                            red: 61,
+                        }
+                    } else if (x - minimap_middle.x).abs() <= 1 && (y - minimap_middle.y).abs() <= 1
+                    {
+                        Pixel {
+                            blue: 255,
+                            green: 255,
+                            red: 255,
+                        }
+                    } else {
+                        // Game content, which is never uniform.
+                        let value = 100 + noise(x, y) % 150;
+                        Pixel {
+                            blue: value,
+                            green: value / 2,
+                            red: 255 - value,
+                        }
+                    };
+                frame.recolor_pixel(&Position { x, y }, &pixel);
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn find_screen_in_window() {
+        let top_left = Position { x: 20, y: 30 };
+        let bottom_right = Position { x: 1019, y: 629 };
+        let frame = draw_window(
+            1100,
+            700,
+            top_left,
+            bottom_right,
+            LayoutMode::ResizableModernOneRow,
+        );
+
+        let config = find_screen_config(&frame, /*layout_mode=*/ None).unwrap();
+        assert_eq!(config.screen_top_left, Some(top_left));
+        assert_eq!(config.screen_bottom_right, Some(bottom_right));
+        assert_eq!(config.layout.as_deref(), Some("resizable-modern"));
+    }
+
+    #[test]
+    fn find_fixed_classic_screen() {
+        let top_left = Position { x: 20, y: 30 };
+        let bottom_right =
+            top_left + Locations::FIXED_CLASSIC_DIMENSIONS - DeltaPosition { dx: 1, dy: 1 };
+        let frame = draw_window(1000, 700, top_left, bottom_right, LayoutMode::FixedClassic);
+
+        let config = find_screen_config(&frame, /*layout_mode=*/ None).unwrap();
+        assert_eq!(config.screen_top_left, Some(top_left));
+        assert_eq!(config.screen_bottom_right, Some(bottom_right));
+        assert_eq!(config.layout.as_deref(), Some("fixed-classic"));
+
+        // The inventory is too far from the border for resizable modern.
+        assert!(find_screen_config(&frame, Some(LayoutMode::ResizableModernOneRow)).is_none());
+    }
+
+    #[test]
+    fn find_screen_with_black_sky() {
+        let top_left = Position { x: 20, y: 30 };
+        let bottom_right = Position { x: 1019, y: 629 };
+        let mut frame = draw_window(
+            1100,
+            700,
+            top_left,
+            bottom_right,
+            LayoutMode::ResizableModernOneRow,
+        );
+        // Night sky above the horizon, below the minimap and above the
+        // inventory.
+        for y in 220..260 {
+            for x in top_left.x..=bottom_right.x {
+                frame.recolor_pixel(
+                    &Position { x, y },
+                    &Pixel {
+                        blue: 0,
+                        green: 0,
+                        red: 0,
+                    },
+                );
+            }
+        }
+
+        let config = find_screen_config(&frame, /*layout_mode=*/ None).unwrap();
+        assert_eq!(config.screen_top_left, Some(top_left));
+        assert_eq!(config.screen_bottom_right, Some(bottom_right));
+    }
+}
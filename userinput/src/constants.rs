@@ -23,3 +23,11 @@ pub const ZERO_LOC: Location = Location {
 /// hitting the edge of the screen well which can cause a failure.) To avoid an
 /// infinite loop though we define a timeout here.
 pub const MOVE_TO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Holding pitch up/down for this long moves the camera across its entire
+/// range, so it can be used to get to a known pitch.
+pub const FULL_PITCH_TIME: Duration = Duration::from_millis(2000);
+
+/// Number of scroll wheel clicks to go from fully zoomed in to fully zoomed
+/// out.
+pub const MAX_ZOOM_STEPS: i32 = 30;
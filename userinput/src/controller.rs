@@ -243,6 +243,10 @@ pub struct InputBot {
 const MIN_CLICK_WAIT: Duration = Duration::from_millis(100);
 const MAX_CLICK_WAIT: Duration = Duration::from_millis(150);
 
+/// Time to wait between scroll wheel clicks.
+const MIN_SCROLL_WAIT: Duration = Duration::from_millis(30);
+const MAX_SCROLL_WAIT: Duration = Duration::from_millis(60);
+
 impl InputBot {
     pub fn new(config: crate::Config) -> InputBot {
         InputBot {
@@ -275,18 +279,22 @@ impl InputBot {
     // think that holding down a key actually sends lots of short presses.
     // Pressing a releasing causes lots of start and stop, perhaps could try to
     // press and do a super short release.
-    fn pan(&mut self, degrees: f32, key: &Key) {
-        const FULL_ROTATION_TIME: Duration = Duration::from_millis(3755);
-
+    fn hold_key(&mut self, key: &Key, duration: Duration) {
         self.keyboard.press(key).unwrap();
         self.keyboard.synchronize().unwrap();
 
-        sleep(FULL_ROTATION_TIME.mul_f32(degrees / 360.0));
+        sleep(duration);
 
         self.keyboard.release(key).unwrap();
         self.keyboard.synchronize().unwrap();
     }
 
+    fn pan(&mut self, degrees: f32, key: &Key) {
+        const FULL_ROTATION_TIME: Duration = Duration::from_millis(3755);
+
+        self.hold_key(key, FULL_ROTATION_TIME.mul_f32(degrees / 360.0));
+    }
+
     // Pressing the mouse buttons is basically stateless, but makes sense to
     // put it here for simplicity.
     fn click_mouse(&self, button: &inputbot::MouseButton) {
@@ -307,6 +315,44 @@ impl InputBot {
         self.pan(degrees, &Key::D);
     }
 
+    /// Tilt the camera towards looking straight down (up) or towards the
+    /// horizon (down). Holding for FULL_PITCH_TIME covers the entire range.
+    pub fn pitch_up(&mut self, duration: Duration) {
+        self.hold_key(&Key::W, duration);
+    }
+    pub fn pitch_down(&mut self, duration: Duration) {
+        self.hold_key(&Key::S, duration);
+    }
+
+    /// Zoom by scrolling the mouse wheel. The mouse must be over the open
+    /// screen, otherwise we scroll whatever interface is under it. Zooming
+    /// out by MAX_ZOOM_STEPS covers the entire range.
+    pub fn zoom_in(&self, steps: i32) {
+        self.scroll_up(steps);
+    }
+    pub fn zoom_out(&self, steps: i32) {
+        self.scroll_down(steps);
+    }
+
+    /// Scroll whatever is under the mouse, e.g. the bank. On X11 a negative
+    /// scroll_ver presses button 4 (wheel up) and a positive one button 5.
+    pub fn scroll_up(&self, steps: i32) {
+        for _ in 0..steps {
+            inputbot::MouseWheel::scroll_ver(-1);
+            sleep(Self::scroll_wait());
+        }
+    }
+    pub fn scroll_down(&self, steps: i32) {
+        for _ in 0..steps {
+            inputbot::MouseWheel::scroll_ver(1);
+            sleep(Self::scroll_wait());
+        }
+    }
+    fn scroll_wait() -> Duration {
+        let mut rng = rand::thread_rng();
+        Uniform::new(MIN_SCROLL_WAIT, MAX_SCROLL_WAIT).sample(&mut rng)
+    }
+
     /// Mouse interactions.
     pub fn left_click(&self) {
         self.click_mouse(&inputbot::MouseButton::LeftButton);
@@ -314,6 +360,19 @@ impl InputBot {
     pub fn right_click(&self) {
         self.click_mouse(&inputbot::MouseButton::RightButton);
     }
+    /// Hold the left button while moving from 'from' to 'to', e.g. to pan
+    /// the worldmap.
+    pub fn left_drag(&self, from: &Position, to: &Position) {
+        let mut rng = rand::thread_rng();
+        let duration = Uniform::new(MIN_CLICK_WAIT, MAX_CLICK_WAIT);
+
+        self.move_to(from);
+        inputbot::MouseButton::LeftButton.press();
+        sleep(duration.sample(&mut rng));
+        self.move_to(to);
+        sleep(duration.sample(&mut rng));
+        inputbot::MouseButton::LeftButton.release();
+    }
     pub fn try_to_move_to(&self, dst: &Position, timeout: Duration) -> bool {
         let time = std::time::Instant::now();
         while time.elapsed() < timeout {
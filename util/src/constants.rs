@@ -11,3 +11,6 @@ use std::time::Duration;
 /// I am going with 1/3 of a second since the downside of missing a redraw is
 /// worse than the delay of an extra hundred ms.
 pub const REDRAW_TIME: Duration = Duration::from_millis(333);
+
+/// The game updates the world, e.g. moving the player, once per tick.
+pub const GAME_TICK: Duration = Duration::from_millis(600);